@@ -1,9 +1,9 @@
 use crate::auth::authorizer::Authorizer;
 use crate::auth::credential::UserCredential;
+use crate::network::NetworkConfig;
 use anyhow::{Context, Result};
 use minecraft_msa_auth::MinecraftAuthorizationFlow;
 use oauth2::basic::BasicClient;
-use oauth2::reqwest::Client;
 use oauth2::{
     AuthUrl, ClientId, DeviceAuthorizationUrl, Scope, StandardDeviceAuthorizationResponse,
     TokenResponse, TokenUrl,
@@ -23,11 +23,12 @@ struct MinecraftProfileResponse {
 pub struct MicrosoftAuthorizer<F: Fn(String, String)> {
     pub client_id: String,
     pub verification_handler: F,
+    pub network: NetworkConfig,
 }
 
 impl<F: Fn(String, String)> Authorizer for MicrosoftAuthorizer<F> {
     async fn authorize(&self) -> Result<UserCredential> {
-        let request_client = Client::new();
+        let request_client = self.network.build_oauth_client()?;
         let client = BasicClient::new(ClientId::new(self.client_id.clone()))
             .set_auth_uri(AuthUrl::new(MSA_AUTHORIZE_URL.to_string())?)
             .set_token_uri(TokenUrl::new(MSA_TOKEN_URL.to_string())?)
@@ -52,11 +53,13 @@ impl<F: Fn(String, String)> Authorizer for MicrosoftAuthorizer<F> {
             .request_async(&request_client, tokio::time::sleep, None)
             .await?;
 
-        let mc_flow = MinecraftAuthorizationFlow::new(Client::new());
+        let mc_flow = MinecraftAuthorizationFlow::new(request_client);
         let mc_token = mc_flow
             .exchange_microsoft_token(token.access_token().secret())
             .await?;
-        let profile = reqwest::Client::new()
+        let profile = self
+            .network
+            .build_client()?
             .get(MINECRAFT_PROFILE_URL)
             .bearer_auth(mc_token.access_token().as_ref())
             .send()
@@ -70,6 +73,8 @@ impl<F: Fn(String, String)> Authorizer for MicrosoftAuthorizer<F> {
             username: profile.name,
             uuid: profile.id,
             access_token: mc_token.access_token().clone().into_inner(),
+            // The profile endpoint only answers for accounts that own the game.
+            demo: false,
         })
     }
 
@@ -20,6 +20,7 @@ impl Authorizer for OfflineAuthorizer {
             username: self.username.clone(),
             uuid: Uuid::from_bytes(buffer.into()).to_string(),
             access_token: "".to_string(),
+            demo: false,
         })
     }
 
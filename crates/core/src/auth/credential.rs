@@ -3,4 +3,6 @@ pub struct UserCredential {
     pub username: String,
     pub uuid: String,
     pub access_token: String,
+    /// Accounts that don't own the game and launch in demo mode.
+    pub demo: bool,
 }
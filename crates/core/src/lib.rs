@@ -3,6 +3,7 @@ pub mod consts;
 pub mod hash;
 pub mod launcher;
 pub mod minecraft;
+pub mod network;
 pub mod runtime;
 pub mod storage;
 pub mod version;
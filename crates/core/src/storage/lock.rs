@@ -0,0 +1,104 @@
+use std::{
+    fs::{File, OpenOptions, TryLockError},
+    io::{Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, bail};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageLockMode {
+    Shared,
+    Exclusive,
+}
+
+#[derive(Debug)]
+pub struct StorageLock {
+    path: PathBuf,
+    mode: StorageLockMode,
+    file: File,
+}
+
+impl StorageLock {
+    pub fn try_acquire(path: impl Into<PathBuf>, mode: StorageLockMode) -> Result<Self> {
+        let path = path.into();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .read(true)
+            .write(true)
+            .open(&path)
+            .with_context(|| format!("open lock file failed: {}", path.display()))?;
+        let locked = match mode {
+            StorageLockMode::Shared => file.try_lock_shared(),
+            StorageLockMode::Exclusive => file.try_lock(),
+        };
+
+        match locked {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                bail!(
+                    "{} is locked by another process{}",
+                    path.display(),
+                    lock_holder(&mut file)
+                        .map(|pid| format!(" (pid {pid})"))
+                        .unwrap_or_default()
+                )
+            }
+            Err(TryLockError::Error(error)) => {
+                return Err(error)
+                    .with_context(|| format!("acquire lock failed: {}", path.display()));
+            }
+        }
+
+        if mode == StorageLockMode::Exclusive {
+            file.set_len(0)?;
+            file.seek(SeekFrom::Start(0))?;
+            write!(file, "{}", std::process::id())?;
+        }
+
+        Ok(Self { path, mode, file })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn mode(&self) -> StorageLockMode {
+        self.mode
+    }
+}
+
+impl Drop for StorageLock {
+    fn drop(&mut self) {
+        let _ = self.file.unlock();
+    }
+}
+
+fn lock_holder(file: &mut File) -> Option<u32> {
+    let mut contents = String::new();
+    file.read_to_string(&mut contents).ok()?;
+    contents.trim().parse().ok()
+}
+
+#[test]
+fn test_storage_lock_conflict() {
+    let path = std::env::temp_dir().join(format!(
+        "elemental-storage-lock-{}.lock",
+        std::process::id()
+    ));
+    let exclusive = StorageLock::try_acquire(&path, StorageLockMode::Exclusive).unwrap();
+    let error = StorageLock::try_acquire(&path, StorageLockMode::Shared).unwrap_err();
+    assert!(error.to_string().contains("locked by another process"));
+    drop(exclusive);
+
+    let shared = StorageLock::try_acquire(&path, StorageLockMode::Shared).unwrap();
+    let _other = StorageLock::try_acquire(&path, StorageLockMode::Shared).unwrap();
+    assert!(StorageLock::try_acquire(&path, StorageLockMode::Exclusive).is_err());
+    drop(shared);
+    let _ = std::fs::remove_file(path);
+}
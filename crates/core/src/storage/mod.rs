@@ -1,4 +1,7 @@
 pub mod layout;
+pub mod lock;
 pub mod store;
 
+pub use elemental_shared::atomic::{write_atomic, write_atomic_blocking};
+pub use lock::{StorageLock, StorageLockMode};
 pub use store::Storage;
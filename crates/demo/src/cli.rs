@@ -23,6 +23,8 @@ pub struct Cli {
 enum DemoCliCommand {
     #[command(name = "list", about = "List local prepared instances")]
     List,
+    #[command(name = "gc", about = "Remove shared files no local instance references")]
+    Gc(GcArgs),
     Vanilla(VanillaArgs),
     Fabric(LoaderArgs),
     #[command(name = "legacyfabric", alias = "legacy-fabric")]
@@ -38,6 +40,12 @@ enum DemoCliCommand {
     NeoForge(LoaderArgs),
 }
 
+#[derive(Clone, Debug, Default, Args)]
+struct GcArgs {
+    #[arg(long, help = "Only report what would be removed")]
+    dry_run: bool,
+}
+
 #[derive(Clone, Debug, Default, Args)]
 struct VanillaArgs {
     #[arg(long)]
@@ -95,6 +103,10 @@ impl Cli {
             .unwrap_or(DemoCliCommand::Fabric(LoaderArgs::default()))
         {
             DemoCliCommand::List => DemoCommand::ListInstances { storage_root },
+            DemoCliCommand::Gc(arguments) => DemoCommand::CollectGarbage {
+                storage_root,
+                dry_run: arguments.dry_run,
+            },
             DemoCliCommand::Vanilla(arguments) => DemoCommand::Launch(build_vanilla_config(
                 local_only,
                 common_config_from_vanilla_args(storage_root, arguments),
@@ -6,8 +6,9 @@ use elemental::{
         auth::authorizers::offline::OfflineAuthorizer, launcher::command::LaunchCommand,
         runtime::distribution::Distribution, storage::Storage,
     },
-    driver::families::version_json::{
-        BaseInstanceLayout, BaseRootLayout, VersionJsonGameStorageExt,
+    driver::{
+        families::version_json::{BaseInstanceLayout, BaseRootLayout, VersionJsonGameStorageExt},
+        gc::{SharedArtifactKind, StorageGcMode},
     },
     launcher::{
         DriverSpec, LaunchOptions, Launcher, LoaderSpec, PrepareInstanceRequest, VanillaSpec,
@@ -24,13 +25,17 @@ pub async fn run(command: DemoCommand) -> Result<()> {
     match command {
         DemoCommand::Launch(config) => run_launch(config).await,
         DemoCommand::ListInstances { storage_root } => run_list_instances(storage_root).await,
+        DemoCommand::CollectGarbage {
+            storage_root,
+            dry_run,
+        } => run_collect_garbage(storage_root, dry_run).await,
     }
 }
 
 async fn run_launch(config: DemoConfig) -> Result<()> {
     let launcher = Launcher::builder()
         .storage_root(config.storage_root.clone())
-        .build();
+        .build()?;
     let launch_options: LaunchOptions = build_launch_config(&config);
     let driver_spec = to_driver_spec(&config)?;
 
@@ -54,6 +59,9 @@ async fn run_launch(config: DemoConfig) -> Result<()> {
         }))
         .await?
     };
+    if prepared.is_stale() {
+        println!("Origin unreachable, prepared from cached metadata.");
+    }
 
     let command_result = launcher
         .build_launch_command(&prepared, offline_authorizer(), &launch_options)
@@ -69,7 +77,7 @@ async fn run_launch(config: DemoConfig) -> Result<()> {
 }
 
 async fn run_list_instances(storage_root: std::path::PathBuf) -> Result<()> {
-    let launcher = Launcher::builder().storage_root(storage_root).build();
+    let launcher = Launcher::builder().storage_root(storage_root).build()?;
     let instances = launcher.inspect_instances().await?;
 
     if instances.is_empty() {
@@ -77,16 +85,69 @@ async fn run_list_instances(storage_root: std::path::PathBuf) -> Result<()> {
         return Ok(());
     }
 
+    let usage = launcher.disk_usage().await?;
     println!("Found {} local instance(s):", instances.len());
     for instance in instances {
+        let (private_bytes, shared_bytes) = usage
+            .instance(&instance.instance_name)
+            .map(|usage| (usage.private_bytes, usage.shared_bytes))
+            .unwrap_or_default();
         println!(
-            "- {} | driver={} | root={}",
+            "- {} | driver={} | root={} | private={} bytes | shared={} bytes",
             instance.instance_name,
             instance.driver.driver.id,
-            instance.instance_root.display()
+            instance.instance_root.display(),
+            private_bytes,
+            shared_bytes
+        );
+    }
+    println!(
+        "Shared artifacts: {} file(s), {} bytes",
+        usage.shared.len(),
+        usage.shared_bytes
+    );
+    println!(
+        "Runtimes: {} bytes",
+        usage.shared_bytes_of(SharedArtifactKind::Runtime)
+    );
+
+    Ok(())
+}
+
+async fn run_collect_garbage(storage_root: std::path::PathBuf, dry_run: bool) -> Result<()> {
+    let launcher = Launcher::builder().storage_root(storage_root).build()?;
+    let mode = if dry_run {
+        StorageGcMode::DryRun
+    } else {
+        StorageGcMode::Delete
+    };
+    let report = launcher.collect_garbage(mode).await?;
+
+    println!(
+        "Scanned {} shared file(s) ({} bytes) referenced by {} instance(s).",
+        report.scanned_files,
+        report.scanned_bytes,
+        report.instances.len()
+    );
+    for (label, kind) in [
+        ("libraries", SharedArtifactKind::Library),
+        ("asset objects", SharedArtifactKind::AssetObject),
+        ("asset indexes", SharedArtifactKind::AssetIndex),
+        ("log configs", SharedArtifactKind::AssetLogConfig),
+    ] {
+        println!(
+            "- {label}: {} orphaned file(s), {} bytes",
+            report.orphaned_count_of(kind),
+            report.orphaned_bytes_of(kind)
         );
     }
 
+    if report.is_dry_run() {
+        println!("Dry run: {} bytes can be reclaimed.", report.orphaned_bytes);
+    } else {
+        println!("Reclaimed {} bytes.", report.deleted_bytes);
+    }
+
     Ok(())
 }
 
@@ -34,7 +34,13 @@ pub struct DemoConfig {
 #[derive(Clone, Debug)]
 pub enum DemoCommand {
     Launch(DemoConfig),
-    ListInstances { storage_root: PathBuf },
+    ListInstances {
+        storage_root: PathBuf,
+    },
+    CollectGarbage {
+        storage_root: PathBuf,
+        dry_run: bool,
+    },
 }
 
 impl DemoDriver {
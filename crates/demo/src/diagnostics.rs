@@ -19,6 +19,7 @@ use elemental::{
 
 pub struct VersionDiagnostics {
     pub version_root: PathBuf,
+    pub game_directory: PathBuf,
     pub metadata_path: PathBuf,
     pub version_jar_path: PathBuf,
     pub natives_root: PathBuf,
@@ -61,6 +62,7 @@ where
 {
     let metadata = version.metadata()?;
     let version_root = version.path.clone();
+    let game_directory = version.try_get_resource(VersionJsonInstanceResource::GameDirectory)?;
     let metadata_path = version.try_get_resource(VersionJsonInstanceResource::Metadata)?;
     let version_jar_path = version.try_get_resource(VersionJsonInstanceResource::Jar)?;
     let natives_root = version.try_get_resource(VersionJsonInstanceResource::Natives)?;
@@ -69,6 +71,7 @@ where
 
     Ok(VersionDiagnostics {
         version_root,
+        game_directory,
         metadata_path,
         version_jar_path,
         natives_root,
@@ -137,6 +140,10 @@ pub fn print_launch_diagnostics(diagnostics_input: &LaunchDiagnostics<'_>) {
         "version root: {}",
         diagnostics_input.diagnostics.version_root.display()
     );
+    println!(
+        "game directory: {}",
+        diagnostics_input.diagnostics.game_directory.display()
+    );
     println!(
         "metadata path: {}",
         diagnostics_input.diagnostics.metadata_path.display()
@@ -0,0 +1,610 @@
+use std::{
+    fs::{self, File},
+    io::{self, Read},
+    path::{Component, Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result, anyhow, bail};
+use elemental_core::storage::{Storage, layout::Layoutable, write_atomic_blocking};
+use serde::{Deserialize, Serialize};
+use zip::{CompressionMethod, ZipArchive, ZipWriter, write::SimpleFileOptions};
+
+use crate::{
+    families::version_json::{
+        VersionJsonInstanceLayout, VersionJsonInstanceResource, VersionJsonRootLayout,
+    },
+    references::{SharedArtifactKind, SharedReferenceCollector, SharedReferences},
+};
+
+pub const INSTANCE_ARCHIVE_FORMAT_VERSION: u32 = 1;
+
+const MANIFEST_ENTRY: &str = "manifest.json";
+const INSTANCE_ENTRY_PREFIX: &str = "instance";
+const SHARED_ENTRY_PREFIX: &str = "shared";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstanceArchiveDriver {
+    pub id: String,
+    pub game_version: String,
+    pub loader_version: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstanceArchiveManifest {
+    pub format_version: u32,
+    pub instance_name: String,
+    pub driver: InstanceArchiveDriver,
+    pub resources: Vec<String>,
+    pub shared_artifacts: bool,
+    pub exported_at_unix_ms: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstanceArchiveOptions {
+    resources: Vec<VersionJsonInstanceResource>,
+    shared_artifacts: bool,
+}
+
+impl Default for InstanceArchiveOptions {
+    fn default() -> Self {
+        Self {
+            resources: vec![
+                VersionJsonInstanceResource::Saves,
+                VersionJsonInstanceResource::Mods,
+                VersionJsonInstanceResource::Configs,
+                VersionJsonInstanceResource::ResourcePacks,
+                VersionJsonInstanceResource::ShaderPacks,
+                VersionJsonInstanceResource::Jarmods(None),
+            ],
+            shared_artifacts: false,
+        }
+    }
+}
+
+impl InstanceArchiveOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn include(mut self, resource: VersionJsonInstanceResource) -> Self {
+        if !self.resources.contains(&resource) {
+            self.resources.push(resource);
+        }
+        self
+    }
+
+    pub fn exclude(mut self, resource: VersionJsonInstanceResource) -> Self {
+        self.resources.retain(|included| included != &resource);
+        self
+    }
+
+    pub fn set_shared_artifacts(mut self, shared_artifacts: bool) -> Self {
+        self.shared_artifacts = shared_artifacts;
+        self
+    }
+
+    pub fn resources(&self) -> &[VersionJsonInstanceResource] {
+        &self.resources
+    }
+
+    pub fn shared_artifacts(&self) -> bool {
+        self.shared_artifacts
+    }
+
+    pub fn includes(&self, resource: &VersionJsonInstanceResource) -> bool {
+        is_required_resource(resource) || self.resources.contains(resource)
+    }
+
+    fn archived_resources(&self) -> Result<Vec<VersionJsonInstanceResource>> {
+        let mut resources = vec![
+            VersionJsonInstanceResource::Metadata,
+            VersionJsonInstanceResource::Elemental(None),
+        ];
+        for resource in &self.resources {
+            if resource_key(resource).is_none() {
+                bail!("instance resource {resource:?} can't be archived");
+            }
+            if !resources.contains(resource) {
+                resources.push(resource.clone());
+            }
+        }
+
+        Ok(resources)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstanceArchiveReport {
+    pub archive: PathBuf,
+    pub manifest: InstanceArchiveManifest,
+    pub files: usize,
+    pub bytes: u64,
+    pub shared_files: usize,
+    pub shared_bytes: u64,
+}
+
+pub fn export_instance_archive<L, VL>(
+    instance: &Storage<VL, Storage<L>>,
+    driver: InstanceArchiveDriver,
+    destination: impl AsRef<Path>,
+    options: &InstanceArchiveOptions,
+) -> Result<InstanceArchiveReport>
+where
+    L: VersionJsonRootLayout,
+    VL: VersionJsonInstanceLayout,
+{
+    let destination = destination.as_ref();
+    let instance_name = instance
+        .name()
+        .ok_or_else(|| anyhow!("instance has no name: {}", instance.path.display()))?;
+    let metadata_path = instance.try_get_resource(VersionJsonInstanceResource::Metadata)?;
+    if !metadata_path.is_file() {
+        bail!(
+            "instance '{instance_name}' has no metadata: {}",
+            metadata_path.display()
+        );
+    }
+
+    let resources = options.archived_resources()?;
+    let state_root = instance.try_get_resource(VersionJsonInstanceResource::Elemental(None))?;
+    let manifest = InstanceArchiveManifest {
+        format_version: INSTANCE_ARCHIVE_FORMAT_VERSION,
+        instance_name,
+        driver,
+        resources: resources
+            .iter()
+            .filter_map(resource_key)
+            .map(ToOwned::to_owned)
+            .collect(),
+        shared_artifacts: options.shared_artifacts,
+        exported_at_unix_ms: SystemTime::now()
+            .duration_since(UNIX_EPOCH)?
+            .as_millis()
+            .try_into()?,
+    };
+    let mut report = InstanceArchiveReport {
+        archive: destination.to_path_buf(),
+        manifest: manifest.clone(),
+        files: 0,
+        bytes: 0,
+        shared_files: 0,
+        shared_bytes: 0,
+    };
+
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let staging = staging_path(destination)?;
+    let mut writer = ZipWriter::new(
+        File::create(&staging)
+            .with_context(|| format!("create archive failed: {}", staging.display()))?,
+    );
+
+    let result = (|| -> Result<()> {
+        writer.start_file(MANIFEST_ENTRY, entry_options(0))?;
+        serde_json::to_writer_pretty(&mut writer, &manifest)?;
+
+        for resource in &resources {
+            let Some(key) = resource_key(resource) else {
+                continue;
+            };
+            let path = instance.try_get_resource(resource.clone())?;
+            let entry = format!("{INSTANCE_ENTRY_PREFIX}/{key}");
+            if path.is_file() {
+                report.bytes += write_entry(&mut writer, &entry, &path)?;
+                report.files += 1;
+                continue;
+            }
+
+            let mut files = Vec::new();
+            collect_archive_files(&path, &state_root, &mut files)?;
+            files.sort();
+            for file in files {
+                let name = format!("{entry}/{}", relative_entry_name(&path, &file)?);
+                report.bytes += write_entry(&mut writer, &name, &file)?;
+                report.files += 1;
+            }
+        }
+
+        if options.shared_artifacts {
+            let mut references = SharedReferences::default();
+            SharedReferenceCollector::default().collect_instance(&mut references, instance)?;
+            let mut shared = references
+                .paths()
+                .filter(|(path, _)| path.is_file())
+                .map(|(path, kind)| (path.clone(), kind))
+                .collect::<Vec<(PathBuf, SharedArtifactKind)>>();
+            shared.sort_by(|left, right| left.0.cmp(&right.0));
+
+            for (path, kind) in shared {
+                let (Some(key), Some(resource)) = (shared_kind_key(kind), kind.root_resource())
+                else {
+                    continue;
+                };
+                let root = instance.parent.try_get_resource(resource)?;
+                let name = format!(
+                    "{SHARED_ENTRY_PREFIX}/{key}/{}",
+                    relative_entry_name(&root, &path)?
+                );
+                report.shared_bytes += write_entry(&mut writer, &name, &path)?;
+                report.shared_files += 1;
+            }
+        }
+
+        writer.finish()?.sync_all()?;
+        Ok(())
+    })();
+
+    if let Err(error) = result {
+        let _ = fs::remove_file(&staging);
+        return Err(error)
+            .with_context(|| format!("export instance archive failed: {}", destination.display()));
+    }
+    fs::rename(&staging, destination)
+        .with_context(|| format!("move archive failed: {}", destination.display()))?;
+
+    Ok(report)
+}
+
+pub fn read_instance_archive_manifest(
+    archive: impl AsRef<Path>,
+) -> Result<InstanceArchiveManifest> {
+    let archive = archive.as_ref();
+    let mut zip = open_archive(archive)?;
+    read_manifest(&mut zip)
+        .with_context(|| format!("read archive manifest failed: {}", archive.display()))
+}
+
+pub fn import_instance_archive<L, VL>(
+    archive: impl AsRef<Path>,
+    instance: &Storage<VL, Storage<L>>,
+    options: &InstanceArchiveOptions,
+) -> Result<InstanceArchiveReport>
+where
+    L: VersionJsonRootLayout,
+    VL: VersionJsonInstanceLayout,
+{
+    let archive = archive.as_ref();
+    let mut zip = open_archive(archive)?;
+    let manifest = read_manifest(&mut zip)
+        .with_context(|| format!("read archive manifest failed: {}", archive.display()))?;
+    let mut report = InstanceArchiveReport {
+        archive: archive.to_path_buf(),
+        manifest,
+        files: 0,
+        bytes: 0,
+        shared_files: 0,
+        shared_bytes: 0,
+    };
+
+    for index in 0..zip.len() {
+        let mut entry = zip.by_index(index)?;
+        if entry.is_dir() {
+            continue;
+        }
+        let Some(name) = entry.enclosed_name() else {
+            bail!("archive entry escapes its root: {}", entry.name());
+        };
+        let mut components = name.components().filter_map(|component| match component {
+            Component::Normal(part) => Some(part.to_string_lossy().to_string()),
+            _ => None,
+        });
+
+        match (components.next().as_deref(), components.next()) {
+            (Some(INSTANCE_ENTRY_PREFIX), Some(key)) => {
+                let Some(resource) = resource_from_key(&key) else {
+                    continue;
+                };
+                if !options.includes(&resource) {
+                    continue;
+                }
+
+                let root = instance.try_get_resource(resource)?;
+                let target = components.fold(root, |path, part| path.join(part));
+                report.bytes += extract_entry(&mut entry, &target)?;
+                report.files += 1;
+            }
+            (Some(SHARED_ENTRY_PREFIX), Some(key)) => {
+                let Some(kind) = shared_kind_from_key(&key) else {
+                    continue;
+                };
+                if !options.shared_artifacts {
+                    continue;
+                }
+
+                let Some(resource) = kind.root_resource() else {
+                    continue;
+                };
+                let root = instance.parent.try_get_resource(resource)?;
+                let target = components.fold(root, |path, part| path.join(part));
+                let mut contents = Vec::new();
+                entry.read_to_end(&mut contents)?;
+                // Library paths aren't content addressed, so only an identical copy is left alone.
+                if is_same_file_contents(&target, &contents)? {
+                    continue;
+                }
+                write_atomic_blocking(&target, &contents)?;
+                report.shared_bytes += contents.len() as u64;
+                report.shared_files += 1;
+            }
+            _ => {}
+        }
+    }
+
+    Ok(report)
+}
+
+fn is_same_file_contents(path: &Path, contents: &[u8]) -> Result<bool> {
+    match fs::metadata(path) {
+        Ok(metadata) if metadata.len() != contents.len() as u64 => return Ok(false),
+        Ok(_) => {}
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(error) => {
+            return Err(error).with_context(|| format!("stat failed: {}", path.display()));
+        }
+    }
+
+    let existing = fs::read(path).with_context(|| format!("read failed: {}", path.display()))?;
+    Ok(existing == contents)
+}
+
+fn is_required_resource(resource: &VersionJsonInstanceResource) -> bool {
+    matches!(
+        resource,
+        VersionJsonInstanceResource::Metadata | VersionJsonInstanceResource::Elemental(None)
+    )
+}
+
+fn resource_key(resource: &VersionJsonInstanceResource) -> Option<&'static str> {
+    Some(match resource {
+        VersionJsonInstanceResource::Metadata => "metadata",
+        VersionJsonInstanceResource::Jar => "jar",
+        VersionJsonInstanceResource::Natives => "natives",
+        VersionJsonInstanceResource::Logs => "logs",
+        VersionJsonInstanceResource::Configs => "config",
+        VersionJsonInstanceResource::ShaderPacks => "shaderpacks",
+        VersionJsonInstanceResource::ResourcePacks => "resourcepacks",
+        VersionJsonInstanceResource::Saves => "saves",
+        VersionJsonInstanceResource::Mods => "mods",
+        VersionJsonInstanceResource::Jarmods(None) => "jarmods",
+        VersionJsonInstanceResource::Elemental(None) => "elemental",
+        VersionJsonInstanceResource::GameDirectory
+        | VersionJsonInstanceResource::PatchedJar
+        | VersionJsonInstanceResource::Resources
+        | VersionJsonInstanceResource::Jarmods(Some(_))
+        | VersionJsonInstanceResource::Elemental(Some(_)) => return None,
+    })
+}
+
+fn resource_from_key(key: &str) -> Option<VersionJsonInstanceResource> {
+    Some(match key {
+        "metadata" => VersionJsonInstanceResource::Metadata,
+        "jar" => VersionJsonInstanceResource::Jar,
+        "natives" => VersionJsonInstanceResource::Natives,
+        "logs" => VersionJsonInstanceResource::Logs,
+        "config" => VersionJsonInstanceResource::Configs,
+        "shaderpacks" => VersionJsonInstanceResource::ShaderPacks,
+        "resourcepacks" => VersionJsonInstanceResource::ResourcePacks,
+        "saves" => VersionJsonInstanceResource::Saves,
+        "mods" => VersionJsonInstanceResource::Mods,
+        "jarmods" => VersionJsonInstanceResource::Jarmods(None),
+        "elemental" => VersionJsonInstanceResource::Elemental(None),
+        _ => return None,
+    })
+}
+
+fn shared_kind_key(kind: SharedArtifactKind) -> Option<&'static str> {
+    match kind {
+        SharedArtifactKind::Library => Some("libraries"),
+        SharedArtifactKind::AssetObject => Some("asset_objects"),
+        SharedArtifactKind::AssetIndex => Some("asset_indexes"),
+        SharedArtifactKind::AssetLogConfig => Some("asset_log_configs"),
+        SharedArtifactKind::Runtime | SharedArtifactKind::GameDirectory => None,
+    }
+}
+
+fn shared_kind_from_key(key: &str) -> Option<SharedArtifactKind> {
+    SharedArtifactKind::all()
+        .into_iter()
+        .find(|kind| shared_kind_key(*kind) == Some(key))
+}
+
+fn staging_path(destination: &Path) -> Result<PathBuf> {
+    let name = destination
+        .file_name()
+        .ok_or_else(|| anyhow!("invalid archive path: {}", destination.display()))?;
+    Ok(destination.with_file_name(format!(
+        ".{}.tmp.{}",
+        name.to_string_lossy(),
+        std::process::id()
+    )))
+}
+
+fn entry_options(size: u64) -> SimpleFileOptions {
+    SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Deflated)
+        .large_file(size >= u32::MAX as u64)
+}
+
+fn write_entry(writer: &mut ZipWriter<File>, name: &str, path: &Path) -> Result<u64> {
+    let mut file =
+        File::open(path).with_context(|| format!("open file failed: {}", path.display()))?;
+    writer.start_file(name, entry_options(file.metadata()?.len()))?;
+    io::copy(&mut file, writer).with_context(|| format!("archive file failed: {}", path.display()))
+}
+
+fn extract_entry(entry: &mut impl Read, target: &Path) -> Result<u64> {
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = File::create(target)
+        .with_context(|| format!("create file failed: {}", target.display()))?;
+    io::copy(entry, &mut file).with_context(|| format!("extract file failed: {}", target.display()))
+}
+
+fn open_archive(archive: &Path) -> Result<ZipArchive<File>> {
+    let file = File::open(archive)
+        .with_context(|| format!("open archive failed: {}", archive.display()))?;
+    ZipArchive::new(file).with_context(|| format!("read archive failed: {}", archive.display()))
+}
+
+fn read_manifest(zip: &mut ZipArchive<File>) -> Result<InstanceArchiveManifest> {
+    let manifest: InstanceArchiveManifest = serde_json::from_reader(zip.by_name(MANIFEST_ENTRY)?)?;
+    if manifest.format_version > INSTANCE_ARCHIVE_FORMAT_VERSION {
+        bail!(
+            "unsupported instance archive format version {}",
+            manifest.format_version
+        );
+    }
+
+    Ok(manifest)
+}
+
+/// Walks an archived resource, hidden files included, leaving out what belongs to a running
+/// process: lock files, in-flight writes in the state directory and the state directory itself
+/// when it sits inside another resource, as it is archived on its own.
+fn collect_archive_files(root: &Path, state_root: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    if !root.is_dir() {
+        return Ok(());
+    }
+
+    for entry in root
+        .read_dir()
+        .with_context(|| format!("read directory failed: {}", root.display()))?
+    {
+        let entry = entry?;
+        let path = entry.path();
+        if path == state_root
+            || path
+                .extension()
+                .is_some_and(|extension| extension == "lock")
+            || (path.starts_with(state_root)
+                && entry.file_name().to_string_lossy().starts_with('.'))
+        {
+            continue;
+        }
+
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            collect_archive_files(&path, state_root, files)?;
+        } else if file_type.is_file() {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+fn relative_entry_name(root: &Path, path: &Path) -> Result<String> {
+    let relative = path
+        .strip_prefix(root)
+        .with_context(|| format!("{} is outside {}", path.display(), root.display()))?;
+    Ok(relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/"))
+}
+
+#[test]
+fn test_archive_round_trip_restores_resources_and_replaces_differing_shared_artifacts() {
+    use crate::{
+        families::version_json::{BaseInstanceLayout, BaseRootLayout, VersionJsonGameStorageExt},
+        references::write_test_instance,
+    };
+
+    let temp = std::env::temp_dir().join(format!("elemental-archive-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&temp);
+    let write = |path: PathBuf, contents: &[u8]| {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, contents).unwrap();
+        path
+    };
+    let source_root = temp.join("source");
+    write_test_instance(&source_root, "a", &["org/a/a/1/a-1.jar"], "idx");
+    write(
+        source_root.join("versions/a/saves/world/level.dat"),
+        b"level",
+    );
+    write(source_root.join("versions/a/mods/mod.jar"), b"mod");
+    write(
+        source_root.join("versions/a/mods/.index/mod.toml"),
+        b"index",
+    );
+    write(
+        source_root.join("versions/a/.elemental/jarmods.json"),
+        b"{}",
+    );
+    write(source_root.join("versions/a/.elemental/instance.lock"), b"");
+    write(
+        source_root.join("versions/a/.elemental/.jarmods.json.tmp"),
+        b"{",
+    );
+    write(source_root.join("libraries/org/a/a/1/a-1.jar"), b"library");
+    write(
+        source_root.join("assets/indexes/idx.json"),
+        br#"{"objects":{}}"#,
+    );
+    let source = Storage::new(&source_root, BaseRootLayout)
+        .instance("a", BaseInstanceLayout)
+        .unwrap();
+    let options = InstanceArchiveOptions::new().set_shared_artifacts(true);
+    let driver = InstanceArchiveDriver {
+        id: "vanilla".to_owned(),
+        game_version: "1.5.2".to_owned(),
+        loader_version: None,
+    };
+    let archive = temp.join("a.zip");
+    let exported = export_instance_archive(&source, driver.clone(), &archive, &options).unwrap();
+    assert_eq!(exported.shared_files, 2);
+
+    let target_root = temp.join("target");
+    let stale_library = write(target_root.join("libraries/org/a/a/1/a-1.jar"), b"stale!!");
+    let kept_index = target_root.join("assets/indexes/idx.json");
+    write(kept_index.clone(), br#"{"objects":{}}"#);
+    assert_eq!(
+        read_instance_archive_manifest(&archive).unwrap().driver,
+        driver
+    );
+    let target = Storage::with_parent(
+        target_root.join("versions/b"),
+        Storage::new(&target_root, BaseRootLayout),
+        BaseInstanceLayout,
+    );
+    let imported = import_instance_archive(&archive, &target, &options).unwrap();
+
+    assert_eq!(imported.files, exported.files);
+    assert_eq!(imported.shared_files, 1);
+    assert_eq!(fs::read(&stale_library).unwrap(), b"library");
+    assert_eq!(
+        fs::read(target_root.join("versions/b/saves/world/level.dat")).unwrap(),
+        b"level"
+    );
+    assert_eq!(
+        fs::read(target_root.join("versions/b/mods/mod.jar")).unwrap(),
+        b"mod"
+    );
+    assert_eq!(
+        fs::read(target_root.join("versions/b/mods/.index/mod.toml")).unwrap(),
+        b"index"
+    );
+    assert!(
+        target_root
+            .join("versions/b/.elemental/jarmods.json")
+            .is_file()
+    );
+    assert!(
+        !target_root
+            .join("versions/b/.elemental/instance.lock")
+            .exists()
+    );
+    assert!(
+        !target_root
+            .join("versions/b/.elemental/.jarmods.json.tmp")
+            .exists()
+    );
+    assert!(target_root.join("versions/b/b.json").is_file());
+
+    fs::remove_dir_all(&temp).unwrap();
+}
@@ -4,11 +4,14 @@ use anyhow::Result;
 use async_trait::async_trait;
 use elemental_core::minecraft::MinecraftVersionId;
 
+use crate::http::Fetched;
+
 #[async_trait]
 pub trait Catalog {
     type Release;
 
-    async fn releases(&self) -> Result<HashMap<MinecraftVersionId, Vec<Self::Release>>>;
+    /// Releases by game version, `stale` when any metadata behind them was served from the cache.
+    async fn releases(&self) -> Result<Fetched<HashMap<MinecraftVersionId, Vec<Self::Release>>>>;
 }
 
 pub fn push_single_game_release<R>(
@@ -46,20 +49,23 @@ pub async fn collect_single_game_loader_releases<
     LoadLoaderVersionsFuture,
     BuildRelease,
 >(
-    game_versions: Vec<MinecraftVersionId>,
+    game_versions: Fetched<Vec<MinecraftVersionId>>,
     mut load_loader_versions: LoadLoaderVersions,
     mut build_release: BuildRelease,
-) -> Result<HashMap<MinecraftVersionId, Vec<Release>>>
+) -> Result<Fetched<HashMap<MinecraftVersionId, Vec<Release>>>>
 where
     LoadLoaderVersions: FnMut(MinecraftVersionId) -> LoadLoaderVersionsFuture,
-    LoadLoaderVersionsFuture: Future<Output = Result<Vec<Loader>>>,
+    LoadLoaderVersionsFuture: Future<Output = Result<Fetched<Vec<Loader>>>>,
     BuildRelease: FnMut(&MinecraftVersionId, Loader) -> Release,
 {
     let mut releases = HashMap::new();
+    let mut stale = game_versions.stale;
 
-    for game_version in game_versions {
-        let game_releases = load_loader_versions(game_version.clone())
-            .await?
+    for game_version in game_versions.value {
+        let loaders = load_loader_versions(game_version.clone()).await?;
+        stale |= loaders.stale;
+        let game_releases = loaders
+            .value
             .into_iter()
             .map(|loader| build_release(&game_version, loader))
             .collect::<Vec<Release>>();
@@ -67,5 +73,5 @@ where
         releases.insert(game_version, game_releases);
     }
 
-    Ok(releases)
+    Ok(Fetched::fresh(releases).or_stale(stale))
 }
@@ -5,6 +5,7 @@ use async_trait::async_trait;
 use elemental_core::minecraft::MinecraftVersionId;
 
 use crate::catalog::{Catalog, collect_single_game_releases};
+use crate::http::Fetched;
 use crate::loader_version::LoaderVersionId;
 
 use super::source::CleanroomSource;
@@ -43,11 +44,10 @@ impl CleanroomCatalog {
 impl Catalog for CleanroomCatalog {
     type Release = CleanroomRelease;
 
-    async fn releases(&self) -> Result<HashMap<MinecraftVersionId, Vec<Self::Release>>> {
+    async fn releases(&self) -> Result<Fetched<HashMap<MinecraftVersionId, Vec<Self::Release>>>> {
         let metadata = self.source.maven_metadata().await?;
-        Ok(collect_single_game_releases(
-            metadata.versioning.versions.version,
-            |version| {
+        Ok(metadata.map(|metadata| {
+            collect_single_game_releases(metadata.versioning.versions.version, |version| {
                 Some((
                     MinecraftVersionId::from(CLEANROOM_GAME_VERSION),
                     CleanroomRelease {
@@ -55,7 +55,7 @@ impl Catalog for CleanroomCatalog {
                         description: Some(CLEANROOM_RELEASE_DESCRIPTION.to_owned()),
                     },
                 ))
-            },
-        ))
+            })
+        }))
     }
 }
@@ -17,6 +17,7 @@ use crate::{
         ProfiledVersionJsonDriver, ProfiledVersionJsonFamily, ProfiledVersionJsonFamilyExt,
         VanillaFallbackRemoteResolver,
     },
+    http::Fetched,
     loader_version::LoaderVersionId,
 };
 
@@ -79,7 +80,7 @@ impl ProfiledVersionJsonFamily for FabricDriverFamily {
         source: &Self::Source,
         game_version: &MinecraftVersionId,
         loader_version: &LoaderVersionId,
-    ) -> Result<Self::Profile> {
+    ) -> Result<Fetched<Self::Profile>> {
         source
             .profile_json(game_version.as_str(), loader_version.as_str())
             .await
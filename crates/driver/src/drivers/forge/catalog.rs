@@ -5,6 +5,7 @@ use async_trait::async_trait;
 use elemental_core::minecraft::MinecraftVersionId;
 
 use crate::catalog::{Catalog, collect_single_game_releases};
+use crate::http::Fetched;
 use crate::loader_version::LoaderVersionId;
 
 use super::source::ForgeSource;
@@ -41,11 +42,10 @@ impl ForgeCatalog {
 impl Catalog for ForgeCatalog {
     type Release = ForgeRelease;
 
-    async fn releases(&self) -> Result<HashMap<MinecraftVersionId, Vec<Self::Release>>> {
+    async fn releases(&self) -> Result<Fetched<HashMap<MinecraftVersionId, Vec<Self::Release>>>> {
         let metadata = self.source.maven_metadata().await?;
-        Ok(collect_single_game_releases(
-            metadata.versioning.versions.version,
-            |version| {
+        Ok(metadata.map(|metadata| {
+            collect_single_game_releases(metadata.versioning.versions.version, |version| {
                 let (game_version, loader_version) = version.split_once('-')?;
                 Some((
                     MinecraftVersionId::from(game_version),
@@ -55,7 +55,7 @@ impl Catalog for ForgeCatalog {
                         description: None,
                     },
                 ))
-            },
-        ))
+            })
+        }))
     }
 }
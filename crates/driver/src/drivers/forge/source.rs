@@ -63,9 +63,13 @@ impl ForgeEndpoints {
         )
     }
 
-    pub fn installer_url(&self, game_version: &str, loader_version: &str) -> Result<String> {
+    pub fn installer_url_candidates(
+        &self,
+        game_version: &str,
+        loader_version: &str,
+    ) -> Result<Vec<String>> {
         let version = release_version(game_version, loader_version);
-        self.origin_policy.resolve(
+        self.origin_policy.resolve_candidates(
             ForgeOrigin::Maven,
             &format!("/net/minecraftforge/forge/{version}/forge-{version}-installer.jar"),
         )
@@ -79,6 +83,10 @@ impl ForgeEndpoints {
     pub fn rewrite_upstream(&self, raw_url: &str) -> Result<String> {
         self.origin_policy.rewrite_origin_url(raw_url)
     }
+
+    pub fn rewrite_upstream_candidates(&self, raw_url: &str) -> Result<Vec<String>> {
+        self.origin_policy.rewrite_origin_url_candidates(raw_url)
+    }
 }
 
 impl InstallerArtifactEndpoints for ForgeEndpoints {
@@ -89,6 +97,15 @@ impl InstallerArtifactEndpoints for ForgeEndpoints {
     fn rewrite_upstream(&self, raw_url: &str) -> Result<String> {
         ForgeEndpoints::rewrite_upstream(self, raw_url)
     }
+
+    fn artifact_url_candidates(&self, artifact_path: &str) -> Result<Vec<String>> {
+        self.origin_policy
+            .resolve_candidates(ForgeOrigin::Maven, artifact_path)
+    }
+
+    fn rewrite_upstream_candidates(&self, raw_url: &str) -> Result<Vec<String>> {
+        ForgeEndpoints::rewrite_upstream_candidates(self, raw_url)
+    }
 }
 
 impl InstallerMavenEndpoints for ForgeEndpoints {
@@ -107,7 +124,8 @@ impl InstallerMavenEndpoints for ForgeEndpoints {
 
         Ok(InstallerMavenArtifactSpec {
             coordinate: format!("net.minecraftforge:forge:{version}:installer"),
-            download_url: self.installer_url(game_version.as_str(), loader_version.as_str())?,
+            download_urls: self
+                .installer_url_candidates(game_version.as_str(), loader_version.as_str())?,
             relative_path: forge_installer_relative_path(&version),
         })
     }
@@ -4,9 +4,8 @@ use anyhow::Result;
 use async_trait::async_trait;
 use elemental_core::minecraft::MinecraftVersionId;
 
-use crate::catalog::{
-    Catalog, push_single_game_release,
-};
+use crate::catalog::{Catalog, push_single_game_release};
+use crate::http::Fetched;
 use crate::loader_version::LoaderVersionId;
 
 use super::source::LiteLoaderSource;
@@ -35,10 +34,12 @@ impl LiteLoaderCatalog {
 impl Catalog for LiteLoaderCatalog {
     type Release = LiteLoaderCatalogRelease;
 
-    async fn releases(&self) -> Result<HashMap<MinecraftVersionId, Vec<Self::Release>>> {
+    async fn releases(&self) -> Result<Fetched<HashMap<MinecraftVersionId, Vec<Self::Release>>>> {
         let mut releases = HashMap::new();
 
-        for release in self.source.releases().await? {
+        let source_releases = self.source.releases().await?;
+
+        for release in source_releases.value {
             let game_version = MinecraftVersionId::from(release.game_version);
             push_single_game_release(
                 &mut releases,
@@ -51,6 +52,6 @@ impl Catalog for LiteLoaderCatalog {
             );
         }
 
-        Ok(releases)
+        Ok(Fetched::fresh(releases).or_stale(source_releases.stale))
     }
 }
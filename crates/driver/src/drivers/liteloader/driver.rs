@@ -9,6 +9,7 @@ use crate::{
     drivers::{liteloader::source::LiteLoaderSource, vanilla::source::VanillaSource},
     families::version_json::direct_profiled::DirectProfiledVersionJsonDefaults,
     families::version_json::{ProfiledVersionJsonDriver, ProfiledVersionJsonFamily},
+    http::Fetched,
     inspect::{LibraryPrefixSet, ProfileIdPattern, ProfiledDriverIdentity},
     loader_version::LoaderVersionId,
 };
@@ -54,7 +55,7 @@ impl ProfiledVersionJsonFamily for LiteLoaderDriverFamily {
         source: &Self::Source,
         game_version: &MinecraftVersionId,
         loader_version: &LoaderVersionId,
-    ) -> Result<Self::Profile> {
+    ) -> Result<Fetched<Self::Profile>> {
         source
             .profile_json(game_version.as_str(), loader_version.as_str())
             .await
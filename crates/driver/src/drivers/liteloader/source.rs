@@ -3,7 +3,7 @@ use elemental_schema::fabric::ProfileJson;
 
 use crate::{
     families::version_json::UpstreamUrlRewriter,
-    http::{HttpSource, fetch_json},
+    http::{Fetched, HttpCache, HttpClientSource, HttpSource},
     url::{Origin, OriginPolicy},
 };
 
@@ -13,6 +13,8 @@ use super::{
     profile::build_profile_json,
 };
 
+pub const LITELOADER_SOURCE_NAME: &str = "liteloader source";
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum LiteLoaderOrigin {
     Downloads,
@@ -148,6 +150,16 @@ impl LiteLoaderEndpoints {
     }
 }
 
+impl HttpClientSource for LiteLoaderSource {
+    fn set_client(&mut self, client: reqwest::Client) {
+        self.inner.set_client(client);
+    }
+
+    fn set_cache(&mut self, cache: HttpCache) {
+        self.inner.set_cache(cache);
+    }
+}
+
 impl Default for LiteLoaderSource {
     fn default() -> Self {
         Self::new(LiteLoaderEndpoints::default())
@@ -157,7 +169,7 @@ impl Default for LiteLoaderSource {
 impl LiteLoaderSource {
     pub fn new(endpoints: LiteLoaderEndpoints) -> Self {
         Self {
-            inner: HttpSource::new(endpoints, "liteloader source"),
+            inner: HttpSource::new(endpoints, LITELOADER_SOURCE_NAME),
         }
     }
 
@@ -171,24 +183,26 @@ impl LiteLoaderSource {
         self.inner.endpoints()
     }
 
-    pub async fn releases(&self) -> Result<Vec<LiteLoaderRelease>> {
-        let manifest = self.manifest().await?;
-        Ok(collect_releases(manifest))
+    pub async fn releases(&self) -> Result<Fetched<Vec<LiteLoaderRelease>>> {
+        Ok(self.manifest().await?.map(collect_releases))
     }
 
     pub async fn profile_json(
         &self,
         game_version: &str,
         loader_version: &str,
-    ) -> Result<ProfileJson> {
+    ) -> Result<Fetched<ProfileJson>> {
         let manifest = self.manifest().await?;
-        let selected = select_build(&manifest, game_version, loader_version)?;
-        build_profile_json(self.inner.client(), self.endpoints(), &selected).await
+        let selected = select_build(&manifest.value, game_version, loader_version)?;
+        let profile = build_profile_json(self.inner.client(), self.endpoints(), &selected).await?;
+        Ok(Fetched::fresh(profile).or_stale(manifest.stale))
     }
 
-    async fn manifest(&self) -> Result<LiteLoaderManifest> {
+    async fn manifest(&self) -> Result<Fetched<LiteLoaderManifest>> {
         let url = self.endpoints().versions_manifest_url()?;
-        fetch_json(self.inner.client(), url.as_str(), "liteloader source").await
+        self.inner
+            .fetch_json(url.as_str(), LITELOADER_SOURCE_NAME)
+            .await
     }
 }
 
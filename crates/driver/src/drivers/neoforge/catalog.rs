@@ -5,6 +5,7 @@ use async_trait::async_trait;
 use elemental_core::minecraft::MinecraftVersionId;
 
 use crate::catalog::{Catalog, collect_single_game_releases};
+use crate::http::Fetched;
 use crate::loader_version::LoaderVersionId;
 
 use super::source::NeoForgeSource;
@@ -43,11 +44,10 @@ impl NeoForgeCatalog {
 impl Catalog for NeoForgeCatalog {
     type Release = NeoForgeRelease;
 
-    async fn releases(&self) -> Result<HashMap<MinecraftVersionId, Vec<Self::Release>>> {
+    async fn releases(&self) -> Result<Fetched<HashMap<MinecraftVersionId, Vec<Self::Release>>>> {
         let metadata = self.source.maven_metadata().await?;
-        Ok(collect_single_game_releases(
-            metadata.versioning.versions.version,
-            |version| {
+        Ok(metadata.map(|metadata| {
+            collect_single_game_releases(metadata.versioning.versions.version, |version| {
                 let game_version_hint = infer_game_version_from_loader_version(version.as_str())?;
                 Some((
                     game_version_hint.clone(),
@@ -57,8 +57,8 @@ impl Catalog for NeoForgeCatalog {
                         description: Some(GAME_VERSION_HEURISTIC_DESCRIPTION.to_owned()),
                     },
                 ))
-            },
-        ))
+            })
+        }))
     }
 }
 
@@ -63,9 +63,9 @@ impl NeoForgeEndpoints {
         )
     }
 
-    pub fn installer_url(&self, loader_version: &str) -> Result<String> {
+    pub fn installer_url_candidates(&self, loader_version: &str) -> Result<Vec<String>> {
         let version = release_version(loader_version);
-        self.origin_policy.resolve(
+        self.origin_policy.resolve_candidates(
             NeoForgeOrigin::Maven,
             &format!("/net/neoforged/neoforge/{version}/neoforge-{version}-installer.jar"),
         )
@@ -79,6 +79,14 @@ impl NeoForgeEndpoints {
     pub fn rewrite_upstream(&self, raw_url: &str) -> Result<String> {
         self.origin_policy.rewrite_known_origin_url_or_keep(raw_url)
     }
+
+    pub fn rewrite_upstream_candidates(&self, raw_url: &str) -> Result<Vec<String>> {
+        let rewritten = self.rewrite_upstream(raw_url)?;
+        Ok(self
+            .origin_policy
+            .try_rewrite_origin_url_candidates(&rewritten)?
+            .unwrap_or_else(|| vec![rewritten]))
+    }
 }
 
 impl InstallerArtifactEndpoints for NeoForgeEndpoints {
@@ -89,6 +97,15 @@ impl InstallerArtifactEndpoints for NeoForgeEndpoints {
     fn rewrite_upstream(&self, raw_url: &str) -> Result<String> {
         NeoForgeEndpoints::rewrite_upstream(self, raw_url)
     }
+
+    fn artifact_url_candidates(&self, artifact_path: &str) -> Result<Vec<String>> {
+        self.origin_policy
+            .resolve_candidates(NeoForgeOrigin::Maven, artifact_path)
+    }
+
+    fn rewrite_upstream_candidates(&self, raw_url: &str) -> Result<Vec<String>> {
+        NeoForgeEndpoints::rewrite_upstream_candidates(self, raw_url)
+    }
 }
 
 impl InstallerMavenEndpoints for NeoForgeEndpoints {
@@ -107,7 +124,7 @@ impl InstallerMavenEndpoints for NeoForgeEndpoints {
 
         Ok(InstallerMavenArtifactSpec {
             coordinate: format!("net.neoforged:neoforge:{version}:installer"),
-            download_url: self.installer_url(loader_version.as_str())?,
+            download_urls: self.installer_url_candidates(loader_version.as_str())?,
             relative_path: neoforge_installer_relative_path(&version),
         })
     }
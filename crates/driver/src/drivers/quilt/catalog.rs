@@ -5,6 +5,7 @@ use async_trait::async_trait;
 use elemental_core::minecraft::MinecraftVersionId;
 
 use crate::catalog::{Catalog, collect_single_game_loader_releases};
+use crate::http::Fetched;
 use crate::loader_version::LoaderVersionId;
 
 use super::source::QuiltSource;
@@ -33,14 +34,13 @@ impl QuiltCatalog {
 impl Catalog for QuiltCatalog {
     type Release = QuiltRelease;
 
-    async fn releases(&self) -> Result<HashMap<MinecraftVersionId, Vec<Self::Release>>> {
-        let game_versions = self
-            .source
-            .game_versions()
-            .await?
-            .into_iter()
-            .map(|game_version| MinecraftVersionId::from(game_version.version))
-            .collect::<Vec<MinecraftVersionId>>();
+    async fn releases(&self) -> Result<Fetched<HashMap<MinecraftVersionId, Vec<Self::Release>>>> {
+        let game_versions = self.source.game_versions().await?.map(|game_versions| {
+            game_versions
+                .into_iter()
+                .map(|game_version| MinecraftVersionId::from(game_version.version))
+                .collect::<Vec<MinecraftVersionId>>()
+        });
 
         collect_single_game_loader_releases(
             game_versions,
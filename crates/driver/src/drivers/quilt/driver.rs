@@ -11,6 +11,7 @@ use crate::{
         PASSTHROUGH_PROFILE_BEHAVIOR, ProfiledVersionJsonDriver, ProfiledVersionJsonFamily,
         VanillaFallbackRemoteResolver, merge_profile_with_behavior,
     },
+    http::Fetched,
     inspect::LibraryPrefixSet,
     loader_version::LoaderVersionId,
 };
@@ -52,7 +53,7 @@ impl ProfiledVersionJsonFamily for QuiltDriverFamily {
         source: &Self::Source,
         game_version: &MinecraftVersionId,
         loader_version: &LoaderVersionId,
-    ) -> Result<Self::Profile> {
+    ) -> Result<Fetched<Self::Profile>> {
         source
             .profile_json(game_version.as_str(), loader_version.as_str())
             .await
@@ -4,9 +4,8 @@ use anyhow::Result;
 use async_trait::async_trait;
 use elemental_core::minecraft::MinecraftVersionId;
 
-use crate::catalog::{
-    Catalog, push_single_game_release,
-};
+use crate::catalog::{Catalog, push_single_game_release};
+use crate::http::Fetched;
 use crate::loader_version::LoaderVersionId;
 
 use super::source::RiftSource;
@@ -22,7 +21,6 @@ pub struct RiftCatalogRelease {
     pub published_at: Option<String>,
 }
 
-
 impl RiftCatalog {
     pub fn new(source: RiftSource) -> Self {
         Self { source }
@@ -37,10 +35,12 @@ impl RiftCatalog {
 impl Catalog for RiftCatalog {
     type Release = RiftCatalogRelease;
 
-    async fn releases(&self) -> Result<HashMap<MinecraftVersionId, Vec<Self::Release>>> {
+    async fn releases(&self) -> Result<Fetched<HashMap<MinecraftVersionId, Vec<Self::Release>>>> {
         let mut releases = HashMap::new();
 
-        for release in self.source.releases().await? {
+        let source_releases = self.source.releases().await?;
+
+        for release in source_releases.value {
             let profile = self.source.profile_json_for_release(&release).await?;
             let game_version = MinecraftVersionId::from(profile.inherits_from);
             push_single_game_release(
@@ -54,6 +54,6 @@ impl Catalog for RiftCatalog {
             );
         }
 
-        Ok(releases)
+        Ok(Fetched::fresh(releases).or_stale(source_releases.stale))
     }
 }
@@ -9,6 +9,7 @@ use crate::{
     drivers::{rift::source::RiftSource, vanilla::source::VanillaSource},
     families::version_json::direct_profiled::DirectProfiledVersionJsonDefaults,
     families::version_json::{ProfiledVersionJsonDriver, ProfiledVersionJsonFamily},
+    http::Fetched,
     inspect::{LibraryPrefixSet, ProfileIdPattern, ProfiledDriverIdentity},
     loader_version::LoaderVersionId,
 };
@@ -58,10 +59,11 @@ impl ProfiledVersionJsonFamily for RiftDriverFamily {
         source: &Self::Source,
         game_version: &MinecraftVersionId,
         loader_version: &LoaderVersionId,
-    ) -> Result<Self::Profile> {
+    ) -> Result<Fetched<Self::Profile>> {
         let profile = source.profile_json(loader_version.as_str()).await?;
         validate_requested_game_version(&profile, game_version, loader_version)?;
-        Ok(profile)
+        // The profile is read from the release jar, which isn't cached.
+        Ok(Fetched::fresh(profile))
     }
 
     fn merge_profile(
@@ -13,11 +13,13 @@ use serde::Deserialize;
 
 use crate::{
     families::version_json::UpstreamUrlRewriter,
-    http::{HttpSource, fetch_bytes, fetch_json},
+    http::{Fetched, HttpCache, HttpClientSource, HttpSource, fetch_bytes},
     inspect::ProfileIdPattern,
     url::{Origin, OriginPolicy},
 };
 
+pub const RIFT_SOURCE_NAME: &str = "rift source";
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum RiftOrigin {
     GitHubApi,
@@ -161,6 +163,16 @@ impl RiftEndpoints {
     }
 }
 
+impl HttpClientSource for RiftSource {
+    fn set_client(&mut self, client: reqwest::Client) {
+        self.inner.set_client(client);
+    }
+
+    fn set_cache(&mut self, cache: HttpCache) {
+        self.inner.set_cache(cache);
+    }
+}
+
 impl Default for RiftSource {
     fn default() -> Self {
         Self::new(RiftEndpoints::default())
@@ -170,7 +182,7 @@ impl Default for RiftSource {
 impl RiftSource {
     pub fn new(endpoints: RiftEndpoints) -> Self {
         Self {
-            inner: HttpSource::new(endpoints, "rift source"),
+            inner: HttpSource::new(endpoints, RIFT_SOURCE_NAME),
         }
     }
 
@@ -184,16 +196,20 @@ impl RiftSource {
         self.inner.endpoints()
     }
 
-    pub async fn releases(&self) -> Result<Vec<RiftRelease>> {
+    pub async fn releases(&self) -> Result<Fetched<Vec<RiftRelease>>> {
         let url = self.endpoints().releases_url()?;
-        let releases: Vec<GitHubRelease> =
-            fetch_json(self.inner.client(), url.as_str(), "rift source").await?;
-
-        releases
-            .into_iter()
-            .filter(|release| !release.draft)
-            .map(build_rift_release)
-            .collect()
+        let releases: Fetched<Vec<GitHubRelease>> = self
+            .inner
+            .fetch_json(url.as_str(), RIFT_SOURCE_NAME)
+            .await?;
+
+        releases.try_map(|releases| {
+            releases
+                .into_iter()
+                .filter(|release| !release.draft)
+                .map(build_rift_release)
+                .collect()
+        })
     }
 
     pub async fn profile_json(&self, loader_version: &str) -> Result<ProfileJson> {
@@ -205,9 +221,9 @@ impl RiftSource {
         let url = self
             .endpoints()
             .release_asset_url(release.tag_name.as_str(), release.asset_name.as_str())?;
-        let jar_bytes = fetch_bytes(self.inner.client(), url.as_str(), "rift source").await?;
+        let jar_bytes = fetch_bytes(self.inner.client(), url.as_str(), RIFT_SOURCE_NAME).await?;
         let raw_profile = read_release_profile_json(release, jar_bytes).await?;
-        let profile = parse_profile_json(raw_profile.as_str())?;
+        let profile = convert_profile_json(raw_profile);
 
         normalize_profile_json(profile, release, self.endpoints())
     }
@@ -256,9 +272,12 @@ fn normalize_loader_version(tag_name: &str) -> Result<String> {
     Ok(loader_version.to_owned())
 }
 
-async fn read_release_profile_json(release: &RiftRelease, jar_bytes: Vec<u8>) -> Result<String> {
+async fn read_release_profile_json(
+    release: &RiftRelease,
+    jar_bytes: Vec<u8>,
+) -> Result<RiftRawProfileJson> {
     JarBytes::new(jar_bytes.as_slice())
-        .by_name_string("profile.json")
+        .by_name_json("profile.json")
         .with_context(|| {
             format!(
                 "read embedded Rift profile failed from '{}'",
@@ -267,11 +286,8 @@ async fn read_release_profile_json(release: &RiftRelease, jar_bytes: Vec<u8>) ->
         })
 }
 
-fn parse_profile_json(raw_profile: &str) -> Result<ProfileJson> {
-    let raw_profile: RiftRawProfileJson =
-        serde_json::from_str(raw_profile).context("decode embedded Rift profile failed")?;
-
-    Ok(ProfileJson {
+fn convert_profile_json(raw_profile: RiftRawProfileJson) -> ProfileJson {
+    ProfileJson {
         id: raw_profile.id,
         inherits_from: raw_profile.inherits_from,
         arguments: raw_profile.arguments,
@@ -287,7 +303,7 @@ fn parse_profile_json(raw_profile: &str) -> Result<ProfileJson> {
         release_type: raw_profile.release_type,
         time: raw_profile.time,
         release_time: raw_profile.release_time,
-    })
+    }
 }
 
 fn raw_profile_library_to_profile(raw_library: RiftRawProfileLibrary) -> ProfileLibrary {
@@ -4,7 +4,10 @@ use anyhow::Result;
 use async_trait::async_trait;
 use elemental_core::minecraft::MinecraftVersionId;
 
-use crate::catalog::{Catalog, push_single_game_release};
+use crate::{
+    catalog::{Catalog, push_single_game_release},
+    http::Fetched,
+};
 
 use super::source::VanillaSource;
 
@@ -32,11 +35,11 @@ impl VanillaCatalog {
 impl Catalog for VanillaCatalog {
     type Release = VanillaRelease;
 
-    async fn releases(&self) -> Result<HashMap<MinecraftVersionId, Vec<Self::Release>>> {
+    async fn releases(&self) -> Result<Fetched<HashMap<MinecraftVersionId, Vec<Self::Release>>>> {
         let mut releases = HashMap::new();
         let manifest = self.source.launch_meta().await?;
 
-        for version in manifest.versions {
+        for version in manifest.value.versions {
             let version_id = version.id;
             push_single_game_release(
                 &mut releases,
@@ -48,6 +51,6 @@ impl Catalog for VanillaCatalog {
             );
         }
 
-        Ok(releases)
+        Ok(Fetched::fresh(releases).or_stale(manifest.stale))
     }
 }
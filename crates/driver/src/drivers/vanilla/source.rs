@@ -7,10 +7,12 @@ use elemental_schema::mojang::{
 use crate::{
     drivers::vanilla::prepared::ResolvedVanillaMetadata,
     families::version_json::VersionJsonRemoteResolver,
-    http::{HttpSource, fetch_json},
+    http::{Fetched, HttpCache, HttpClientSource, HttpSource},
     url::{Origin, OriginPolicy},
 };
 
+pub const VANILLA_SOURCE_NAME: &str = "vanilla source";
+
 const LAUNCHERMETA_ORIGIN: &str = "https://launchermeta.mojang.com";
 const PISTONMETA_ORIGIN: &str = "https://piston-meta.mojang.com";
 const PISTONDATA_ORIGIN: &str = "https://piston-data.mojang.com";
@@ -105,12 +107,23 @@ impl VanillaEndpoints {
         self.origin_policy.rewrite_origin_url(raw_url)
     }
 
+    pub fn rewrite_upstream_candidates(&self, raw_url: &str) -> Result<Vec<String>> {
+        self.origin_policy.rewrite_origin_url_candidates(raw_url)
+    }
+
     pub fn object_url(&self, hash: impl AsRef<str>) -> Result<String> {
         let hash = hash.as_ref();
         let prefix = hash.get(0..2).context("asset hash is too short")?;
         self.origin_policy
             .resolve(VanillaOrigin::Resources, &format!("{prefix}/{hash}"))
     }
+
+    pub fn object_url_candidates(&self, hash: impl AsRef<str>) -> Result<Vec<String>> {
+        let hash = hash.as_ref();
+        let prefix = hash.get(0..2).context("asset hash is too short")?;
+        self.origin_policy
+            .resolve_candidates(VanillaOrigin::Resources, &format!("{prefix}/{hash}"))
+    }
 }
 
 impl VersionJsonRemoteResolver for VanillaEndpoints {
@@ -121,6 +134,24 @@ impl VersionJsonRemoteResolver for VanillaEndpoints {
     fn object_url(&self, hash: &str) -> Result<String> {
         self.object_url(hash)
     }
+
+    fn rewrite_upstream_candidates(&self, raw_url: &str) -> Result<Vec<String>> {
+        self.rewrite_upstream_candidates(raw_url)
+    }
+
+    fn object_url_candidates(&self, hash: &str) -> Result<Vec<String>> {
+        self.object_url_candidates(hash)
+    }
+}
+
+impl HttpClientSource for VanillaSource {
+    fn set_client(&mut self, client: reqwest::Client) {
+        self.inner.set_client(client);
+    }
+
+    fn set_cache(&mut self, cache: HttpCache) {
+        self.inner.set_cache(cache);
+    }
 }
 
 impl Default for VanillaSource {
@@ -132,7 +163,7 @@ impl Default for VanillaSource {
 impl VanillaSource {
     pub fn new(endpoints: VanillaEndpoints) -> Self {
         Self {
-            inner: HttpSource::new(endpoints, "vanilla source"),
+            inner: HttpSource::new(endpoints, VANILLA_SOURCE_NAME),
         }
     }
 
@@ -146,22 +177,28 @@ impl VanillaSource {
         self.inner.endpoints()
     }
 
-    pub async fn launch_meta(&self) -> Result<LaunchMetaData> {
+    pub async fn launch_meta(&self) -> Result<Fetched<LaunchMetaData>> {
         let url = self.endpoints().version_manifest_url()?;
-        fetch_json(self.inner.client(), url.as_str(), "vanilla source").await
+        self.inner
+            .fetch_json(url.as_str(), VANILLA_SOURCE_NAME)
+            .await
     }
 
-    pub async fn piston_meta(&self, url: impl AsRef<str>) -> Result<PistonMetaData> {
+    pub async fn piston_meta(&self, url: impl AsRef<str>) -> Result<Fetched<PistonMetaData>> {
         let url = self.endpoints().rewrite_upstream(url.as_ref())?;
-        fetch_json(self.inner.client(), url.as_str(), "vanilla source").await
+        self.inner
+            .fetch_json(url.as_str(), VANILLA_SOURCE_NAME)
+            .await
     }
 
     pub async fn asset_index_objects(
         &self,
         url: impl AsRef<str>,
-    ) -> Result<PistonMetaAssetIndexObjects> {
+    ) -> Result<Fetched<PistonMetaAssetIndexObjects>> {
         let url = self.endpoints().rewrite_upstream(url.as_ref())?;
-        fetch_json(self.inner.client(), url.as_str(), "vanilla source").await
+        self.inner
+            .fetch_json(url.as_str(), VANILLA_SOURCE_NAME)
+            .await
     }
 }
 
@@ -171,6 +208,7 @@ pub async fn resolve_vanilla_metadata(
 ) -> Result<ResolvedVanillaMetadata> {
     let launchmeta = vanilla_source.launch_meta().await?;
     let metadata_url = launchmeta
+        .value
         .versions
         .iter()
         .find(|version| version.id == game_version)
@@ -179,14 +217,15 @@ pub async fn resolve_vanilla_metadata(
         .clone();
     let metadata = vanilla_source.piston_meta(metadata_url).await?;
     let asset_index_objects = vanilla_source
-        .asset_index_objects(&metadata.asset_index.url)
+        .asset_index_objects(&metadata.value.asset_index.url)
         .await?;
 
     Ok(ResolvedVanillaMetadata::new(
         vanilla_source.endpoints().clone(),
-        metadata,
-        asset_index_objects,
-    ))
+        metadata.value,
+        asset_index_objects.value,
+    )
+    .with_stale(launchmeta.stale || metadata.stale || asset_index_objects.stale))
 }
 
 pub fn rewrite_upstream_with_vanilla_fallback<RewriteFn>(
@@ -205,3 +244,20 @@ where
     rewrite_family()
         .with_context(|| format!("rewrite {family_name} upstream url failed for '{raw_url}'"))
 }
+
+pub fn rewrite_upstream_candidates_with_vanilla_fallback<RewriteFn>(
+    vanilla_endpoints: &VanillaEndpoints,
+    raw_url: &str,
+    family_name: &str,
+    rewrite_family: RewriteFn,
+) -> Result<Vec<String>>
+where
+    RewriteFn: FnOnce() -> Result<Vec<String>>,
+{
+    if let Ok(candidates) = vanilla_endpoints.rewrite_upstream_candidates(raw_url) {
+        return Ok(candidates);
+    }
+
+    rewrite_family()
+        .with_context(|| format!("rewrite {family_name} upstream url failed for '{raw_url}'"))
+}
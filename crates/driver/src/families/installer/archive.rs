@@ -28,7 +28,7 @@ impl<P: AsRef<Path>> InstallerArchive<P> {
     where
         T: DeserializeOwned,
     {
-        Ok(serde_json::from_str(&self.read_string(name)?)?)
+        self.jar.by_name_json(normalize_entry_name(name))
     }
 
     pub fn extract_maven_artifacts(&self, dest: &Path) -> Result<()> {
@@ -12,16 +12,16 @@ use crate::{
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct InstallerArtifact {
     pub coordinate: String,
-    pub url: String,
+    pub urls: Vec<String>,
     pub path: PathBuf,
     pub expected_size: Option<u64>,
     pub sha1: Option<String>,
 }
 
 impl InstallerArtifact {
-    pub fn download_task(&self) -> DownloadTask {
-        DownloadTask::new(
-            self.url.clone(),
+    pub fn download_task(&self) -> Result<DownloadTask> {
+        DownloadTask::from_candidates(
+            self.urls.clone(),
             self.path.clone(),
             self.expected_size,
             self.sha1.clone(),
@@ -49,7 +49,7 @@ pub fn installer_coordinate_file_name(notation: &str) -> Result<String> {
 pub fn build_installer_artifact<L>(
     game_storage: &Storage<L>,
     coordinate: String,
-    url: String,
+    urls: Vec<String>,
     library_relative_path: PathBuf,
 ) -> Result<InstallerArtifact>
 where
@@ -57,7 +57,7 @@ where
 {
     Ok(InstallerArtifact {
         coordinate,
-        url,
+        urls,
         path: game_storage.try_get_resource(VersionJsonRootResource::Libraries(Some(
             library_relative_path,
         )))?,
@@ -13,7 +13,8 @@ use elemental_schema::{forge::ForgeInstallerProfile, mojang::piston::PistonMetaL
 
 use crate::{
     drivers::vanilla::source::{
-        VanillaEndpoints, VanillaSource, rewrite_upstream_with_vanilla_fallback,
+        VanillaEndpoints, VanillaSource, rewrite_upstream_candidates_with_vanilla_fallback,
+        rewrite_upstream_with_vanilla_fallback,
     },
     families::{
         installer::{
@@ -37,6 +38,14 @@ use crate::{
 pub trait InstallerArtifactEndpoints: Clone + Debug + Send + Sync + 'static {
     fn artifact_url(&self, artifact_path: &str) -> Result<String>;
     fn rewrite_upstream(&self, raw_url: &str) -> Result<String>;
+
+    fn artifact_url_candidates(&self, artifact_path: &str) -> Result<Vec<String>> {
+        Ok(vec![self.artifact_url(artifact_path)?])
+    }
+
+    fn rewrite_upstream_candidates(&self, raw_url: &str) -> Result<Vec<String>> {
+        Ok(vec![self.rewrite_upstream(raw_url)?])
+    }
 }
 
 pub trait InstallerArtifactSource: Clone + Debug {
@@ -102,8 +111,18 @@ pub trait InstallerFamily: Clone + Copy + Debug + Send + Sync + 'static {
         endpoints.rewrite_upstream(raw_url)
     }
 
-    fn default_artifact_url(endpoints: &Self::Endpoints, artifact_path: &str) -> Result<String> {
-        endpoints.artifact_url(artifact_path)
+    fn rewrite_upstream_candidates(
+        endpoints: &Self::Endpoints,
+        raw_url: &str,
+    ) -> Result<Vec<String>> {
+        endpoints.rewrite_upstream_candidates(raw_url)
+    }
+
+    fn default_artifact_url_candidates(
+        endpoints: &Self::Endpoints,
+        artifact_path: &str,
+    ) -> Result<Vec<String>> {
+        endpoints.artifact_url_candidates(artifact_path)
     }
 }
 
@@ -166,12 +185,16 @@ where
         }
     }
 
-    pub fn artifact_url(&self, raw_url: &str, artifact_path: &str) -> Result<String> {
+    pub fn artifact_url_candidates(
+        &self,
+        raw_url: &str,
+        artifact_path: &str,
+    ) -> Result<Vec<String>> {
         if raw_url.trim().is_empty() {
-            return F::default_artifact_url(&self.endpoints, artifact_path);
+            return F::default_artifact_url_candidates(&self.endpoints, artifact_path);
         }
 
-        F::rewrite_upstream(&self.endpoints, raw_url)
+        self.rewrite_upstream_candidates(raw_url)
     }
 }
 
@@ -191,6 +214,19 @@ where
     fn object_url(&self, hash: &str) -> Result<String> {
         self.vanilla_endpoints.object_url(hash)
     }
+
+    fn rewrite_upstream_candidates(&self, raw_url: &str) -> Result<Vec<String>> {
+        rewrite_upstream_candidates_with_vanilla_fallback(
+            &self.vanilla_endpoints,
+            raw_url,
+            F::FAMILY_NAME,
+            || F::rewrite_upstream_candidates(&self.endpoints, raw_url),
+        )
+    }
+
+    fn object_url_candidates(&self, hash: &str) -> Result<Vec<String>> {
+        self.vanilla_endpoints.object_url_candidates(hash)
+    }
 }
 
 impl<F, L, VL> ResolvedInstallerFamilyVersion<F, L, VL>
@@ -278,7 +314,9 @@ where
             &self.instance,
             &installer_state.install_profile,
             F::FAMILY_NAME,
-            |raw_url, artifact_path| remote_resolver.artifact_url(raw_url, artifact_path),
+            |raw_url, artifact_path| {
+                remote_resolver.artifact_url_candidates(raw_url, artifact_path)
+            },
         )
         .await?;
 
@@ -393,3 +431,43 @@ where
         self.launch_version.required_java_major_version()
     }
 }
+
+#[test]
+fn test_installer_library_urls_include_origin_mirrors() {
+    use crate::{
+        drivers::forge::{
+            prepared::ForgeFamily,
+            source::{ForgeEndpoints, ForgeOrigin},
+        },
+        url::OriginPolicy,
+    };
+
+    let endpoints = ForgeEndpoints::new(
+        OriginPolicy::default()
+            .with_mirror(
+                ForgeOrigin::Maven,
+                "https://mirror.example/forge/".to_owned(),
+            )
+            .expect("mirror should parse"),
+    );
+    let resolver =
+        InstallerFamilyRemoteResolver::<ForgeFamily>::new(VanillaEndpoints::official(), endpoints);
+    let path = "net/minecraftforge/forge/1.20.1-47.2.0/forge-1.20.1-47.2.0-universal.jar";
+
+    let expected = vec![
+        format!("https://maven.minecraftforge.net/{path}"),
+        format!("https://mirror.example/forge/{path}"),
+    ];
+    assert_eq!(
+        resolver
+            .artifact_url_candidates("", path)
+            .expect("default library url should resolve"),
+        expected
+    );
+    assert_eq!(
+        resolver
+            .artifact_url_candidates(&format!("https://maven.minecraftforge.net/{path}"), path)
+            .expect("upstream library url should resolve"),
+        expected
+    );
+}
@@ -6,14 +6,14 @@ use std::{
 use anyhow::{Context, Result, bail};
 use elemental_core::{
     runtime::distribution::Distribution,
-    storage::{Storage, layout::Layoutable},
+    storage::{Storage, StorageLockMode, layout::Layoutable, write_atomic_blocking},
 };
 use elemental_infra::{
     downloader::{
         core::ElementalDownloader,
         task::{DownloadExecutionPolicy, DownloadPlan, DownloadTask},
     },
-    jar::JarFile,
+    jar::{JarFile, Manifest},
 };
 use elemental_schema::forge::{ForgeInstallerProcessor, ForgeInstallerProfile};
 use regex::Regex;
@@ -22,8 +22,8 @@ use tokio::{fs::create_dir_all, process::Command};
 use crate::families::{
     installer::{InstallerArchive, InstallerArtifact, installer_coordinate_path},
     version_json::{
-        VersionJsonInstanceLayout, VersionJsonInstanceResource, VersionJsonRootLayout,
-        VersionJsonRootResource, classpath::join_classpath,
+        VersionJsonGameStorageExt, VersionJsonInstanceLayout, VersionJsonInstanceResource,
+        VersionJsonRootLayout, VersionJsonRootResource, classpath::join_classpath,
     },
 };
 
@@ -44,14 +44,14 @@ pub async fn ensure_installer_profile_libraries_downloaded<L, VL, F>(
     instance: &Storage<VL, Storage<L>>,
     install_profile: &ForgeInstallerProfile,
     family_name: &str,
-    artifact_url: F,
+    artifact_urls: F,
 ) -> Result<()>
 where
     L: VersionJsonRootLayout,
     VL: VersionJsonInstanceLayout,
-    F: Fn(&str, &str) -> Result<String>,
+    F: Fn(&str, &str) -> Result<Vec<String>>,
 {
-    let tasks = install_profile_library_tasks(instance, install_profile, &artifact_url)?;
+    let tasks = install_profile_library_tasks(instance, install_profile, &artifact_urls)?;
 
     if tasks.is_empty() {
         return Ok(());
@@ -101,6 +101,15 @@ where
         return Ok(());
     }
 
+    // Processor outputs land in the shared libraries directory, so serialize them across processes.
+    let _lock = instance.parent.try_lock(
+        &processor_lock_name(install_profile, family_name),
+        StorageLockMode::Exclusive,
+    )?;
+    if context.client_outputs_ready()? {
+        return Ok(());
+    }
+
     context.ensure_client_directories().await?;
 
     for processor in client_processors {
@@ -147,6 +156,24 @@ where
     context.client_outputs_ready()
 }
 
+fn processor_lock_name(install_profile: &ForgeInstallerProfile, family_name: &str) -> String {
+    let version = install_profile
+        .version
+        .as_deref()
+        .or(install_profile.minecraft.as_deref())
+        .unwrap_or_default()
+        .chars()
+        .map(|character| {
+            if character.is_ascii_alphanumeric() || matches!(character, '.' | '-' | '_') {
+                character
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    format!("{family_name}-processors-{version}")
+}
+
 fn processor_applies_to_client(processor: &ForgeInstallerProcessor) -> bool {
     processor.sides.is_empty() || processor.sides.iter().any(|side| side == "client")
 }
@@ -154,12 +181,12 @@ fn processor_applies_to_client(processor: &ForgeInstallerProcessor) -> bool {
 fn install_profile_library_tasks<L, VL, F>(
     instance: &Storage<VL, Storage<L>>,
     install_profile: &ForgeInstallerProfile,
-    artifact_url: &F,
+    artifact_urls: &F,
 ) -> Result<Vec<DownloadTask>>
 where
     L: VersionJsonRootLayout,
     VL: VersionJsonInstanceLayout,
-    F: Fn(&str, &str) -> Result<String>,
+    F: Fn(&str, &str) -> Result<Vec<String>>,
 {
     let mut seen = HashSet::new();
     let mut tasks = Vec::new();
@@ -179,12 +206,12 @@ where
             continue;
         }
 
-        tasks.push(DownloadTask::new(
-            artifact_url(artifact.url.as_str(), artifact.path.as_str())?,
+        tasks.push(DownloadTask::from_candidates(
+            artifact_urls(artifact.url.as_str(), artifact.path.as_str())?,
             path,
             artifact.size.map(|size| size as u64),
             artifact.sha1.clone(),
-        ));
+        )?);
     }
 
     Ok(tasks)
@@ -328,7 +355,7 @@ impl<'a> InstallerProcessorContext<'a> {
                 if let Some(parent) = output_path.parent() {
                     std::fs::create_dir_all(parent)?;
                 }
-                std::fs::write(&output_path, self.archive.read_bytes(raw)?)?;
+                write_atomic_blocking(&output_path, self.archive.read_bytes(raw)?)?;
             }
 
             return Ok(output_path.to_string_lossy().to_string());
@@ -364,7 +391,17 @@ async fn run_processor(
     processor: &ForgeInstallerProcessor,
 ) -> Result<()> {
     let processor_jar_path = context.processor_jar_path(processor)?;
-    let main_class = read_jar_main_class(&processor_jar_path)?;
+    let main_class = JarFile::new(&processor_jar_path)
+        .manifest()?
+        .as_ref()
+        .and_then(Manifest::main_class)
+        .map(ToOwned::to_owned)
+        .with_context(|| {
+            format!(
+                "jar manifest is missing Main-Class: {}",
+                processor_jar_path.display()
+            )
+        })?;
     let classpath_entries = context.processor_classpath(processor)?;
     let classpath = join_classpath(
         classpath_entries
@@ -416,35 +453,6 @@ async fn run_processor(
     );
 }
 
-fn read_jar_main_class(path: &Path) -> Result<String> {
-    let manifest = JarFile::new(path).by_name_string("META-INF/MANIFEST.MF")?;
-    let unfolded = unfold_manifest_lines(manifest.as_str());
-    let main_class = unfolded
-        .lines()
-        .find_map(|line| line.strip_prefix("Main-Class: "))
-        .map(str::trim)
-        .map(ToOwned::to_owned)
-        .with_context(|| format!("jar manifest is missing Main-Class: {}", path.display()))?;
-    Ok(main_class)
-}
-
-fn unfold_manifest_lines(manifest: &str) -> String {
-    let mut lines: Vec<String> = Vec::new();
-
-    for line in manifest.lines() {
-        if let Some(last) = lines.last_mut()
-            && let Some(continued) = line.strip_prefix(' ')
-        {
-            last.push_str(continued);
-            continue;
-        }
-
-        lines.push(line.to_owned());
-    }
-
-    lines.join("\n")
-}
-
 async fn ensure_parent_directories(arguments: &[String]) -> Result<()> {
     let mut seen = HashSet::new();
 
@@ -11,6 +11,7 @@ use elemental_core::{
     storage::{
         Storage,
         layout::{Layout, Layoutable},
+        write_atomic,
     },
 };
 use elemental_infra::downloader::{
@@ -140,7 +141,7 @@ impl<'a> InstallerStatePaths<'a> {
             )
         })?;
         create_dir_all(parent).await?;
-        tokio::fs::write(path, serde_json::to_vec_pretty(install_profile)?).await?;
+        write_atomic(path, serde_json::to_vec_pretty(install_profile)?).await?;
         Ok(())
     }
 
@@ -157,7 +158,7 @@ impl<'a> InstallerStatePaths<'a> {
                 )
             })?;
             create_dir_all(parent).await?;
-            tokio::fs::write(path, serde_json::to_vec_pretty(version)?).await?;
+            write_atomic(path, serde_json::to_vec_pretty(version)?).await?;
             return Ok(());
         }
 
@@ -272,7 +273,7 @@ pub async fn ensure_installer_artifact_downloaded(
         .run_plan(DownloadPlan::named(
             format!("{family_name}-installer-{}", installer_artifact.coordinate),
             DownloadExecutionPolicy::ServiceDefault,
-            vec![installer_artifact.download_task()],
+            vec![installer_artifact.download_task()?],
         )?)
         .await
         .with_context(|| format!("download {family_name} installer failed"))?;
@@ -347,6 +348,7 @@ where
         merged_metadata,
         base_metadata.asset_index_objects,
     )
+    .with_stale(base_metadata.stale)
     .persist(request.instance)
     .await?;
 
@@ -576,7 +578,7 @@ where
             .unwrap_or(base_metadata.compliance_level),
         downloads: embedded.downloads.unwrap_or(base_metadata.downloads),
         id: embedded.id.unwrap_or(base_metadata.id),
-        java_version: embedded.java_version.unwrap_or(base_metadata.java_version),
+        java_version: embedded.java_version.or(base_metadata.java_version),
         libraries: merge_libraries(base_metadata.libraries, embedded.libraries),
         logging: merge_embedded_logging(base_metadata.logging, embedded.logging),
         main_class: embedded.main_class.unwrap_or(base_metadata.main_class),
@@ -12,7 +12,7 @@ use crate::{
         },
         version_json::VersionJsonRootLayout,
     },
-    http::HttpSource,
+    http::{Fetched, HttpCache, HttpClientSource, HttpSource},
     loader_version::LoaderVersionId,
     maven::fetch_maven_metadata,
 };
@@ -20,7 +20,7 @@ use crate::{
 #[derive(Debug, Clone)]
 pub struct InstallerMavenArtifactSpec {
     pub coordinate: String,
-    pub download_url: String,
+    pub download_urls: Vec<String>,
     pub relative_path: PathBuf,
 }
 
@@ -46,6 +46,19 @@ where
     inner: HttpSource<E>,
 }
 
+impl<E> HttpClientSource for InstallerMavenSource<E>
+where
+    E: InstallerMavenEndpoints,
+{
+    fn set_client(&mut self, client: reqwest::Client) {
+        self.inner.set_client(client);
+    }
+
+    fn set_cache(&mut self, cache: HttpCache) {
+        self.inner.set_cache(cache);
+    }
+}
+
 impl<E> Default for InstallerMavenSource<E>
 where
     E: InstallerMavenEndpoints + Default,
@@ -75,9 +88,9 @@ where
         self.inner.endpoints()
     }
 
-    pub async fn maven_metadata(&self) -> Result<MavenMetadataBody> {
+    pub async fn maven_metadata(&self) -> Result<Fetched<MavenMetadataBody>> {
         let url = self.endpoints().maven_metadata_url()?;
-        fetch_maven_metadata(self.inner.client(), url, E::SOURCE_NAME).await
+        fetch_maven_metadata(&self.inner, url, E::SOURCE_NAME).await
     }
 
     pub fn installer_artifact<L>(
@@ -96,7 +109,7 @@ where
         build_installer_artifact(
             game_storage,
             spec.coordinate,
-            spec.download_url,
+            spec.download_urls,
             spec.relative_path,
         )
     }
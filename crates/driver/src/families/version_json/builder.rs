@@ -12,6 +12,7 @@ use elemental_core::{
     storage::{Storage, layout::Layoutable},
 };
 use elemental_schema::mojang::piston::PistonMetaData;
+use tokio::fs::create_dir_all;
 
 use super::{
     classpath::{classpath_separator, join_classpath},
@@ -20,10 +21,21 @@ use super::{
     layout::{VersionJsonInstanceLayout, VersionJsonRootLayout},
     resource::{VersionJsonInstanceResource, VersionJsonRootResource},
     rules::VersionJsonRuleContext,
-    storage::VersionJsonVersionStorageExt,
+    storage::{VersionJsonGameStorageExt, VersionJsonVersionStorageExt},
     variables::{LauncherVariables, UserType},
 };
 
+const QUICK_PLAY_MULTIPLAYER_FEATURE: &str = "is_quick_play_multiplayer";
+const DEFAULT_SERVER_PORT: &str = "25565";
+const LAUNCHWRAPPER_MAIN_CLASS: &str = "net.minecraft.launchwrapper.Launch";
+const LAUNCHWRAPPER_LIBRARY_PREFIX: &str = "net.minecraft:launchwrapper:";
+// The launchwrapper tweaker that boots vanilla applet-era clients.
+const ALPHA_VANILLA_TWEAK_CLASS: &str = "net.minecraft.launchwrapper.AlphaVanillaTweaker";
+const APPLET_MAIN_CLASSES: [&str; 2] = [
+    "net.minecraft.client.MinecraftApplet",
+    "com.mojang.minecraft.MinecraftApplet",
+];
+
 pub struct VersionJsonLaunchBuilder<
     A: Authorizer,
     L: VersionJsonRootLayout,
@@ -35,6 +47,7 @@ pub struct VersionJsonLaunchBuilder<
     inner: LauncherVariables,
     extra_jvm_arguments: Vec<String>,
     extra_game_arguments: Vec<String>,
+    demo: bool,
 }
 
 impl<A: Authorizer, L: VersionJsonRootLayout, VL: VersionJsonInstanceLayout>
@@ -48,6 +61,7 @@ impl<A: Authorizer, L: VersionJsonRootLayout, VL: VersionJsonInstanceLayout>
             inner: LauncherVariables::default(),
             extra_jvm_arguments: Vec::new(),
             extra_game_arguments: Vec::new(),
+            demo: false,
         }
     }
 
@@ -81,6 +95,12 @@ impl<A: Authorizer, L: VersionJsonRootLayout, VL: VersionJsonInstanceLayout>
         self
     }
 
+    /// Launches in demo mode even when the credential owns the game.
+    pub fn set_demo(mut self, demo: bool) -> Self {
+        self.demo = demo;
+        self
+    }
+
     pub fn set_launcher(mut self, name: String, version: String) -> Self {
         self.inner.launcher_name = name;
         self.inner.launcher_version = version;
@@ -118,15 +138,36 @@ impl<A: Authorizer, L: VersionJsonRootLayout, VL: VersionJsonInstanceLayout>
             .version
             .metadata()
             .context("read version metadata failed")?;
-        let rule_context = VersionJsonRuleContext::current();
         let credential = self
             .authorizer
             .authorize()
             .await
             .context("authorize failed")?;
+        let rule_context = self.rule_context(&credential);
         let version_name = self.version.name().context("get version name failed")?;
-        let paths = LaunchPaths::resolve(&self.version)?;
+        let version_jar = self
+            .version
+            .launch_jar()
+            .await
+            .context("resolve launch jar failed")?;
+        let asset_index_objects = self
+            .version
+            .parent
+            .asset_index_objects(metadata.asset_index.id.as_str())
+            .context("read asset index failed")?;
+        let legacy_assets_root = self
+            .version
+            .legacy_assets_root(&metadata.asset_index.id, &asset_index_objects)?;
+        let paths = LaunchPaths::resolve(&self.version, version_jar, legacy_assets_root)?;
         paths.ensure_version_jar_exists()?;
+        create_dir_all(&paths.game_directory)
+            .await
+            .with_context(|| {
+                format!(
+                    "create game directory failed: {}",
+                    paths.game_directory.display()
+                )
+            })?;
 
         self.apply_default_variables(&metadata, credential, version_name, &paths);
 
@@ -140,10 +181,54 @@ impl<A: Authorizer, L: VersionJsonRootLayout, VL: VersionJsonInstanceLayout>
 
         Ok(
             LaunchCommand::new(self.runtime.executable(), command_arguments)
-                .with_cwd(paths.version_root),
+                .with_cwd(paths.game_directory),
         )
     }
 
+    /// Feature flags for argument rules, taken before defaults fill in the resolution.
+    fn rule_context(&self, credential: &UserCredential) -> VersionJsonRuleContext {
+        VersionJsonRuleContext::current()
+            .with_feature(
+                "has_custom_resolution",
+                !self.inner.resolution_width.is_empty() && !self.inner.resolution_height.is_empty(),
+            )
+            .with_feature("is_demo_user", credential.demo || self.demo)
+            .with_feature(
+                "has_quick_plays_support",
+                self.inner.quick_play_path.is_some(),
+            )
+            .with_feature(
+                "is_quick_play_singleplayer",
+                self.inner.quick_play_singleplayer.is_some(),
+            )
+            .with_feature(
+                QUICK_PLAY_MULTIPLAYER_FEATURE,
+                self.inner.quick_play_multiplayer.is_some(),
+            )
+            .with_feature(
+                "is_quick_play_realms",
+                self.inner.quick_play_realms.is_some(),
+            )
+    }
+
+    /// `--server`/`--port` for versions that predate quick play.
+    fn legacy_quick_play_arguments(&self, metadata: &PistonMetaData) -> Vec<String> {
+        let Some(server) = &self.inner.quick_play_multiplayer else {
+            return Vec::new();
+        };
+        if metadata.has_game_argument_feature(QUICK_PLAY_MULTIPLAYER_FEATURE) {
+            return Vec::new();
+        }
+
+        let (host, port) = split_server_address(server);
+        vec![
+            "--server".to_owned(),
+            host.to_owned(),
+            "--port".to_owned(),
+            port.to_owned(),
+        ]
+    }
+
     pub fn variables(self) -> LauncherVariables {
         self.inner
     }
@@ -172,9 +257,15 @@ impl<A: Authorizer, L: VersionJsonRootLayout, VL: VersionJsonInstanceLayout>
         }
 
         self.inner.version_name = version_name;
-        self.inner.game_directory = paths.version_root.to_string_lossy().to_string();
+        self.inner.game_directory = paths.game_directory.to_string_lossy().to_string();
         self.inner.assets_root = paths.assets_root.to_string_lossy().to_string();
+        self.inner.game_assets = paths.game_assets.to_string_lossy().to_string();
         self.inner.assets_index_name = metadata.assets.clone();
+        self.inner.auth_session = if credential.access_token.is_empty() {
+            "-".to_owned()
+        } else {
+            format!("token:{}:{}", credential.access_token, credential.uuid)
+        };
         self.inner.auth_uuid = credential.uuid;
         self.inner.auth_access_token = credential.access_token;
         self.inner.user_type = if self.inner.auth_access_token.is_empty() {
@@ -195,8 +286,14 @@ impl<A: Authorizer, L: VersionJsonRootLayout, VL: VersionJsonInstanceLayout>
         raw_jvm_arguments: Vec<String>,
     ) -> Result<Vec<String>> {
         let mut arguments = self.build_jvm_arguments(metadata, raw_jvm_arguments)?;
-        arguments.push(metadata.main_class.clone());
-        arguments.extend(self.inner.apply(metadata.game_arguments(rule_context))?);
+        let (main_class, game_arguments) =
+            resolve_entry_point(metadata, metadata.game_arguments(rule_context))?;
+        arguments.push(main_class);
+        let has_server_argument = game_arguments.iter().any(|argument| argument == "--server");
+        arguments.extend(self.inner.apply(game_arguments)?);
+        if !has_server_argument {
+            arguments.extend(self.legacy_quick_play_arguments(metadata));
+        }
         arguments.extend(self.inner.apply(self.extra_game_arguments.clone())?);
         Ok(arguments)
     }
@@ -349,29 +446,42 @@ impl<A: Authorizer, L: VersionJsonRootLayout, VL: VersionJsonInstanceLayout>
 
 #[derive(Debug, Clone)]
 struct LaunchPaths {
-    version_root: PathBuf,
+    game_directory: PathBuf,
     version_jar: PathBuf,
     assets_root: PathBuf,
+    /// The legacy asset tree for `virtual` and `map_to_resources` indexes, else `assets_root`.
+    game_assets: PathBuf,
     libraries_root: PathBuf,
     natives_directory: PathBuf,
 }
 
 impl LaunchPaths {
-    fn resolve<L, VL>(version: &Storage<VL, Storage<L>>) -> Result<Self>
+    fn resolve<L, VL>(
+        version: &Storage<VL, Storage<L>>,
+        version_jar: PathBuf,
+        legacy_assets_root: Option<PathBuf>,
+    ) -> Result<Self>
     where
         L: VersionJsonRootLayout,
         VL: VersionJsonInstanceLayout,
     {
+        let assets_root = resolve_absolute_path(
+            version
+                .parent
+                .try_get_resource(VersionJsonRootResource::Assets)?,
+        )?;
+        let game_assets = match legacy_assets_root {
+            Some(path) => resolve_absolute_path(path)?,
+            None => assets_root.clone(),
+        };
+
         Ok(Self {
-            version_root: resolve_absolute_path(version.path.clone())?,
-            version_jar: resolve_absolute_path(
-                version.try_get_resource(VersionJsonInstanceResource::Jar)?,
-            )?,
-            assets_root: resolve_absolute_path(
-                version
-                    .parent
-                    .try_get_resource(VersionJsonRootResource::Assets)?,
+            game_directory: resolve_absolute_path(
+                version.try_get_resource(VersionJsonInstanceResource::GameDirectory)?,
             )?,
+            version_jar: resolve_absolute_path(version_jar)?,
+            assets_root,
+            game_assets,
             libraries_root: resolve_absolute_path(
                 version
                     .parent
@@ -395,6 +505,73 @@ impl LaunchPaths {
     }
 }
 
+/// Boots applet-era clients through launchwrapper and fills in the game arguments that
+/// old-style manifests leave out.
+fn resolve_entry_point(
+    metadata: &PistonMetaData,
+    mut game_arguments: Vec<String>,
+) -> Result<(String, Vec<String>)> {
+    if !APPLET_MAIN_CLASSES.contains(&metadata.main_class.as_str()) {
+        if metadata.main_class == LAUNCHWRAPPER_MAIN_CLASS && game_arguments.is_empty() {
+            game_arguments = legacy_game_arguments();
+        }
+        return Ok((metadata.main_class.clone(), game_arguments));
+    }
+
+    if !metadata
+        .libraries
+        .iter()
+        .any(|library| library.name.starts_with(LAUNCHWRAPPER_LIBRARY_PREFIX))
+    {
+        bail!(
+            "'{}' starts from the applet '{}' but doesn't list launchwrapper to host it",
+            metadata.id,
+            metadata.main_class
+        );
+    }
+    if game_arguments.is_empty() {
+        game_arguments = legacy_game_arguments();
+    }
+    if !game_arguments
+        .iter()
+        .any(|argument| argument == "--tweakClass")
+    {
+        game_arguments.extend([
+            "--tweakClass".to_owned(),
+            ALPHA_VANILLA_TWEAK_CLASS.to_owned(),
+        ]);
+    }
+
+    Ok((LAUNCHWRAPPER_MAIN_CLASS.to_owned(), game_arguments))
+}
+
+/// Splits `host[:port]`, accepting bracketed IPv6 hosts.
+fn split_server_address(address: &str) -> (&str, &str) {
+    if let Some(bracketed) = address.strip_prefix('[')
+        && let Some((host, rest)) = bracketed.split_once(']')
+    {
+        let port = rest.strip_prefix(':').filter(|port| !port.is_empty());
+        return (host, port.unwrap_or(DEFAULT_SERVER_PORT));
+    }
+
+    match address.rsplit_once(':') {
+        Some((host, port)) if !host.contains(':') && !port.is_empty() => (host, port),
+        _ => (address, DEFAULT_SERVER_PORT),
+    }
+}
+
+// What launchwrapper's legacy tweakers expect, as listed by pre-1.6 manifests.
+fn legacy_game_arguments() -> Vec<String> {
+    vec![
+        "${auth_player_name}".to_owned(),
+        "${auth_session}".to_owned(),
+        "--gameDir".to_owned(),
+        "${game_directory}".to_owned(),
+        "--assetsDir".to_owned(),
+        "${game_assets}".to_owned(),
+    ]
+}
+
 fn legacy_jvm_arguments() -> Vec<String> {
     vec![
         "-Djava.library.path=${natives_directory}".to_owned(),
@@ -420,3 +597,176 @@ fn resolve_absolute_path(path: PathBuf) -> Result<PathBuf> {
 
     Ok(current_dir()?.join(path))
 }
+
+#[test]
+fn test_split_server_address_handles_ports_and_ipv6_hosts() {
+    assert_eq!(split_server_address("[::1]:25566"), ("::1", "25566"));
+    assert_eq!(split_server_address("[::1]"), ("::1", DEFAULT_SERVER_PORT));
+    assert_eq!(split_server_address("host"), ("host", DEFAULT_SERVER_PORT));
+    assert_eq!(split_server_address("host:25566"), ("host", "25566"));
+    assert_eq!(
+        split_server_address("host:"),
+        ("host:", DEFAULT_SERVER_PORT)
+    );
+    assert_eq!(
+        split_server_address("2001:db8::1"),
+        ("2001:db8::1", DEFAULT_SERVER_PORT)
+    );
+}
+
+#[test]
+fn test_rule_context_enables_demo_from_credential_or_config() {
+    use elemental_core::auth::authorizers::offline::OfflineAuthorizer;
+
+    use super::layout::{BaseInstanceLayout, BaseRootLayout};
+
+    let root = std::env::temp_dir().join(format!("elemental-rules-{}", std::process::id()));
+    let builder = |demo: bool| {
+        VersionJsonLaunchBuilder::new(
+            OfflineAuthorizer {
+                username: "player".to_owned(),
+            },
+            Distribution {
+                release: None,
+                path: root.join("runtime"),
+                executable_override: None,
+                provider: "test",
+            },
+            Storage::with_parent(
+                root.join("versions/a"),
+                Storage::new(&root, BaseRootLayout),
+                BaseInstanceLayout,
+            ),
+        )
+        .set_demo(demo)
+    };
+    let credential = |demo: bool| UserCredential {
+        username: "player".to_owned(),
+        uuid: String::new(),
+        access_token: String::new(),
+        demo,
+    };
+
+    let context = builder(false).rule_context(&credential(false));
+    assert!(!context.feature_enabled("is_demo_user"));
+    assert!(!context.feature_enabled("has_custom_resolution"));
+    assert!(!context.feature_enabled(QUICK_PLAY_MULTIPLAYER_FEATURE));
+    assert!(
+        builder(false)
+            .rule_context(&credential(true))
+            .feature_enabled("is_demo_user")
+    );
+    let context = builder(true)
+        .set_resolution("1280".to_owned(), "720".to_owned())
+        .set_quick_play(None, Some("[::1]:25566".to_owned()), None, None)
+        .rule_context(&credential(false));
+    assert!(context.feature_enabled("is_demo_user"));
+    assert!(context.feature_enabled("has_custom_resolution"));
+    assert!(context.feature_enabled(QUICK_PLAY_MULTIPLAYER_FEATURE));
+}
+
+#[test]
+fn test_resolve_entry_point_hosts_applets_in_launchwrapper() {
+    let metadata = |main_class: &str, libraries: &[&str]| -> PistonMetaData {
+        let libraries = libraries
+            .iter()
+            .map(|name| {
+                serde_json::json!({
+                    "name": name,
+                    "downloads": { "artifact": { "path": "a.jar", "url": "https://example.invalid/a.jar" } },
+                })
+            })
+            .collect::<Vec<_>>();
+        serde_json::from_value(serde_json::json!({
+            "assetIndex": { "id": "pre-1.6", "sha1": "", "size": 0, "totalSize": 0, "url": "" },
+            "assets": "pre-1.6",
+            "downloads": { "client": { "sha1": "", "size": 0, "url": "" } },
+            "id": "a1.2.6",
+            "libraries": libraries,
+            "mainClass": main_class,
+            "type": "old_alpha",
+            "time": "2010-12-02T22:00:00+00:00",
+            "releaseTime": "2010-12-02T22:00:00+00:00",
+        }))
+        .unwrap()
+    };
+    let launchwrapper = ["net.minecraft:launchwrapper:1.6"];
+    let tweaked = |mut arguments: Vec<String>| {
+        arguments.extend([
+            "--tweakClass".to_owned(),
+            ALPHA_VANILLA_TWEAK_CLASS.to_owned(),
+        ]);
+        arguments
+    };
+
+    for applet in APPLET_MAIN_CLASSES {
+        let (main_class, arguments) =
+            resolve_entry_point(&metadata(applet, &launchwrapper), Vec::new()).unwrap();
+        assert_eq!(main_class, LAUNCHWRAPPER_MAIN_CLASS);
+        assert_eq!(arguments, tweaked(legacy_game_arguments()));
+    }
+
+    // Listed arguments are kept, and a tweaker already named is not doubled.
+    let listed = vec!["${auth_player_name}".to_owned()];
+    let (_, arguments) = resolve_entry_point(
+        &metadata(APPLET_MAIN_CLASSES[0], &launchwrapper),
+        listed.clone(),
+    )
+    .unwrap();
+    assert_eq!(arguments, tweaked(listed.clone()));
+    let (_, arguments) = resolve_entry_point(
+        &metadata(APPLET_MAIN_CLASSES[0], &launchwrapper),
+        tweaked(listed.clone()),
+    )
+    .unwrap();
+    assert_eq!(arguments, tweaked(listed.clone()));
+
+    // Manifests already booting launchwrapper only get the legacy arguments filled in.
+    let (main_class, arguments) = resolve_entry_point(
+        &metadata(LAUNCHWRAPPER_MAIN_CLASS, &launchwrapper),
+        Vec::new(),
+    )
+    .unwrap();
+    assert_eq!(main_class, LAUNCHWRAPPER_MAIN_CLASS);
+    assert_eq!(arguments, legacy_game_arguments());
+    let (main_class, arguments) = resolve_entry_point(
+        &metadata("net.minecraft.client.main.Main", &[]),
+        listed.clone(),
+    )
+    .unwrap();
+    assert_eq!(main_class, "net.minecraft.client.main.Main");
+    assert_eq!(arguments, listed);
+
+    let error = resolve_entry_point(
+        &metadata(APPLET_MAIN_CLASSES[1], &["org.lwjgl:lwjgl:2"]),
+        Vec::new(),
+    )
+    .unwrap_err()
+    .to_string();
+    assert!(error.contains("doesn't list launchwrapper"), "{error}");
+}
+
+#[test]
+fn test_launch_paths_point_game_assets_at_the_legacy_tree() {
+    use super::layout::{BaseInstanceLayout, BaseRootLayout};
+
+    let root = std::env::temp_dir().join(format!("elemental-launch-paths-{}", std::process::id()));
+    let version = Storage::with_parent(
+        root.join("versions/a"),
+        Storage::new(&root, BaseRootLayout),
+        BaseInstanceLayout,
+    );
+    let jar = root.join("versions/a/a.jar");
+
+    let paths = LaunchPaths::resolve(&version, jar.clone(), None).unwrap();
+    assert_eq!(paths.assets_root, root.join("assets"));
+    assert_eq!(paths.game_assets, paths.assets_root);
+
+    let virtual_root = root.join("assets/virtual/legacy");
+    let paths = LaunchPaths::resolve(&version, jar.clone(), Some(virtual_root.clone())).unwrap();
+    assert_eq!(paths.assets_root, root.join("assets"));
+    assert_eq!(paths.game_assets, virtual_root);
+
+    let paths = LaunchPaths::resolve(&version, jar, Some(PathBuf::from("resources"))).unwrap();
+    assert_eq!(paths.game_assets, current_dir().unwrap().join("resources"));
+}
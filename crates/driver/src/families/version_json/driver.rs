@@ -180,13 +180,16 @@ where
             .family
             .profile(&self.source, &game_version, &loader_version)
             .await?;
-        let metadata = self.family.merge_profile(base_metadata.metadata, profile)?;
+        let metadata = self
+            .family
+            .merge_profile(base_metadata.metadata, profile.value)?;
 
         Ok(ResolvedVersionJsonMetadata::new(
             self.remote_resolver(),
             metadata,
             base_metadata.asset_index_objects,
-        ))
+        )
+        .with_stale(base_metadata.stale || profile.stale))
     }
 }
 
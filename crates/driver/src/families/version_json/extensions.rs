@@ -8,8 +8,20 @@ use super::{PistonMetaRulesExt, VersionJsonPlatform, VersionJsonRuleContext};
 pub trait PistonMetaDataExt {
     fn jvm_arguments(&self, context: &VersionJsonRuleContext) -> Vec<String>;
     fn game_arguments(&self, context: &VersionJsonRuleContext) -> Vec<String>;
+    /// `javaVersion.majorVersion`, inferred from the release time for old-style manifests.
+    fn java_major_version(&self) -> usize;
+    /// Whether any game argument rule is gated on `feature`.
+    fn has_game_argument_feature(&self, feature: &str) -> bool;
 }
 
+// First snapshots that raised the required runtime, by release date.
+const JAVA_MAJOR_VERSION_CUTOFFS: [(&str, usize); 3] = [
+    ("2024-04-03", 21), // 24w14a
+    ("2021-11-03", 17), // 21w44a
+    ("2021-05-12", 16), // 21w19a
+];
+const LEGACY_JAVA_MAJOR_VERSION: usize = 8;
+
 impl PistonMetaDataExt for PistonMetaData {
     fn jvm_arguments(&self, context: &VersionJsonRuleContext) -> Vec<String> {
         self.arguments
@@ -33,6 +45,33 @@ impl PistonMetaDataExt for PistonMetaData {
             .map(str::to_owned)
             .collect()
     }
+
+    fn java_major_version(&self) -> usize {
+        if let Some(java_version) = &self.java_version {
+            return java_version.major_version;
+        }
+
+        // `releaseTime` is RFC 3339, so its date prefix orders lexicographically.
+        let release_date = self.release_time.get(..10).unwrap_or_default();
+        JAVA_MAJOR_VERSION_CUTOFFS
+            .iter()
+            .find(|(cutoff, _)| release_date >= *cutoff)
+            .map(|(_, major_version)| *major_version)
+            .unwrap_or(LEGACY_JAVA_MAJOR_VERSION)
+    }
+
+    fn has_game_argument_feature(&self, feature: &str) -> bool {
+        self.arguments
+            .iter()
+            .flat_map(|arguments| &arguments.game)
+            .filter_map(|argument| match argument {
+                PistonMetaGenericArgument::Rule(rule_argument) => Some(&rule_argument.rules),
+                PistonMetaGenericArgument::Plain(_) => None,
+            })
+            .flatten()
+            .filter_map(|rule| rule.features.as_ref())
+            .any(|features| features.contains_key(feature))
+    }
 }
 
 pub trait PistonMetaLibrariesExt {
@@ -152,3 +191,77 @@ fn extend_argument_values(target: &mut Vec<String>, argument: &PistonMetaRuleArg
         }
     }
 }
+
+#[test]
+fn test_old_style_manifests_deserialize_and_infer_java_major_version() {
+    let manifest = |id: &str, main_class: &str, release_time: &str| {
+        serde_json::json!({
+            "assetIndex": { "id": "pre-1.6", "sha1": "", "size": 0, "totalSize": 0, "url": "" },
+            "assets": "pre-1.6",
+            "downloads": { "client": { "sha1": "", "size": 0, "url": "" } },
+            "id": id,
+            "libraries": [{
+                "name": "net.minecraft:launchwrapper:1.6",
+                "downloads": { "artifact": {
+                    "path": "net/minecraft/launchwrapper/1.6/launchwrapper-1.6.jar",
+                    "url": "https://libraries.minecraft.net/net/minecraft/launchwrapper/1.6/launchwrapper-1.6.jar",
+                } },
+            }],
+            "mainClass": main_class,
+            "minimumLauncherVersion": 7,
+            "type": "old_alpha",
+            "time": release_time,
+            "releaseTime": release_time,
+        })
+    };
+
+    for (id, main_class, release_time) in [
+        (
+            "b1.7.3",
+            "net.minecraft.launchwrapper.Launch",
+            "2011-07-07T22:00:00+00:00",
+        ),
+        (
+            "a1.2.6",
+            "net.minecraft.client.MinecraftApplet",
+            "2010-12-02T22:00:00+00:00",
+        ),
+        (
+            "c0.30_01c",
+            "com.mojang.minecraft.MinecraftApplet",
+            "2009-12-21T22:00:00+00:00",
+        ),
+    ] {
+        let metadata: PistonMetaData =
+            serde_json::from_value(manifest(id, main_class, release_time)).unwrap();
+        assert!(metadata.java_version.is_none(), "{id}");
+        assert!(metadata.logging.is_none(), "{id}");
+        assert_eq!(metadata.compliance_level, 0, "{id}");
+        assert!(metadata.arguments.is_none(), "{id}");
+        assert_eq!(
+            metadata.java_major_version(),
+            LEGACY_JAVA_MAJOR_VERSION,
+            "{id}"
+        );
+    }
+
+    let released = |release_time: &str| {
+        serde_json::from_value::<PistonMetaData>(manifest("snapshot", "Main", release_time))
+            .unwrap()
+            .java_major_version()
+    };
+    assert_eq!(released("2021-05-11T10:00:00+00:00"), 8);
+    assert_eq!(released("2021-05-12T10:00:00+00:00"), 16);
+    assert_eq!(released("2021-11-02T10:00:00+00:00"), 16);
+    assert_eq!(released("2021-11-03T10:00:00+00:00"), 17);
+    assert_eq!(released("2024-04-02T10:00:00+00:00"), 17);
+    assert_eq!(released("2024-04-03T10:00:00+00:00"), 21);
+    assert_eq!(released(""), LEGACY_JAVA_MAJOR_VERSION);
+
+    // An explicit runtime always wins over the release date.
+    let mut metadata = manifest("1.20.5", "Main", "2024-04-23T10:00:00+00:00");
+    metadata["javaVersion"] =
+        serde_json::json!({ "component": "java-runtime-delta", "majorVersion": 22 });
+    let metadata: PistonMetaData = serde_json::from_value(metadata).unwrap();
+    assert_eq!(metadata.java_major_version(), 22);
+}
@@ -9,6 +9,7 @@ use elemental_schema::mojang::piston::PistonMetaData;
 use crate::{
     driver::{DriverDescriptor, InstalledDriver},
     drivers::vanilla::source::VanillaSource,
+    http::Fetched,
     loader_version::LoaderVersionId,
 };
 
@@ -35,7 +36,7 @@ pub trait ProfiledVersionJsonFamily: Clone + Debug + Send + Sync + 'static {
         source: &Self::Source,
         game_version: &MinecraftVersionId,
         loader_version: &LoaderVersionId,
-    ) -> Result<Self::Profile>;
+    ) -> Result<Fetched<Self::Profile>>;
 
     fn merge_profile(
         &self,
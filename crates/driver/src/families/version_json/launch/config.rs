@@ -31,6 +31,7 @@ pub struct VersionJsonLaunchConfig {
     pub quick_play: Option<QuickPlayOptions>,
     pub extra_jvm_arguments: Vec<String>,
     pub extra_game_arguments: Vec<String>,
+    pub demo: bool,
 }
 
 impl LaunchResolution {
@@ -68,6 +69,7 @@ impl VersionJsonLaunchConfig {
             quick_play: None,
             extra_jvm_arguments: Vec::new(),
             extra_game_arguments: Vec::new(),
+            demo: false,
         }
     }
 
@@ -91,6 +93,11 @@ impl VersionJsonLaunchConfig {
         self
     }
 
+    pub fn set_demo(mut self, demo: bool) -> Self {
+        self.demo = demo;
+        self
+    }
+
     pub fn try_set_extra_jvm_argument_string(
         mut self,
         extra_jvm_argument_string: String,
@@ -8,8 +8,9 @@ use elemental_core::{
 };
 
 use crate::families::version_json::{
-    LaunchedVersionJsonInstance, PreparedVersionJsonInstance, VersionJsonInstanceLayout,
-    VersionJsonRemoteResolver, VersionJsonRootLayout, builder::VersionJsonLaunchBuilder,
+    LaunchedVersionJsonInstance, PreparedVersionJsonInstance, VersionJsonGameStorageExt,
+    VersionJsonInstanceLayout, VersionJsonRemoteResolver, VersionJsonRootLayout,
+    builder::VersionJsonLaunchBuilder,
 };
 
 pub mod arguments;
@@ -31,6 +32,19 @@ where
 {
     let required_major_version =
         runtime_major_version.unwrap_or_else(|| prepared_version.required_java_major_version());
+    // Runtimes installed into the game root take precedence over the system ones.
+    if runtime_executable_path.is_none()
+        && let Some(runtime) = prepared_version
+            .resolved_version
+            .version
+            .parent
+            .runtimes()
+            .await?
+            .into_iter()
+            .find(|runtime| runtime.matches_java_major_version(required_major_version))
+    {
+        return Ok(runtime);
+    }
 
     resolve_runtime(
         required_major_version,
@@ -80,6 +94,10 @@ where
         );
     }
 
+    if config.demo {
+        builder = builder.set_demo(true);
+    }
+
     if !config.extra_jvm_arguments.is_empty() {
         builder = builder.set_extra_jvm_arguments(config.extra_jvm_arguments.clone());
     }
@@ -1,15 +1,40 @@
-use std::path::{Path, PathBuf};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
 
-use elemental_core::storage::layout::Layout;
+use anyhow::{Context, Result};
+use elemental_core::storage::{Storage, layout::Layout};
+use elemental_shared::scope::Scope;
 
 use super::resource::{VersionJsonInstanceResource, VersionJsonRootResource};
 
+const XDG_APPLICATION_DIR: &str = "elemental";
+
 #[derive(Debug, Clone, Default)]
 pub struct BaseRootLayout;
 
 #[derive(Debug, Clone, Default)]
 pub struct BaseInstanceLayout;
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct XdgRootLayout {
+    pub cache_root: PathBuf,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum GameDirectoryIsolation {
+    #[default]
+    PerVersion,
+    Shared(PathBuf),
+    Custom(HashMap<String, PathBuf>),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct IsolatedInstanceLayout {
+    pub isolation: GameDirectoryIsolation,
+}
+
 impl Layout for BaseRootLayout {
     type Resource = VersionJsonRootResource;
     type ExtendedResource = VersionJsonRootResource;
@@ -19,8 +44,10 @@ impl Layout for BaseRootLayout {
         let asset_indexes_root = assets_root.join("indexes");
         let asset_objects_root = assets_root.join("objects");
         let asset_log_configs_root = assets_root.join("log_configs");
+        let asset_virtual_root = assets_root.join("virtual");
         let versions_root = root.join("versions");
         let libraries_root = root.join("libraries");
+        let runtimes_root = root.join("runtime");
 
         match resource {
             VersionJsonRootResource::Assets => Some(assets_root),
@@ -41,6 +68,10 @@ impl Layout for BaseRootLayout {
                 Some(id) => Some(asset_log_configs_root.join(id)),
                 None => Some(asset_log_configs_root),
             },
+            VersionJsonRootResource::AssetVirtual(id) => match id.filter(|id| !id.is_empty()) {
+                Some(id) => Some(asset_virtual_root.join(id)),
+                None => Some(asset_virtual_root),
+            },
             VersionJsonRootResource::Versions(name) => match name.filter(|name| !name.is_empty()) {
                 Some(name) => Some(versions_root.join(name)),
                 None => Some(versions_root),
@@ -51,6 +82,14 @@ impl Layout for BaseRootLayout {
                     None => Some(libraries_root),
                 }
             }
+            VersionJsonRootResource::Runtimes(name) => match name.filter(|name| !name.is_empty()) {
+                Some(name) => Some(runtimes_root.join(name)),
+                None => Some(runtimes_root),
+            },
+            VersionJsonRootResource::Elemental(path) => match path {
+                Some(path) => Some(root.join(".elemental").join(path)),
+                None => Some(root.join(".elemental")),
+            },
         }
     }
 
@@ -67,6 +106,67 @@ impl Layout for BaseRootLayout {
     }
 }
 
+impl XdgRootLayout {
+    pub fn new(cache_root: impl Into<PathBuf>) -> Self {
+        Self {
+            cache_root: cache_root.into(),
+        }
+    }
+
+    pub fn from_scopes() -> Result<Self> {
+        Ok(Self::new(xdg_scope_root(Scope::Cache)?))
+    }
+
+    pub fn data_root() -> Result<PathBuf> {
+        xdg_scope_root(Scope::Data)
+    }
+
+    pub fn state_root() -> Result<PathBuf> {
+        xdg_scope_root(Scope::State)
+    }
+
+    /// Where the download journal keeps the plans of unfinished sessions.
+    pub fn journal_root() -> Result<PathBuf> {
+        Ok(Self::state_root()?.join("journal"))
+    }
+
+    /// Where cached metadata responses live, next to the shared downloads they describe.
+    pub fn metadata_cache_root(&self) -> PathBuf {
+        self.cache_root.join("metadata")
+    }
+
+    pub fn storage() -> Result<Storage<Self>> {
+        Ok(Storage::new(Self::data_root()?, Self::from_scopes()?))
+    }
+}
+
+impl Layout for XdgRootLayout {
+    type Resource = VersionJsonRootResource;
+    type ExtendedResource = VersionJsonRootResource;
+
+    fn get_resource(&self, root: &Path, resource: Self::Resource) -> Option<PathBuf> {
+        match resource {
+            // Installed versions, runtimes and launcher state are data, the rest can be fetched again.
+            VersionJsonRootResource::Versions(_)
+            | VersionJsonRootResource::Runtimes(_)
+            | VersionJsonRootResource::Elemental(_) => BaseRootLayout.get_resource(root, resource),
+            _ => BaseRootLayout.get_resource(&self.cache_root, resource),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "XdgRoot"
+    }
+
+    fn get_extended_resource(
+        &self,
+        root: &Path,
+        resource: Self::ExtendedResource,
+    ) -> Option<PathBuf> {
+        self.get_resource(root, resource)
+    }
+}
+
 impl Layout for BaseInstanceLayout {
     type Resource = VersionJsonInstanceResource;
     type ExtendedResource = VersionJsonInstanceResource;
@@ -81,13 +181,25 @@ impl Layout for BaseInstanceLayout {
                 let name = root.file_name()?.to_string_lossy().to_string();
                 Some(root.join(format!("{name}.jar")))
             }
+            VersionJsonInstanceResource::PatchedJar => {
+                let name = root.file_name()?.to_string_lossy().to_string();
+                Some(root.join(format!("{name}-patched.jar")))
+            }
+            VersionJsonInstanceResource::Jarmods(name) => {
+                match name.filter(|name| !name.is_empty()) {
+                    Some(name) => Some(root.join("jarmods").join(name)),
+                    None => Some(root.join("jarmods")),
+                }
+            }
             VersionJsonInstanceResource::Natives => Some(root.join("natives")),
+            VersionJsonInstanceResource::GameDirectory => Some(root.to_path_buf()),
             VersionJsonInstanceResource::Logs => Some(root.join("logs")),
             VersionJsonInstanceResource::Configs => Some(root.join("config")),
             VersionJsonInstanceResource::ShaderPacks => Some(root.join("shaderpacks")),
             VersionJsonInstanceResource::ResourcePacks => Some(root.join("resourcepacks")),
             VersionJsonInstanceResource::Saves => Some(root.join("saves")),
             VersionJsonInstanceResource::Mods => Some(root.join("mods")),
+            VersionJsonInstanceResource::Resources => Some(root.join("resources")),
             VersionJsonInstanceResource::Elemental(path) => match path {
                 Some(path) => Some(root.join(".elemental").join(path)),
                 None => Some(root.join(".elemental")),
@@ -108,9 +220,231 @@ impl Layout for BaseInstanceLayout {
     }
 }
 
+impl IsolatedInstanceLayout {
+    pub fn new(isolation: GameDirectoryIsolation) -> Self {
+        Self { isolation }
+    }
+
+    pub fn per_version() -> Self {
+        Self::new(GameDirectoryIsolation::PerVersion)
+    }
+
+    pub fn shared(game_directory: impl Into<PathBuf>) -> Self {
+        Self::new(GameDirectoryIsolation::Shared(game_directory.into()))
+    }
+
+    pub fn custom() -> Self {
+        Self::new(GameDirectoryIsolation::Custom(HashMap::new()))
+    }
+
+    pub fn with_instance_directory(
+        mut self,
+        instance_name: impl Into<String>,
+        game_directory: impl Into<PathBuf>,
+    ) -> Self {
+        let instance_name = instance_name.into();
+        let game_directory = game_directory.into();
+        match &mut self.isolation {
+            GameDirectoryIsolation::Custom(directories) => {
+                directories.insert(instance_name, game_directory);
+            }
+            _ => {
+                self.isolation = GameDirectoryIsolation::Custom(HashMap::from([(
+                    instance_name,
+                    game_directory,
+                )]));
+            }
+        }
+        self
+    }
+
+    fn game_directory(&self, root: &Path) -> Option<PathBuf> {
+        match &self.isolation {
+            GameDirectoryIsolation::PerVersion => Some(root.to_path_buf()),
+            GameDirectoryIsolation::Shared(game_directory) => Some(game_directory.clone()),
+            GameDirectoryIsolation::Custom(directories) => {
+                let name = root.file_name()?.to_string_lossy();
+                Some(
+                    directories
+                        .get(name.as_ref())
+                        .cloned()
+                        .unwrap_or_else(|| root.to_path_buf()),
+                )
+            }
+        }
+    }
+}
+
+impl Layout for IsolatedInstanceLayout {
+    type Resource = VersionJsonInstanceResource;
+    type ExtendedResource = VersionJsonInstanceResource;
+
+    fn get_resource(&self, root: &Path, resource: Self::Resource) -> Option<PathBuf> {
+        match resource {
+            VersionJsonInstanceResource::GameDirectory
+            | VersionJsonInstanceResource::Logs
+            | VersionJsonInstanceResource::Configs
+            | VersionJsonInstanceResource::ShaderPacks
+            | VersionJsonInstanceResource::ResourcePacks
+            | VersionJsonInstanceResource::Saves
+            | VersionJsonInstanceResource::Mods
+            | VersionJsonInstanceResource::Resources => {
+                BaseInstanceLayout.get_resource(&self.game_directory(root)?, resource)
+            }
+            _ => BaseInstanceLayout.get_resource(root, resource),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "IsolatedInstance"
+    }
+
+    fn get_extended_resource(
+        &self,
+        root: &Path,
+        resource: Self::ExtendedResource,
+    ) -> Option<PathBuf> {
+        self.get_resource(root, resource)
+    }
+}
+
+fn xdg_scope_root(scope: Scope) -> Result<PathBuf> {
+    Ok(scope
+        .path()
+        .with_context(|| format!("there is no valid {scope:?} directory"))?
+        .join(XDG_APPLICATION_DIR))
+}
+
 pub trait VersionJsonRootLayout: Layout<Resource = VersionJsonRootResource> {}
 
 pub trait VersionJsonInstanceLayout: Layout<Resource = VersionJsonInstanceResource> {}
 
 impl<L> VersionJsonRootLayout for L where L: Layout<Resource = VersionJsonRootResource> {}
 impl<L> VersionJsonInstanceLayout for L where L: Layout<Resource = VersionJsonInstanceResource> {}
+
+#[test]
+fn test_xdg_root_layout_keeps_data_apart_from_refetchable_downloads() {
+    let data = Path::new("/data/elemental");
+    let layout = XdgRootLayout::new("/cache/elemental");
+    let resolve = |resource| layout.get_resource(data, resource).unwrap();
+
+    assert_eq!(
+        resolve(VersionJsonRootResource::Versions(Some("a".to_owned()))),
+        data.join("versions/a")
+    );
+    assert_eq!(
+        resolve(VersionJsonRootResource::Runtimes(Some("java-8".to_owned()))),
+        data.join("runtime/java-8")
+    );
+    assert_eq!(
+        resolve(VersionJsonRootResource::Elemental(Some(PathBuf::from(
+            "locks/root.lock"
+        )))),
+        data.join(".elemental/locks/root.lock")
+    );
+    assert_eq!(
+        resolve(VersionJsonRootResource::Libraries(Some(PathBuf::from(
+            "org/a/a-1.jar"
+        )))),
+        Path::new("/cache/elemental/libraries/org/a/a-1.jar")
+    );
+    assert_eq!(
+        resolve(VersionJsonRootResource::AssetObjects(Some(
+            "aa11".to_owned()
+        ))),
+        Path::new("/cache/elemental/assets/objects/aa/aa11")
+    );
+    assert_eq!(
+        resolve(VersionJsonRootResource::AssetVirtual(Some(
+            "legacy".to_owned()
+        ))),
+        Path::new("/cache/elemental/assets/virtual/legacy")
+    );
+    assert_eq!(
+        layout.metadata_cache_root(),
+        Path::new("/cache/elemental/metadata")
+    );
+    assert_eq!(
+        BaseRootLayout
+            .get_resource(data, VersionJsonRootResource::Runtimes(None))
+            .unwrap(),
+        data.join("runtime")
+    );
+}
+
+#[test]
+fn test_isolated_instance_layout_moves_only_private_resources() {
+    let root = Path::new("/games/versions/a");
+    let private = [
+        (VersionJsonInstanceResource::GameDirectory, ""),
+        (VersionJsonInstanceResource::Logs, "logs"),
+        (VersionJsonInstanceResource::Configs, "config"),
+        (VersionJsonInstanceResource::ShaderPacks, "shaderpacks"),
+        (VersionJsonInstanceResource::ResourcePacks, "resourcepacks"),
+        (VersionJsonInstanceResource::Saves, "saves"),
+        (VersionJsonInstanceResource::Mods, "mods"),
+        (VersionJsonInstanceResource::Resources, "resources"),
+    ];
+    let owned = [
+        (VersionJsonInstanceResource::Metadata, "a.json"),
+        (VersionJsonInstanceResource::Jar, "a.jar"),
+        (VersionJsonInstanceResource::PatchedJar, "a-patched.jar"),
+        (VersionJsonInstanceResource::Jarmods(None), "jarmods"),
+        (VersionJsonInstanceResource::Natives, "natives"),
+        (VersionJsonInstanceResource::Elemental(None), ".elemental"),
+        (
+            VersionJsonInstanceResource::Elemental(Some(PathBuf::from("jarmods.json"))),
+            ".elemental/jarmods.json",
+        ),
+    ];
+    let assert_layout = |layout: &IsolatedInstanceLayout, root: &Path, game_directory: &Path| {
+        for (resource, relative) in &private {
+            assert_eq!(
+                layout.get_resource(root, resource.clone()).unwrap(),
+                game_directory.join(relative),
+                "{resource:?}"
+            );
+        }
+        for (resource, relative) in &owned {
+            assert_eq!(
+                layout.get_resource(root, resource.clone()).unwrap(),
+                root.join(relative),
+                "{resource:?}"
+            );
+        }
+    };
+
+    assert_layout(&IsolatedInstanceLayout::per_version(), root, root);
+    assert_layout(&IsolatedInstanceLayout::default(), root, root);
+
+    let shared = Path::new("/games/shared");
+    let layout = IsolatedInstanceLayout::shared(shared);
+    assert_layout(&layout, root, shared);
+    assert_eq!(
+        layout
+            .get_resource(
+                Path::new("/games/versions/b"),
+                VersionJsonInstanceResource::Saves
+            )
+            .unwrap(),
+        shared.join("saves")
+    );
+
+    let custom = Path::new("/games/custom-a");
+    let layout = IsolatedInstanceLayout::custom().with_instance_directory("a", custom);
+    assert_layout(&layout, root, custom);
+    // Instances without a directory of their own keep the per-version one.
+    let other = Path::new("/games/versions/b");
+    let layout = IsolatedInstanceLayout::shared(shared).with_instance_directory("a", custom);
+    assert_eq!(
+        layout.isolation,
+        GameDirectoryIsolation::Custom(HashMap::from([("a".to_owned(), custom.to_path_buf())]))
+    );
+    assert_layout(&layout, root, custom);
+    assert_eq!(
+        layout
+            .get_resource(other, VersionJsonInstanceResource::Saves)
+            .unwrap(),
+        other.join("saves")
+    );
+}
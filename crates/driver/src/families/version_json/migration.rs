@@ -0,0 +1,227 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use elemental_core::storage::{Storage, StorageLockMode, layout::Layoutable};
+
+use super::{
+    layout::VersionJsonRootLayout, resource::VersionJsonRootResource,
+    storage::VersionJsonGameStorageExt,
+};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RootMigrationReport {
+    pub moved: Vec<(PathBuf, PathBuf)>,
+    pub conflicts: Vec<PathBuf>,
+}
+
+impl RootMigrationReport {
+    pub fn is_complete(&self) -> bool {
+        self.conflicts.is_empty()
+    }
+}
+
+pub fn migrate_root_layout<L, NL>(
+    legacy: &Storage<L>,
+    target: &Storage<NL>,
+) -> Result<RootMigrationReport>
+where
+    L: VersionJsonRootLayout,
+    NL: VersionJsonRootLayout,
+{
+    let _legacy_lock = legacy.try_lock_root(StorageLockMode::Exclusive)?;
+    let _target_lock = if legacy.path == target.path {
+        None
+    } else {
+        Some(target.try_lock_root(StorageLockMode::Exclusive)?)
+    };
+    let mut report = RootMigrationReport::default();
+
+    // Specific asset directories go first so layouts that split them up still land correctly,
+    // then whatever else lives under `assets` (legacy virtual trees, skins) follows.
+    for resource in [
+        VersionJsonRootResource::Versions(None),
+        VersionJsonRootResource::Libraries(None),
+        VersionJsonRootResource::AssetIndexes(None),
+        VersionJsonRootResource::AssetObjects(None),
+        VersionJsonRootResource::AssetLogConfigs(None),
+        VersionJsonRootResource::Assets,
+        VersionJsonRootResource::Runtimes(None),
+    ] {
+        let source = legacy.try_get_resource(resource.clone())?;
+        let destination = target.try_get_resource(resource)?;
+        if !source.exists() || source == destination {
+            continue;
+        }
+
+        move_merging(&source, &destination, &mut report).with_context(|| {
+            format!(
+                "migrate {} to {} failed",
+                source.display(),
+                destination.display()
+            )
+        })?;
+    }
+
+    // Root state moves entry by entry, the held root locks stay where they are.
+    let source = legacy.try_get_resource(VersionJsonRootResource::Elemental(None))?;
+    let destination = target.try_get_resource(VersionJsonRootResource::Elemental(None))?;
+    if source.is_dir() && source != destination {
+        for entry in source.read_dir()? {
+            let entry = entry?;
+            if entry.file_name() == "locks" {
+                continue;
+            }
+            let destination = destination.join(entry.file_name());
+            move_merging(&entry.path(), &destination, &mut report).with_context(|| {
+                format!(
+                    "migrate {} to {} failed",
+                    entry.path().display(),
+                    destination.display()
+                )
+            })?;
+        }
+    }
+
+    Ok(report)
+}
+
+fn move_merging(source: &Path, destination: &Path, report: &mut RootMigrationReport) -> Result<()> {
+    if !destination.exists() {
+        if let Some(parent) = destination.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        move_entry(source, destination)?;
+        report
+            .moved
+            .push((source.to_path_buf(), destination.to_path_buf()));
+        return Ok(());
+    }
+
+    if !source.is_dir() || !destination.is_dir() {
+        report.conflicts.push(source.to_path_buf());
+        return Ok(());
+    }
+
+    for entry in source.read_dir()? {
+        let entry = entry?;
+        move_merging(&entry.path(), &destination.join(entry.file_name()), report)?;
+    }
+
+    if source.read_dir()?.next().is_none() {
+        fs::remove_dir(source)?;
+    }
+
+    Ok(())
+}
+
+fn move_entry(source: &Path, destination: &Path) -> Result<()> {
+    if fs::rename(source, destination).is_ok() {
+        return Ok(());
+    }
+
+    // Renaming fails across file systems, so fall back to copying and removing the source.
+    move_by_copying(source, destination)
+}
+
+fn move_by_copying(source: &Path, destination: &Path) -> Result<()> {
+    copy_entry(source, destination)?;
+    if source.is_dir() {
+        fs::remove_dir_all(source)?;
+    } else {
+        fs::remove_file(source)?;
+    }
+
+    Ok(())
+}
+
+fn copy_entry(source: &Path, destination: &Path) -> Result<()> {
+    if !source.is_dir() {
+        fs::copy(source, destination)?;
+        return Ok(());
+    }
+
+    fs::create_dir_all(destination)?;
+    for entry in source.read_dir()? {
+        let entry = entry?;
+        copy_entry(&entry.path(), &destination.join(entry.file_name()))?;
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_migrate_root_layout_moves_shared_files_runtimes_and_state() {
+    use super::layout::{BaseRootLayout, XdgRootLayout};
+
+    let root = std::env::temp_dir().join(format!("elemental-migration-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    let write = |relative: &str, contents: &[u8]| {
+        let path = root.join(relative);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, contents).unwrap();
+    };
+    write("legacy/versions/a/a.json", b"{}");
+    write("legacy/libraries/org/a/a-1.jar", b"library");
+    write("legacy/assets/objects/aa/aa11", b"object");
+    write("legacy/assets/virtual/legacy/a.ogg", b"sound");
+    write("legacy/runtime/java-8/bin/java", b"java");
+    write("legacy/.elemental/jarmods/state.json", b"{}");
+    // Already present at the target, left in place and reported.
+    write("data/versions/a/a.json", b"{\"kept\":true}");
+
+    let legacy = Storage::new(root.join("legacy"), BaseRootLayout);
+    let target = Storage::new(root.join("data"), XdgRootLayout::new(root.join("cache")));
+    let report = migrate_root_layout(&legacy, &target).unwrap();
+
+    assert_eq!(
+        report.conflicts,
+        vec![root.join("legacy/versions/a/a.json")]
+    );
+    assert!(!report.is_complete());
+    for (relative, contents) in [
+        ("cache/libraries/org/a/a-1.jar", b"library".as_slice()),
+        ("cache/assets/objects/aa/aa11", b"object"),
+        ("cache/assets/virtual/legacy/a.ogg", b"sound"),
+        ("data/runtime/java-8/bin/java", b"java"),
+        ("data/.elemental/jarmods/state.json", b"{}"),
+        ("data/versions/a/a.json", b"{\"kept\":true}"),
+    ] {
+        assert_eq!(
+            fs::read(root.join(relative)).unwrap(),
+            contents,
+            "{relative}"
+        );
+    }
+    assert!(!root.join("legacy/libraries").exists());
+    assert!(!root.join("legacy/runtime").exists());
+    assert!(!root.join("legacy/.elemental/jarmods").exists());
+    assert!(root.join("legacy/.elemental/locks").is_dir());
+
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn test_move_by_copying_replaces_renames_across_file_systems() {
+    let root =
+        std::env::temp_dir().join(format!("elemental-migration-copy-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    let source = root.join("source");
+    fs::create_dir_all(source.join("nested")).unwrap();
+    fs::write(source.join("a.jar"), b"a").unwrap();
+    fs::write(source.join("nested/b.jar"), b"b").unwrap();
+    fs::write(root.join("single.jar"), b"single").unwrap();
+
+    move_by_copying(&source, &root.join("moved")).unwrap();
+    move_by_copying(&root.join("single.jar"), &root.join("moved/single.jar")).unwrap();
+
+    assert!(!source.exists());
+    assert!(!root.join("single.jar").exists());
+    assert_eq!(fs::read(root.join("moved/a.jar")).unwrap(), b"a");
+    assert_eq!(fs::read(root.join("moved/nested/b.jar")).unwrap(), b"b");
+    assert_eq!(fs::read(root.join("moved/single.jar")).unwrap(), b"single");
+
+    fs::remove_dir_all(&root).unwrap();
+}
@@ -6,6 +6,7 @@ pub mod extensions;
 pub mod family;
 pub mod launch;
 pub mod layout;
+pub mod migration;
 pub mod platform;
 pub mod prepared;
 pub mod profile;
@@ -26,8 +27,10 @@ pub use launch::{
     parse_argument_string, resolve_prepared_version_runtime,
 };
 pub use layout::{
-    BaseInstanceLayout, BaseRootLayout, VersionJsonInstanceLayout, VersionJsonRootLayout,
+    BaseInstanceLayout, BaseRootLayout, GameDirectoryIsolation, IsolatedInstanceLayout,
+    VersionJsonInstanceLayout, VersionJsonRootLayout, XdgRootLayout,
 };
+pub use migration::{RootMigrationReport, migrate_root_layout};
 pub use platform::VersionJsonPlatform;
 pub use prepared::{
     LaunchedVersionJsonInstance, PreparedVersionJsonInstance, ResolvedVersionJsonInstance,
@@ -44,4 +47,5 @@ pub use rules::{
     OperatingSystemExt, PistonMetaRuleExt, PistonMetaRulesExt, VersionJsonRuleContext,
 };
 pub use source::{LoaderMetaEndpoints, LoaderMetaSource, LoaderProfileEndpoints};
+pub use state::{ExtractedNative, NativeArtifactReport, NativeConflict, NativesExtractionReport};
 pub use storage::{VersionJsonGameStorageExt, VersionJsonVersionStorageExt, inspect_instances};
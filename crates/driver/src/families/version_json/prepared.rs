@@ -8,7 +8,7 @@ use elemental_core::{
 use elemental_infra::downloader::{
     core::ElementalDownloader,
     plan::DownloadPlanner,
-    task::{DownloadExecutionPolicy, DownloadPlan, DownloadTask},
+    task::{DownloadExecutionPolicy, DownloadPlan, DownloadPriority, DownloadTask},
 };
 use elemental_schema::mojang::piston::{
     PistonMetaAssetIndexObjects, PistonMetaData, PistonMetaLibraries,
@@ -16,9 +16,10 @@ use elemental_schema::mojang::piston::{
 };
 
 use crate::families::version_json::{
-    PistonMetaLibrariesExt, VersionJsonGameStorageExt, VersionJsonInstanceLayout,
-    VersionJsonInstanceResource, VersionJsonRootLayout, VersionJsonRootResource,
-    VersionJsonRuleContext, VersionJsonVersionStorageExt, remote::VersionJsonRemoteResolver,
+    PistonMetaDataExt, PistonMetaLibrariesExt, VersionJsonGameStorageExt,
+    VersionJsonInstanceLayout, VersionJsonInstanceResource, VersionJsonRootLayout,
+    VersionJsonRootResource, VersionJsonRuleContext, VersionJsonVersionStorageExt,
+    remote::VersionJsonRemoteResolver,
 };
 
 #[derive(Debug, Clone)]
@@ -26,6 +27,8 @@ pub struct ResolvedVersionJsonMetadata<R: VersionJsonRemoteResolver> {
     remote_resolver: R,
     pub metadata: PistonMetaData,
     pub asset_index_objects: PistonMetaAssetIndexObjects,
+    /// Set when the metadata was served from the http cache because its origin was unreachable.
+    pub stale: bool,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -35,6 +38,8 @@ pub struct VersionJsonInstallStatus {
     pub version_artifacts_ready: bool,
     pub assets_ready: bool,
     pub natives_extracted: bool,
+    pub jar_patched: bool,
+    pub legacy_assets_reconstructed: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -47,6 +52,8 @@ pub struct ResolvedVersionJsonInstance<
     pub version: Storage<VL, Storage<L>>,
     pub metadata: PistonMetaData,
     pub asset_index_objects: PistonMetaAssetIndexObjects,
+    /// Carried over from [`ResolvedVersionJsonMetadata::stale`], never set for loaded versions.
+    pub stale: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -92,7 +99,10 @@ impl VersionJsonInstallStatus {
     }
 
     pub fn is_ready(&self) -> bool {
-        self.is_downloaded() && self.natives_extracted
+        self.is_downloaded()
+            && self.natives_extracted
+            && self.jar_patched
+            && self.legacy_assets_reconstructed
     }
 }
 
@@ -106,9 +116,15 @@ impl<R: VersionJsonRemoteResolver> ResolvedVersionJsonMetadata<R> {
             remote_resolver,
             metadata,
             asset_index_objects,
+            stale: false,
         }
     }
 
+    pub fn with_stale(mut self, stale: bool) -> Self {
+        self.stale = stale;
+        self
+    }
+
     pub async fn persist<
         L: VersionJsonRootLayout + Clone,
         VL: VersionJsonInstanceLayout + Clone,
@@ -130,6 +146,7 @@ impl<R: VersionJsonRemoteResolver> ResolvedVersionJsonMetadata<R> {
             version: instance.clone(),
             metadata: self.metadata,
             asset_index_objects: self.asset_index_objects,
+            stale: self.stale,
         })
     }
 }
@@ -194,6 +211,7 @@ where
             version,
             metadata,
             asset_index_objects,
+            stale: false,
         })
     }
 
@@ -221,7 +239,7 @@ where
     }
 
     pub fn required_java_major_version(&self) -> usize {
-        self.metadata.java_version.major_version
+        self.metadata.java_major_version()
     }
 
     pub async fn status(&self) -> Result<VersionJsonInstallStatus> {
@@ -242,6 +260,11 @@ where
             version_artifacts_ready: self.version_artifacts_ready(&rule_context)?,
             assets_ready: self.assets_ready()?,
             natives_extracted: self.version.natives_are_extracted().await,
+            jar_patched: self.version.jar_is_patched().await,
+            legacy_assets_reconstructed: self.version.legacy_assets_are_reconstructed(
+                &self.metadata.asset_index.id,
+                &self.asset_index_objects,
+            )?,
         })
     }
 
@@ -258,6 +281,14 @@ where
         if !status.natives_extracted {
             self.version.extract_natives().await?;
         }
+        if !status.jar_patched {
+            self.version.patch_jar().await?;
+        }
+        if !status.legacy_assets_reconstructed {
+            self.version
+                .reconstruct_legacy_assets(&self.metadata.asset_index.id, &self.asset_index_objects)
+                .await?;
+        }
 
         self.into_prepared().await
     }
@@ -349,16 +380,19 @@ where
         self.version.name().context("get version name failed")
     }
 
-    fn plan_version_artifacts(&self) -> Result<DownloadPlan> {
+    fn plan_version_artifacts(&self) -> Result<Vec<DownloadTask>> {
         let mut tasks = Vec::new();
-        tasks.push(DownloadTask::new(
-            self.remote_resolver
-                .rewrite_upstream(self.metadata.downloads.client.url.as_str())?,
-            self.version
-                .try_get_resource(VersionJsonInstanceResource::Jar)?,
-            Some(self.metadata.downloads.client.size as u64),
-            Some(self.metadata.downloads.client.sha1.clone()),
-        ));
+        tasks.push(
+            DownloadTask::from_candidates(
+                self.remote_resolver
+                    .rewrite_upstream_candidates(self.metadata.downloads.client.url.as_str())?,
+                self.version
+                    .try_get_resource(VersionJsonInstanceResource::Jar)?,
+                Some(self.metadata.downloads.client.size as u64),
+                Some(self.metadata.downloads.client.sha1.clone()),
+            )?
+            .with_priority(DownloadPriority::High),
+        );
 
         for library in &self.metadata.libraries {
             tasks.extend(self.plan_library_tasks(library)?);
@@ -367,24 +401,21 @@ where
         if let Some(logging) = &self.metadata.logging
             && let Some(client) = &logging.client
         {
-            tasks.push(DownloadTask::new(
-                self.remote_resolver
-                    .rewrite_upstream(client.file.url.as_str())?,
-                self.version
-                    .parent
-                    .try_get_resource(VersionJsonRootResource::AssetLogConfigs(Some(
-                        client.file.id.clone(),
-                    )))?,
-                Some(client.file.size as u64),
-                Some(client.file.sha1.clone()),
-            ));
+            tasks.push(
+                DownloadTask::from_candidates(
+                    self.remote_resolver
+                        .rewrite_upstream_candidates(client.file.url.as_str())?,
+                    self.version.parent.try_get_resource(
+                        VersionJsonRootResource::AssetLogConfigs(Some(client.file.id.clone())),
+                    )?,
+                    Some(client.file.size as u64),
+                    Some(client.file.sha1.clone()),
+                )?
+                .with_priority(DownloadPriority::High),
+            );
         }
 
-        DownloadPlan::named(
-            self.version_name()?,
-            DownloadExecutionPolicy::ServiceDefault,
-            tasks,
-        )
+        Ok(tasks)
     }
 
     fn plan_library_tasks(&self, library: &PistonMetaLibraries) -> Result<Vec<DownloadTask>> {
@@ -404,9 +435,9 @@ where
         &self,
         artifact: &PistonMetaLibrariesDownloadsArtifact,
     ) -> Result<DownloadTask> {
-        Ok(DownloadTask::new(
+        DownloadTask::from_candidates(
             self.remote_resolver
-                .rewrite_upstream(artifact.url.as_str())?,
+                .rewrite_upstream_candidates(artifact.url.as_str())?,
             self.version
                 .parent
                 .try_get_resource(VersionJsonRootResource::Libraries(Some(PathBuf::from(
@@ -414,32 +445,28 @@ where
                 ))))?,
             artifact.size.map(|size| size as u64),
             artifact.sha1.clone(),
-        ))
+        )
     }
 
-    fn plan_assets(&self) -> Result<DownloadPlan> {
-        let version_name = self.version_name()?;
-        let tasks =
-            self.asset_index_objects
-                .objects
-                .values()
-                .map(|object| {
-                    Ok(DownloadTask::new(
-                        self.remote_resolver.object_url(object.hash.as_str())?,
-                        self.version.parent.try_get_resource(
-                            VersionJsonRootResource::AssetObjects(Some(object.hash.clone())),
-                        )?,
-                        Some(object.size as u64),
-                        Some(object.hash.clone()),
-                    ))
-                })
-                .collect::<Result<Vec<DownloadTask>>>()?;
-
-        DownloadPlan::named(
-            format!("{version_name}-assets"),
-            DownloadExecutionPolicy::ServiceDefault,
-            tasks,
-        )
+    fn plan_assets(&self) -> Result<Vec<DownloadTask>> {
+        self.asset_index_objects
+            .objects
+            .values()
+            .map(|object| {
+                DownloadTask::from_candidates(
+                    self.remote_resolver
+                        .object_url_candidates(object.hash.as_str())?,
+                    self.version
+                        .parent
+                        .try_get_resource(VersionJsonRootResource::AssetObjects(Some(
+                            object.hash.clone(),
+                        )))?,
+                    Some(object.size as u64),
+                    Some(object.hash.clone()),
+                )
+                .map(|task| task.with_priority(DownloadPriority::Low))
+            })
+            .collect()
     }
 }
 
@@ -450,18 +477,100 @@ where
     VL: VersionJsonInstanceLayout,
 {
     fn plan(&self) -> Result<Vec<DownloadPlan>> {
-        let mut plans = Vec::new();
-
-        let version_plan = self.plan_version_artifacts()?;
-        if !version_plan.tasks.is_empty() {
-            plans.push(version_plan);
+        let mut tasks = self.plan_version_artifacts()?;
+        tasks.extend(self.plan_assets()?);
+        if tasks.is_empty() {
+            return Ok(Vec::new());
         }
 
-        let assets_plan = self.plan_assets()?;
-        if !assets_plan.tasks.is_empty() {
-            plans.push(assets_plan);
+        // One session, submitted by priority: the session queue is bounded, so submission order
+        // decides which tasks get in while the workers are already busy.
+        tasks.sort_by_key(|task| std::cmp::Reverse(task.priority));
+
+        Ok(vec![DownloadPlan::named(
+            self.version_name()?,
+            DownloadExecutionPolicy::ServiceDefault,
+            tasks,
+        )?])
+    }
+}
+
+#[test]
+fn test_planner_submits_critical_artifacts_before_assets_in_one_session() {
+    use crate::families::version_json::{BaseInstanceLayout, BaseRootLayout};
+
+    #[derive(Debug, Clone)]
+    struct IdentityResolver;
+
+    impl VersionJsonRemoteResolver for IdentityResolver {
+        fn rewrite_upstream(&self, raw_url: &str) -> Result<String> {
+            Ok(raw_url.to_owned())
         }
 
-        Ok(plans)
+        fn object_url(&self, hash: &str) -> Result<String> {
+            Ok(format!("https://example.invalid/objects/{hash}"))
+        }
     }
+
+    let root = std::env::temp_dir().join(format!("elemental-prepared-plan-{}", std::process::id()));
+    let metadata: PistonMetaData = serde_json::from_value(serde_json::json!({
+        "assetIndex": { "id": "idx", "sha1": "", "size": 0, "totalSize": 0, "url": "" },
+        "assets": "idx",
+        "downloads": { "client": { "sha1": "", "size": 0, "url": "https://example.invalid/client.jar" } },
+        "id": "a",
+        "libraries": [{
+            "name": "org.a:a:1",
+            "downloads": { "artifact": { "path": "org/a/a/1/a-1.jar", "url": "https://example.invalid/a-1.jar" } },
+        }],
+        "logging": { "client": {
+            "argument": "-Dlog4j.configurationFile=${path}",
+            "file": { "id": "client.xml", "sha1": "", "size": 0, "url": "https://example.invalid/client.xml" },
+            "type": "log4j2-xml",
+        } },
+        "mainClass": "net.minecraft.client.main.Main",
+        "type": "release",
+        "time": "2013-04-18T15:00:00+00:00",
+        "releaseTime": "2013-04-18T15:00:00+00:00",
+    }))
+    .unwrap();
+    let asset_index_objects: PistonMetaAssetIndexObjects =
+        serde_json::from_value(serde_json::json!({ "objects": {
+            "a.ogg": { "hash": "aa11", "size": 4 },
+            "b.ogg": { "hash": "bb22", "size": 4 },
+        } }))
+        .unwrap();
+    let instance = ResolvedVersionJsonInstance {
+        remote_resolver: IdentityResolver,
+        version: Storage::with_parent(
+            root.join("versions").join("a"),
+            Storage::new(&root, BaseRootLayout),
+            BaseInstanceLayout,
+        ),
+        metadata,
+        asset_index_objects,
+        stale: false,
+    };
+
+    let plans = instance.planner().plan().unwrap();
+    let [plan] = &plans[..] else {
+        panic!("expected a single session for the artifacts and the assets");
+    };
+    assert_eq!(plan.session.name.as_deref(), Some("a"));
+    let priorities = plan
+        .tasks
+        .iter()
+        .map(|task| task.priority)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        priorities,
+        vec![
+            DownloadPriority::High,
+            DownloadPriority::High,
+            DownloadPriority::Normal,
+            DownloadPriority::Low,
+            DownloadPriority::Low,
+        ]
+    );
+    assert_eq!(plan.tasks[0].url, "https://example.invalid/client.jar");
+    assert_eq!(plan.tasks[1].url, "https://example.invalid/client.xml");
 }
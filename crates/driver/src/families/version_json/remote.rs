@@ -2,11 +2,23 @@ use std::fmt::Debug;
 
 use anyhow::Result;
 
-use crate::drivers::vanilla::source::{VanillaEndpoints, rewrite_upstream_with_vanilla_fallback};
+use crate::drivers::vanilla::source::{
+    VanillaEndpoints, rewrite_upstream_candidates_with_vanilla_fallback,
+    rewrite_upstream_with_vanilla_fallback,
+};
 
 pub trait VersionJsonRemoteResolver: Clone + Debug + Send + Sync + 'static {
     fn rewrite_upstream(&self, raw_url: &str) -> Result<String>;
     fn object_url(&self, hash: &str) -> Result<String>;
+
+    /// Ordered download candidates for an upstream url, primary first.
+    fn rewrite_upstream_candidates(&self, raw_url: &str) -> Result<Vec<String>> {
+        Ok(vec![self.rewrite_upstream(raw_url)?])
+    }
+
+    fn object_url_candidates(&self, hash: &str) -> Result<Vec<String>> {
+        Ok(vec![self.object_url(hash)?])
+    }
 }
 
 pub trait UpstreamUrlRewriter: Clone + Debug + Send + Sync + 'static {
@@ -56,4 +68,17 @@ where
     fn object_url(&self, hash: &str) -> Result<String> {
         self.vanilla_endpoints.object_url(hash)
     }
+
+    fn rewrite_upstream_candidates(&self, raw_url: &str) -> Result<Vec<String>> {
+        rewrite_upstream_candidates_with_vanilla_fallback(
+            &self.vanilla_endpoints,
+            raw_url,
+            self.family_name,
+            || Ok(vec![self.family_endpoints.rewrite_upstream(raw_url)?]),
+        )
+    }
+
+    fn object_url_candidates(&self, hash: &str) -> Result<Vec<String>> {
+        self.vanilla_endpoints.object_url_candidates(hash)
+    }
 }
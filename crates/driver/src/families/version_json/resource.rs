@@ -6,20 +6,32 @@ pub enum VersionJsonRootResource {
     AssetIndexes(Option<String>),
     AssetObjects(Option<String>),
     AssetLogConfigs(Option<String>),
+    /// Assets laid out by name for the `legacy` index, `assets/virtual/<id>`.
+    AssetVirtual(Option<String>),
     Versions(Option<String>),
     Libraries(Option<PathBuf>),
+    /// Java runtimes installed into the root, one directory per runtime.
+    Runtimes(Option<String>),
+    Elemental(Option<PathBuf>),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum VersionJsonInstanceResource {
     Metadata,
     Jar,
+    /// The client jar with the instance's jarmods applied, launched instead of `Jar` when the
+    /// instance has any.
+    PatchedJar,
+    Jarmods(Option<String>),
     Natives,
+    GameDirectory,
     Logs,
     Configs,
     ShaderPacks,
     ResourcePacks,
     Saves,
     Mods,
+    /// Assets laid out by name for the `pre-1.6` index.
+    Resources,
     Elemental(Option<PathBuf>),
 }
@@ -19,6 +19,11 @@ impl VersionJsonRuleContext {
         Self::new(VersionJsonPlatform::current(), HashMap::new())
     }
 
+    pub fn with_feature(mut self, key: impl Into<String>, enabled: bool) -> Self {
+        self.features.insert(key.into(), enabled);
+        self
+    }
+
     pub fn platform(&self) -> &VersionJsonPlatform {
         &self.platform
     }
@@ -3,7 +3,7 @@ use std::fmt::Debug;
 use anyhow::Result;
 use serde::de::DeserializeOwned;
 
-use crate::http::{HttpSource, fetch_json};
+use crate::http::{Fetched, HttpCache, HttpClientSource, HttpSource};
 
 pub trait LoaderMetaEndpoints: Clone + Debug + Send + Sync + 'static {
     type GameVersion: DeserializeOwned;
@@ -31,6 +31,19 @@ where
     inner: HttpSource<E>,
 }
 
+impl<E> HttpClientSource for LoaderMetaSource<E>
+where
+    E: LoaderMetaEndpoints,
+{
+    fn set_client(&mut self, client: reqwest::Client) {
+        self.inner.set_client(client);
+    }
+
+    fn set_cache(&mut self, cache: HttpCache) {
+        self.inner.set_cache(cache);
+    }
+}
+
 impl<E> Default for LoaderMetaSource<E>
 where
     E: LoaderMetaEndpoints + Default,
@@ -60,25 +73,28 @@ where
         self.inner.endpoints()
     }
 
-    pub async fn game_versions(&self) -> Result<Vec<E::GameVersion>> {
+    pub async fn game_versions(&self) -> Result<Fetched<Vec<E::GameVersion>>> {
         let url = self.endpoints().game_versions_url()?;
-        fetch_json(self.inner.client(), url.as_str(), E::SOURCE_NAME).await
+        self.inner.fetch_json(url.as_str(), E::SOURCE_NAME).await
     }
 
-    pub async fn loader_versions(&self, game_version: &str) -> Result<Vec<E::LoaderGameVersion>> {
+    pub async fn loader_versions(
+        &self,
+        game_version: &str,
+    ) -> Result<Fetched<Vec<E::LoaderGameVersion>>> {
         let url = self.endpoints().loader_versions_url(game_version)?;
-        fetch_json(self.inner.client(), url.as_str(), E::SOURCE_NAME).await
+        self.inner.fetch_json(url.as_str(), E::SOURCE_NAME).await
     }
 
     pub async fn profile_json(
         &self,
         game_version: &str,
         loader_version: &str,
-    ) -> Result<E::ProfileJson> {
+    ) -> Result<Fetched<E::ProfileJson>> {
         let url = self
             .endpoints()
             .profile_json_url(game_version, loader_version)?;
-        fetch_json(self.inner.client(), url.as_str(), E::SOURCE_NAME).await
+        self.inner.fetch_json(url.as_str(), E::SOURCE_NAME).await
     }
 }
 
@@ -90,10 +106,10 @@ where
         &self,
         game_version: &str,
         loader_version: &str,
-    ) -> Result<E::LoaderProfile> {
+    ) -> Result<Fetched<E::LoaderProfile>> {
         let url = self
             .endpoints()
             .loader_profile_url(game_version, loader_version)?;
-        fetch_json(self.inner.client(), url.as_str(), E::SOURCE_NAME).await
+        self.inner.fetch_json(url.as_str(), E::SOURCE_NAME).await
     }
 }
@@ -5,7 +5,7 @@ use std::{
 };
 
 use anyhow::Result;
-use elemental_core::storage::layout::Layout;
+use elemental_core::storage::{layout::Layout, write_atomic};
 use elemental_shared::{
     migrator::NoMigrator,
     store::{Store, StoreLoader},
@@ -13,11 +13,12 @@ use elemental_shared::{
 };
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use tokio::fs::{create_dir_all, read_to_string, write};
+use tokio::fs::read_to_string;
 
 use crate::families::version_json::{BaseInstanceLayout, VersionJsonInstanceResource};
 
 const NATIVES_STATE_VERSION: usize = 1;
+const JARMODS_STATE_VERSION: usize = 1;
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct NativesState {
@@ -25,6 +26,58 @@ pub struct NativesState {
     pub native_artifacts: Vec<String>,
     pub extracted_files: Vec<String>,
     pub checked_at_unix_ms: u64,
+    #[serde(default)]
+    pub report: NativesExtractionReport,
+}
+
+/// What the last natives extraction wrote, skipped and refused to overwrite.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct NativesExtractionReport {
+    pub artifacts: Vec<NativeArtifactReport>,
+    /// Entries with the same name but different contents, the first library keeps its copy.
+    pub conflicts: Vec<NativeConflict>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct NativeArtifactReport {
+    pub artifact: String,
+    pub exclude: Vec<String>,
+    pub extracted: Vec<ExtractedNative>,
+    pub excluded: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ExtractedNative {
+    pub name: String,
+    pub size: u64,
+    pub crc32: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct NativeConflict {
+    pub name: String,
+    pub kept_artifact: String,
+    pub kept_crc32: u32,
+    pub skipped_artifact: String,
+    pub skipped_crc32: u32,
+}
+
+/// The ordered jarmods of an instance and what its patched jar was last built from.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct JarmodsState {
+    /// File names under the instance's jarmods directory, applied first to last.
+    pub jarmods: Vec<String>,
+    #[serde(default)]
+    pub patched: Option<PatchedJarState>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PatchedJarState {
+    /// Sha1 of the client jar followed by each jarmod, in order.
+    pub sources: Vec<String>,
+    pub replaced: Vec<String>,
+    pub stripped: Vec<String>,
+    pub patched_at_unix_ms: u64,
 }
 
 #[derive(Debug, Clone)]
@@ -55,13 +108,7 @@ where
     }
 
     async fn save(&self, value: &V) -> Result<()> {
-        if let Some(parent) = self.path.parent()
-            && !parent.exists()
-        {
-            create_dir_all(parent).await?;
-        }
-
-        write(&self.path, serde_json::to_string(value)?).await?;
+        write_atomic(&self.path, serde_json::to_string(value)?).await?;
         Ok(())
     }
 }
@@ -90,3 +137,28 @@ pub async fn natives_state_store(instance_root: &Path) -> Result<NativeStateStor
 
     Ok(store)
 }
+
+pub type JarmodsStateStore =
+    StoreLoader<NoMigrator, JarmodsState, JsonPathPersistor<Store<JarmodsState>>>;
+
+pub fn jarmods_state_path(instance_root: &Path) -> Result<PathBuf> {
+    BaseInstanceLayout.try_get_extended_resource(
+        instance_root,
+        VersionJsonInstanceResource::Elemental(Some(PathBuf::from("jarmods.json"))),
+    )
+}
+
+pub async fn jarmods_state_store(instance_root: &Path) -> Result<JarmodsStateStore> {
+    let persistor = JsonPathPersistor::new(jarmods_state_path(instance_root)?);
+    let store = Store::load(NoMigrator, persistor, JARMODS_STATE_VERSION).await?;
+
+    if store.get(|state| state.version).await != JARMODS_STATE_VERSION {
+        store
+            .set(|state| {
+                state.version = JARMODS_STATE_VERSION;
+            })
+            .await?;
+    }
+
+    Ok(store)
+}
@@ -1,14 +1,21 @@
 use std::{
+    collections::HashMap,
+    env::consts::EXE_SUFFIX,
     fs::File,
-    path::{Path, PathBuf},
+    io::Read,
+    path::{Component, Path, PathBuf},
     time::{SystemTime, UNIX_EPOCH},
 };
 
-use anyhow::{Context, Result, anyhow};
+use anyhow::{Context, Result, anyhow, bail};
 use async_trait::async_trait;
-use elemental_core::storage::{Storage, layout::Layoutable};
-use elemental_infra::jar::JarFile;
+use elemental_core::{
+    runtime::distribution::Distribution,
+    storage::{Storage, StorageLock, StorageLockMode, layout::Layoutable, write_atomic},
+};
+use elemental_infra::jar::{JarFile, JarPatch, JarPatcher};
 use elemental_schema::mojang::piston::{PistonMetaAssetIndexObjects, PistonMetaData};
+use sha1_smol::Sha1;
 use tokio::fs::create_dir_all;
 
 use super::{
@@ -20,7 +27,10 @@ use crate::{
     families::version_json::{
         extensions::PistonMetaLibrariesExt,
         rules::VersionJsonRuleContext,
-        state::{NativesState, natives_state_store},
+        state::{
+            ExtractedNative, NativeArtifactReport, NativeConflict, NativesExtractionReport,
+            NativesState, PatchedJarState, jarmods_state_store, natives_state_store,
+        },
     },
     inspect::InstalledInstance,
 };
@@ -61,6 +71,10 @@ pub trait VersionJsonGameStorageExt {
         objects: &PistonMetaAssetIndexObjects,
     ) -> Result<()>;
     fn asset_index_objects(&self, id: impl AsRef<str>) -> Result<PistonMetaAssetIndexObjects>;
+    fn try_lock(&self, name: &str, mode: StorageLockMode) -> Result<StorageLock>;
+    fn try_lock_root(&self, mode: StorageLockMode) -> Result<StorageLock>;
+    /// The Java runtimes installed under the root's runtime directory.
+    async fn runtimes(&self) -> Result<Vec<Distribution>>;
 }
 
 #[async_trait(?Send)]
@@ -172,7 +186,7 @@ where
             .parent()
             .context("asset index path has no parent directory")?;
         create_dir_all(parent).await?;
-        tokio::fs::write(path, serde_json::to_vec(objects)?).await?;
+        write_atomic(path, serde_json::to_vec(objects)?).await?;
         Ok(())
     }
 
@@ -182,6 +196,35 @@ where
         )))?;
         Ok(serde_json::from_reader(File::open(path)?)?)
     }
+
+    fn try_lock(&self, name: &str, mode: StorageLockMode) -> Result<StorageLock> {
+        let path = self.try_get_resource(VersionJsonRootResource::Elemental(Some(
+            PathBuf::from("locks").join(format!("{name}.lock")),
+        )))?;
+        StorageLock::try_acquire(path, mode)
+            .with_context(|| format!("lock storage '{name}' failed"))
+    }
+
+    fn try_lock_root(&self, mode: StorageLockMode) -> Result<StorageLock> {
+        self.try_lock("root", mode)
+    }
+
+    async fn runtimes(&self) -> Result<Vec<Distribution>> {
+        let runtimes_root = self.try_get_resource(VersionJsonRootResource::Runtimes(None))?;
+        let Ok(entries) = std::fs::read_dir(&runtimes_root) else {
+            return Ok(Vec::new());
+        };
+
+        let mut runtimes = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.join("bin").join(format!("java{EXE_SUFFIX}")).is_file() {
+                runtimes.push(Distribution::build_from_root(path, "Storage").await);
+            }
+        }
+
+        Ok(runtimes)
+    }
 }
 
 #[async_trait(?Send)]
@@ -194,6 +237,36 @@ pub trait VersionJsonVersionStorageExt {
     async fn natives_are_extracted(&self) -> bool;
     async fn ensure_platform_natives_path(&self) -> Result<PathBuf>;
     async fn extract_natives(&self) -> Result<()>;
+    async fn natives_report(&self) -> Result<NativesExtractionReport>;
+    async fn jarmods(&self) -> Result<Vec<String>>;
+    /// Copies `source` into the instance's jarmods and applies it after the existing ones.
+    async fn add_jarmod(&self, source: &Path) -> Result<String>;
+    /// Reorders or removes jarmods, names must already be in the jarmods directory.
+    async fn set_jarmods(&self, jarmods: Vec<String>) -> Result<()>;
+    async fn jar_is_patched(&self) -> bool;
+    async fn patch_jar(&self) -> Result<Option<JarPatch>>;
+    /// The patched jar when the instance has jarmods, the client jar otherwise.
+    async fn launch_jar(&self) -> Result<PathBuf>;
+    /// The tree a `virtual` or `map_to_resources` asset index is read from by name, `None` for
+    /// indexes the game resolves by hash.
+    fn legacy_assets_root(
+        &self,
+        asset_index_id: &str,
+        objects: &PistonMetaAssetIndexObjects,
+    ) -> Result<Option<PathBuf>>;
+    fn legacy_assets_are_reconstructed(
+        &self,
+        asset_index_id: &str,
+        objects: &PistonMetaAssetIndexObjects,
+    ) -> Result<bool>;
+    /// Hardlinks, or copies across filesystems, downloaded objects into the legacy asset tree.
+    /// Returns how many files were written.
+    async fn reconstruct_legacy_assets(
+        &self,
+        asset_index_id: &str,
+        objects: &PistonMetaAssetIndexObjects,
+    ) -> Result<usize>;
+    fn try_lock_instance(&self) -> Result<StorageLock>;
 }
 
 #[async_trait(?Send)]
@@ -213,7 +286,7 @@ where
     async fn write_metadata(&self, metadata: &PistonMetaData) -> Result<()> {
         self.ensure_root().await?;
         let path = self.try_get_resource(VersionJsonInstanceResource::Metadata)?;
-        tokio::fs::write(path, serde_json::to_vec(metadata)?).await?;
+        write_atomic(path, serde_json::to_vec(metadata)?).await?;
         Ok(())
     }
 
@@ -273,13 +346,21 @@ where
         let rule_context = VersionJsonRuleContext::current();
         std::fs::create_dir_all(&destination)?;
 
-        tokio::task::block_in_place(|| -> Result<()> {
+        let report = tokio::task::block_in_place(|| -> Result<NativesExtractionReport> {
             let natives_directory = NativesDirectory::new(&destination);
+            let mut report = NativesExtractionReport::default();
+            // Claim key -> (artifact, crc32) of the copy already written.
+            let mut claimed = HashMap::<String, (String, u32)>::new();
 
             for library in &metadata.libraries {
                 if !library.is_allowed(&rule_context) {
                     continue;
                 }
+                let exclude = library
+                    .extract
+                    .as_ref()
+                    .map(|extract| extract.exclude.clone())
+                    .unwrap_or_else(|| vec!["META-INF/".to_owned()]);
 
                 for artifact in library
                     .native_source_artifacts(rule_context.platform())
@@ -291,11 +372,49 @@ where
                             .try_get_resource(VersionJsonRootResource::Libraries(Some(
                                 PathBuf::from(artifact.path.as_str()),
                             )))?;
-                    JarFile::new(source).extract_blocking(&destination)?;
+                    let extraction = JarFile::new(source)
+                        .extract_excluding_blocking(&destination, &exclude, |entry| {
+                            let key = native_claim_key(&entry.name);
+                            match claimed.get(&key) {
+                                None => {
+                                    claimed.insert(key, (artifact.path.clone(), entry.crc32));
+                                    true
+                                }
+                                Some((_, crc32)) if *crc32 == entry.crc32 => false,
+                                Some((kept_artifact, kept_crc32)) => {
+                                    report.conflicts.push(NativeConflict {
+                                        name: entry.name.clone(),
+                                        kept_artifact: kept_artifact.clone(),
+                                        kept_crc32: *kept_crc32,
+                                        skipped_artifact: artifact.path.clone(),
+                                        skipped_crc32: entry.crc32,
+                                    });
+                                    false
+                                }
+                            }
+                        })
+                        .with_context(|| {
+                            format!("extract natives from '{}' failed", artifact.path)
+                        })?;
+                    report.artifacts.push(NativeArtifactReport {
+                        artifact: artifact.path.clone(),
+                        exclude: exclude.clone(),
+                        extracted: extraction
+                            .extracted
+                            .into_iter()
+                            .map(|entry| ExtractedNative {
+                                name: entry.name,
+                                size: entry.size,
+                                crc32: entry.crc32,
+                            })
+                            .collect(),
+                        excluded: extraction.excluded,
+                    });
                 }
             }
 
-            natives_directory.flatten_binaries()
+            natives_directory.flatten_binaries()?;
+            Ok(report)
         })?;
         let extracted_files = NativesDirectory::new(&destination)
             .root_binaries()?
@@ -314,12 +433,247 @@ where
                     native_artifacts: collect_native_artifact_paths(&metadata, &rule_context),
                     extracted_files,
                     checked_at_unix_ms,
+                    report,
                 };
             })
             .await?;
 
         Ok(())
     }
+
+    async fn natives_report(&self) -> Result<NativesExtractionReport> {
+        let store = natives_state_store(&self.path).await?;
+        Ok(store.cloned().await.value.report)
+    }
+
+    async fn jarmods(&self) -> Result<Vec<String>> {
+        let store = jarmods_state_store(&self.path).await?;
+        Ok(store.cloned().await.value.jarmods)
+    }
+
+    async fn add_jarmod(&self, source: &Path) -> Result<String> {
+        let name = source
+            .file_name()
+            .with_context(|| format!("jarmod has no file name: {}", source.display()))?
+            .to_string_lossy()
+            .to_string();
+        let store = jarmods_state_store(&self.path).await?;
+        if store.get(|state| state.value.jarmods.contains(&name)).await {
+            bail!("jarmod '{name}' is already applied");
+        }
+
+        let target =
+            self.try_get_resource(VersionJsonInstanceResource::Jarmods(Some(name.clone())))?;
+        if let Some(parent) = target.parent() {
+            create_dir_all(parent).await?;
+        }
+        tokio::fs::copy(source, &target).await.with_context(|| {
+            format!(
+                "copy jarmod failed: {} -> {}",
+                source.display(),
+                target.display()
+            )
+        })?;
+        store
+            .set(|state| state.value.jarmods.push(name.clone()))
+            .await?;
+
+        Ok(name)
+    }
+
+    async fn set_jarmods(&self, jarmods: Vec<String>) -> Result<()> {
+        for (index, name) in jarmods.iter().enumerate() {
+            let mut components = Path::new(name).components();
+            if !matches!(
+                (components.next(), components.next()),
+                (Some(Component::Normal(_)), None)
+            ) {
+                bail!("jarmod '{name}' is not a file name in the jarmods directory");
+            }
+            if jarmods[..index].contains(name) {
+                bail!("jarmod '{name}' is listed more than once");
+            }
+            let path =
+                self.try_get_resource(VersionJsonInstanceResource::Jarmods(Some(name.clone())))?;
+            if !path.is_file() {
+                bail!("jarmod not found: {}", path.display());
+            }
+        }
+
+        let store = jarmods_state_store(&self.path).await?;
+        store.set(|state| state.value.jarmods = jarmods).await
+    }
+
+    async fn jar_is_patched(&self) -> bool {
+        let store = match jarmods_state_store(&self.path).await {
+            Ok(store) => store,
+            Err(_) => return false,
+        };
+        let state = store.cloned().await.value;
+        if state.jarmods.is_empty() {
+            return true;
+        }
+        let Some(patched) = state.patched else {
+            return false;
+        };
+        let Ok(patched_jar) = self.try_get_resource(VersionJsonInstanceResource::PatchedJar) else {
+            return false;
+        };
+        if !patched_jar.exists() {
+            return false;
+        }
+
+        match jarmod_sources(self, &state.jarmods) {
+            Ok(sources) => tokio::task::block_in_place(|| sources_fingerprint(&sources))
+                .is_ok_and(|fingerprint| fingerprint == patched.sources),
+            Err(_) => false,
+        }
+    }
+
+    async fn patch_jar(&self) -> Result<Option<JarPatch>> {
+        let store = jarmods_state_store(&self.path).await?;
+        let jarmods = store.get(|state| state.value.jarmods.clone()).await;
+        let patched_jar = self.try_get_resource(VersionJsonInstanceResource::PatchedJar)?;
+        if jarmods.is_empty() {
+            if patched_jar.exists() {
+                tokio::fs::remove_file(&patched_jar).await?;
+            }
+            store.set(|state| state.value.patched = None).await?;
+            return Ok(None);
+        }
+
+        let sources = jarmod_sources(self, &jarmods)?;
+        let (fingerprint, patch) = tokio::task::block_in_place(|| -> Result<_> {
+            let fingerprint = sources_fingerprint(&sources)?;
+            let patch = JarPatcher::new(&sources[0])
+                .with_jarmods(&sources[1..])
+                .write_blocking(&patched_jar)?;
+            Ok((fingerprint, patch))
+        })
+        .with_context(|| format!("patch jar failed: {}", patched_jar.display()))?;
+        let patched_at_unix_ms = current_unix_ms()?;
+        store
+            .set(|state| {
+                state.value.patched = Some(PatchedJarState {
+                    sources: fingerprint,
+                    replaced: patch.replaced.clone(),
+                    stripped: patch.stripped.clone(),
+                    patched_at_unix_ms,
+                });
+            })
+            .await?;
+
+        Ok(Some(patch))
+    }
+
+    async fn launch_jar(&self) -> Result<PathBuf> {
+        if self.jarmods().await?.is_empty() {
+            self.try_get_resource(VersionJsonInstanceResource::Jar)
+        } else {
+            self.try_get_resource(VersionJsonInstanceResource::PatchedJar)
+        }
+    }
+
+    fn legacy_assets_root(
+        &self,
+        asset_index_id: &str,
+        objects: &PistonMetaAssetIndexObjects,
+    ) -> Result<Option<PathBuf>> {
+        if objects.map_to_resources {
+            return Ok(Some(
+                self.try_get_resource(VersionJsonInstanceResource::Resources)?,
+            ));
+        }
+        if objects.is_virtual {
+            return Ok(Some(self.parent.try_get_resource(
+                VersionJsonRootResource::AssetVirtual(Some(asset_index_id.to_owned())),
+            )?));
+        }
+
+        Ok(None)
+    }
+
+    fn legacy_assets_are_reconstructed(
+        &self,
+        asset_index_id: &str,
+        objects: &PistonMetaAssetIndexObjects,
+    ) -> Result<bool> {
+        let Some(root) = self.legacy_assets_root(asset_index_id, objects)? else {
+            return Ok(true);
+        };
+
+        for (name, object) in &objects.objects {
+            let target = legacy_asset_path(&root, name)?;
+            match target.metadata() {
+                Ok(metadata) if metadata.len() == object.size as u64 => {}
+                _ => return Ok(false),
+            }
+        }
+
+        Ok(true)
+    }
+
+    async fn reconstruct_legacy_assets(
+        &self,
+        asset_index_id: &str,
+        objects: &PistonMetaAssetIndexObjects,
+    ) -> Result<usize> {
+        let Some(root) = self.legacy_assets_root(asset_index_id, objects)? else {
+            return Ok(0);
+        };
+
+        tokio::task::block_in_place(|| -> Result<usize> {
+            let mut written = 0;
+            for (name, object) in &objects.objects {
+                let target = legacy_asset_path(&root, name)?;
+                if target
+                    .metadata()
+                    .is_ok_and(|metadata| metadata.len() == object.size as u64)
+                {
+                    continue;
+                }
+
+                let source =
+                    self.parent
+                        .try_get_resource(VersionJsonRootResource::AssetObjects(Some(
+                            object.hash.clone(),
+                        )))?;
+                if let Some(parent) = target.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                if target.symlink_metadata().is_ok() {
+                    std::fs::remove_file(&target).with_context(|| {
+                        format!("remove stale asset failed: {}", target.display())
+                    })?;
+                }
+                if std::fs::hard_link(&source, &target).is_err() {
+                    std::fs::copy(&source, &target).with_context(|| {
+                        format!(
+                            "copy asset '{name}' failed: {} -> {}",
+                            source.display(),
+                            target.display()
+                        )
+                    })?;
+                }
+                written += 1;
+            }
+
+            Ok(written)
+        })
+    }
+
+    fn try_lock_instance(&self) -> Result<StorageLock> {
+        let path = self.try_get_resource(VersionJsonInstanceResource::Elemental(Some(
+            PathBuf::from("instance.lock"),
+        )))?;
+        StorageLock::try_acquire(path, StorageLockMode::Exclusive).with_context(|| {
+            format!(
+                "lock instance '{}' failed",
+                self.name()
+                    .unwrap_or_else(|| self.path.display().to_string())
+            )
+        })
+    }
 }
 
 pub async fn inspect_instances<L, VL>(
@@ -334,6 +688,64 @@ where
     InstalledInstance::detect_all(storage, version_layout, drivers).await
 }
 
+/// Rejects asset names that would escape the legacy asset tree.
+fn legacy_asset_path(root: &Path, name: &str) -> Result<PathBuf> {
+    let relative = Path::new(name);
+    if name.is_empty()
+        || !relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+    {
+        bail!(
+            "refusing to place asset outside of {}: '{name}'",
+            root.display()
+        );
+    }
+
+    Ok(root.join(relative))
+}
+
+/// The client jar followed by each jarmod, in the order they are applied.
+fn jarmod_sources<L, VL>(
+    version: &Storage<VL, Storage<L>>,
+    jarmods: &[String],
+) -> Result<Vec<PathBuf>>
+where
+    L: VersionJsonRootLayout,
+    VL: VersionJsonInstanceLayout,
+{
+    let mut sources = vec![version.try_get_resource(VersionJsonInstanceResource::Jar)?];
+    for name in jarmods {
+        sources.push(
+            version.try_get_resource(VersionJsonInstanceResource::Jarmods(Some(name.clone())))?,
+        );
+    }
+
+    Ok(sources)
+}
+
+fn sources_fingerprint(sources: &[PathBuf]) -> Result<Vec<String>> {
+    sources
+        .iter()
+        .map(|path| {
+            let mut file = File::open(path)
+                .with_context(|| format!("open file failed: {}", path.display()))?;
+            let mut hasher = Sha1::new();
+            let mut buffer = [0u8; 64 * 1024];
+            loop {
+                let read = file
+                    .read(&mut buffer)
+                    .with_context(|| format!("read file failed: {}", path.display()))?;
+                if read == 0 {
+                    break;
+                }
+                hasher.update(&buffer[..read]);
+            }
+            Ok(hasher.digest().to_string())
+        })
+        .collect()
+}
+
 fn current_unix_ms() -> Result<u64> {
     let duration = SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -366,6 +778,18 @@ fn collect_native_artifact_paths(
     paths
 }
 
+/// Binaries are flattened into the natives root, so they collide on their file name even when
+/// their jars keep them in different directories.
+fn native_claim_key(name: &str) -> String {
+    let path = Path::new(name);
+    match path.file_name() {
+        Some(file_name) if NativesDirectory::is_binary(path) => {
+            file_name.to_string_lossy().to_string()
+        }
+        _ => name.to_owned(),
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 struct NativesDirectory<'a> {
     root: &'a Path,
@@ -446,3 +870,190 @@ impl<'a> NativesDirectory<'a> {
             })
     }
 }
+
+#[test]
+fn test_native_claim_key_flattens_binaries_only() {
+    assert_eq!(native_claim_key("lwjgl.dll"), "lwjgl.dll");
+    assert_eq!(native_claim_key("windows/x64/lwjgl.dll"), "lwjgl.dll");
+    assert_eq!(
+        native_claim_key("linux/x64/org/lwjgl/liblwjgl.so"),
+        "liblwjgl.so"
+    );
+    assert_eq!(
+        native_claim_key("org/lwjgl/version.txt"),
+        "org/lwjgl/version.txt"
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_reconstruct_legacy_assets_links_objects_by_name() {
+    use super::layout::{BaseInstanceLayout, BaseRootLayout};
+
+    let root = std::env::temp_dir().join(format!("elemental-legacy-assets-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    let write = |path: PathBuf, contents: &[u8]| {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, contents).unwrap();
+    };
+    write(root.join("assets/objects/aa/aa11"), b"sound");
+    write(root.join("assets/objects/bb/bb22"), b"music!");
+    let version = Storage::with_parent(
+        root.join("versions/a"),
+        Storage::new(&root, BaseRootLayout),
+        BaseInstanceLayout,
+    );
+    let index = |flag: &str| -> PistonMetaAssetIndexObjects {
+        let mut index = serde_json::json!({ "objects": {
+            "sound/a.ogg": { "hash": "aa11", "size": 5 },
+            "music/b.ogg": { "hash": "bb22", "size": 6 },
+        } });
+        if !flag.is_empty() {
+            index[flag] = serde_json::Value::Bool(true);
+        }
+        serde_json::from_value(index).unwrap()
+    };
+
+    let modern = index("");
+    assert!(!modern.is_virtual && !modern.map_to_resources);
+    assert_eq!(version.legacy_assets_root("idx", &modern).unwrap(), None);
+    assert!(
+        version
+            .legacy_assets_are_reconstructed("idx", &modern)
+            .unwrap()
+    );
+    assert_eq!(
+        version
+            .reconstruct_legacy_assets("idx", &modern)
+            .await
+            .unwrap(),
+        0
+    );
+
+    let virtual_index = index("virtual");
+    assert!(virtual_index.is_virtual);
+    let virtual_root = root.join("assets/virtual/legacy");
+    assert_eq!(
+        version
+            .legacy_assets_root("legacy", &virtual_index)
+            .unwrap(),
+        Some(virtual_root.clone())
+    );
+    assert!(
+        !version
+            .legacy_assets_are_reconstructed("legacy", &virtual_index)
+            .unwrap()
+    );
+    assert_eq!(
+        version
+            .reconstruct_legacy_assets("legacy", &virtual_index)
+            .await
+            .unwrap(),
+        2
+    );
+    assert_eq!(
+        std::fs::read(virtual_root.join("sound/a.ogg")).unwrap(),
+        b"sound"
+    );
+    assert_eq!(
+        std::fs::read(virtual_root.join("music/b.ogg")).unwrap(),
+        b"music!"
+    );
+    assert!(
+        version
+            .legacy_assets_are_reconstructed("legacy", &virtual_index)
+            .unwrap()
+    );
+
+    // A file of the indexed size is taken as already placed, a stale one is replaced.
+    let resources_index = index("map_to_resources");
+    assert!(resources_index.map_to_resources);
+    let resources_root = version
+        .try_get_resource(VersionJsonInstanceResource::Resources)
+        .unwrap();
+    assert_eq!(
+        version
+            .legacy_assets_root("pre-1.6", &resources_index)
+            .unwrap(),
+        Some(resources_root.clone())
+    );
+    write(resources_root.join("sound/a.ogg"), b"kept!");
+    write(resources_root.join("music/b.ogg"), b"stale");
+    assert_eq!(
+        version
+            .reconstruct_legacy_assets("pre-1.6", &resources_index)
+            .await
+            .unwrap(),
+        1
+    );
+    assert_eq!(
+        std::fs::read(resources_root.join("sound/a.ogg")).unwrap(),
+        b"kept!"
+    );
+    assert_eq!(
+        std::fs::read(resources_root.join("music/b.ogg")).unwrap(),
+        b"music!"
+    );
+
+    let escaping: PistonMetaAssetIndexObjects = serde_json::from_value(serde_json::json!({
+        "virtual": true,
+        "objects": { "../../escape.ogg": { "hash": "aa11", "size": 5 } },
+    }))
+    .unwrap();
+    assert!(
+        version
+            .reconstruct_legacy_assets("legacy", &escaping)
+            .await
+            .is_err()
+    );
+    assert!(
+        version
+            .legacy_assets_are_reconstructed("legacy", &escaping)
+            .is_err()
+    );
+    assert!(!root.join("assets/escape.ogg").exists());
+    for name in ["", "/abs.ogg", "sound/../../b.ogg"] {
+        assert!(legacy_asset_path(&virtual_root, name).is_err(), "{name}");
+    }
+
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_set_jarmods_rejects_names_outside_the_jarmods_directory() {
+    use super::layout::{BaseInstanceLayout, BaseRootLayout};
+
+    let root = std::env::temp_dir().join(format!("elemental-jarmods-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    let version = Storage::with_parent(
+        root.join("versions/a"),
+        Storage::new(&root, BaseRootLayout),
+        BaseInstanceLayout,
+    );
+    let jarmods = version
+        .try_get_resource(VersionJsonInstanceResource::Jarmods(None))
+        .unwrap();
+    std::fs::create_dir_all(jarmods.join("nested")).unwrap();
+    std::fs::write(jarmods.join("mod.zip"), b"mod").unwrap();
+    std::fs::write(jarmods.join("nested/mod.zip"), b"mod").unwrap();
+    std::fs::write(root.join("versions/a/a.jar"), b"jar").unwrap();
+
+    for name in ["", ".", "..", "../a.jar", "nested/mod.zip", "/mod.zip"] {
+        assert!(
+            version.set_jarmods(vec![name.to_owned()]).await.is_err(),
+            "{name}"
+        );
+    }
+    assert!(
+        version
+            .set_jarmods(vec!["mod.zip".to_owned(), "mod.zip".to_owned()])
+            .await
+            .is_err()
+    );
+    version
+        .set_jarmods(vec!["mod.zip".to_owned()])
+        .await
+        .unwrap();
+    assert_eq!(version.jarmods().await.unwrap(), vec!["mod.zip"]);
+
+    let _ = std::fs::remove_dir_all(&root);
+}
@@ -4,7 +4,7 @@ use std::{
 };
 
 use anyhow::{Context, Result};
-use elemental_core::storage::layout::Layout;
+use elemental_core::storage::{Storage, layout::Layout};
 use elemental_schema::mojang::piston::PistonMetaData;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
@@ -16,6 +16,7 @@ use super::{
     layout::{BaseInstanceLayout, BaseRootLayout},
     resource::{VersionJsonInstanceResource, VersionJsonRootResource},
     rules::VersionJsonRuleContext,
+    storage::{VersionJsonGameStorageExt, VersionJsonVersionStorageExt},
 };
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -24,9 +25,13 @@ pub struct LauncherVariables {
     pub version_name: String,
     pub game_directory: String,
     pub assets_root: String,
+    /// `--assetsDir` of pre-1.7 versions, the virtual or resource-mapped asset tree.
+    pub game_assets: String,
     pub assets_index_name: String,
     pub auth_uuid: String,
     pub auth_access_token: String,
+    /// Pre-1.6 session argument, `token:<access token>:<uuid>` or `-` when offline.
+    pub auth_session: String,
     // Legacy 1.7-era launchers still expect this placeholder to be a JSON object string.
     pub user_properties: String,
     pub clientid: String,
@@ -58,9 +63,11 @@ impl Default for LauncherVariables {
             version_name: Default::default(),
             game_directory: Default::default(),
             assets_root: Default::default(),
+            game_assets: Default::default(),
             assets_index_name: Default::default(),
             auth_uuid: Default::default(),
             auth_access_token: Default::default(),
+            auth_session: "-".to_owned(),
             user_properties: "{}".to_owned(),
             clientid: Default::default(),
             auth_xuid: Default::default(),
@@ -140,15 +147,29 @@ impl LauncherVariables {
             .chain(std::iter::once(version_jar.to_string_lossy().to_string()))
             .collect::<Vec<String>>();
         let classpath = join_classpath(classpath);
+        let version = Storage::with_parent(
+            version_root_path.to_path_buf(),
+            Storage::new(game_root, root_layout),
+            instance_layout,
+        );
+        let asset_index_objects = version
+            .parent
+            .asset_index_objects(pistonmeta.asset_index.id.as_str())
+            .context("read asset index failed")?;
+        let game_assets = version
+            .legacy_assets_root(&pistonmeta.asset_index.id, &asset_index_objects)?
+            .unwrap_or_else(|| assets_root.clone());
 
         Ok(Self {
             auth_player_name: player_name,
             version_name,
             game_directory: game_directory.clone(),
             assets_root: assets_root.to_string_lossy().to_string(),
+            game_assets: game_assets.to_string_lossy().to_string(),
             assets_index_name: pistonmeta.assets.clone(),
             auth_uuid: String::new(),
             auth_access_token: String::new(),
+            auth_session: "-".to_owned(),
             user_properties: "{}".to_owned(),
             clientid: String::new(),
             auth_xuid: String::new(),
@@ -0,0 +1,268 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use elemental_core::storage::{Storage, StorageLockMode, layout::Layoutable};
+
+use crate::{
+    families::version_json::{
+        VersionJsonGameStorageExt, VersionJsonInstanceLayout, VersionJsonRootLayout,
+    },
+    references::{SharedReferenceCollector, SharedReferences, collect_files},
+};
+
+pub use crate::references::SharedArtifactKind;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageGcMode {
+    DryRun,
+    Delete,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorageGcEntry {
+    pub kind: SharedArtifactKind,
+    pub path: PathBuf,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorageGcReport {
+    pub mode: StorageGcMode,
+    pub instances: Vec<String>,
+    pub scanned_files: usize,
+    pub scanned_bytes: u64,
+    pub orphaned: Vec<StorageGcEntry>,
+    pub orphaned_bytes: u64,
+    pub deleted_bytes: u64,
+}
+
+impl StorageGcReport {
+    pub fn is_dry_run(&self) -> bool {
+        self.mode == StorageGcMode::DryRun
+    }
+
+    pub fn orphaned_bytes_of(&self, kind: SharedArtifactKind) -> u64 {
+        self.orphaned
+            .iter()
+            .filter(|entry| entry.kind == kind)
+            .map(|entry| entry.size)
+            .sum()
+    }
+
+    pub fn orphaned_count_of(&self, kind: SharedArtifactKind) -> usize {
+        self.orphaned
+            .iter()
+            .filter(|entry| entry.kind == kind)
+            .count()
+    }
+}
+
+pub async fn collect_storage_garbage<L, VL>(
+    storage: &Storage<L>,
+    version_layout: VL,
+    mode: StorageGcMode,
+) -> Result<StorageGcReport>
+where
+    L: VersionJsonRootLayout + Clone,
+    VL: VersionJsonInstanceLayout + Clone,
+{
+    if mode == StorageGcMode::DryRun {
+        return tokio::task::block_in_place(|| scan_storage_garbage(storage, version_layout));
+    }
+
+    let _lock = storage.try_lock_root(StorageLockMode::Exclusive)?;
+    let mut report = tokio::task::block_in_place(|| scan_storage_garbage(storage, version_layout))?;
+    report.mode = mode;
+
+    for entry in &report.orphaned {
+        tokio::fs::remove_file(&entry.path)
+            .await
+            .with_context(|| format!("remove orphaned file failed: {}", entry.path.display()))?;
+        report.deleted_bytes += entry.size;
+    }
+
+    tokio::task::block_in_place(|| -> Result<()> {
+        for resource in SharedArtifactKind::all()
+            .into_iter()
+            .filter_map(SharedArtifactKind::root_resource)
+        {
+            remove_empty_directories(&storage.try_get_resource(resource)?)?;
+        }
+        Ok(())
+    })?;
+
+    Ok(report)
+}
+
+pub fn scan_storage_garbage<L, VL>(
+    storage: &Storage<L>,
+    version_layout: VL,
+) -> Result<StorageGcReport>
+where
+    L: VersionJsonRootLayout + Clone,
+    VL: VersionJsonInstanceLayout + Clone,
+{
+    let mut collector = SharedReferenceCollector::default();
+    let mut references = SharedReferences::default();
+    let mut instances = Vec::new();
+
+    for instance in storage.instances(version_layout)? {
+        let name = instance
+            .name()
+            .unwrap_or_else(|| instance.path.display().to_string());
+        collector
+            .collect_instance(&mut references, &instance)
+            .with_context(|| format!("collect references of instance '{name}' failed"))?;
+        instances.push(name);
+    }
+
+    let mut report = StorageGcReport {
+        mode: StorageGcMode::DryRun,
+        instances,
+        scanned_files: 0,
+        scanned_bytes: 0,
+        orphaned: Vec::new(),
+        orphaned_bytes: 0,
+        deleted_bytes: 0,
+    };
+
+    for kind in SharedArtifactKind::all() {
+        // Runtimes and outside game directories aren't owned by the game root, never sweep them.
+        let Some(resource) = kind.root_resource() else {
+            continue;
+        };
+        let mut files = Vec::new();
+        collect_files(&storage.try_get_resource(resource)?, &mut files)?;
+
+        for (path, size) in files {
+            report.scanned_files += 1;
+            report.scanned_bytes += size;
+            if references.contains(kind, &path) {
+                continue;
+            }
+
+            report.orphaned_bytes += size;
+            report.orphaned.push(StorageGcEntry { kind, path, size });
+        }
+    }
+
+    report
+        .orphaned
+        .sort_by(|left, right| left.path.cmp(&right.path));
+    Ok(report)
+}
+
+fn remove_empty_directories(root: &Path) -> Result<bool> {
+    if !root.is_dir() {
+        return Ok(false);
+    }
+
+    let mut empty = true;
+    for entry in root.read_dir()? {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() {
+            empty = false;
+            continue;
+        }
+
+        let path = entry.path();
+        if remove_empty_directories(&path)? {
+            std::fs::remove_dir(&path)
+                .with_context(|| format!("remove empty directory failed: {}", path.display()))?;
+        } else {
+            empty = false;
+        }
+    }
+
+    Ok(empty)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_gc_sweeps_orphans_and_keeps_objects_of_unreadable_indexes() {
+    use crate::{
+        families::{
+            installer::install_profile_path,
+            version_json::{BaseInstanceLayout, BaseRootLayout},
+        },
+        references::write_test_instance,
+    };
+
+    let root = std::env::temp_dir().join(format!("elemental-gc-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    let write = |relative: &str, contents: &[u8]| {
+        let path = root.join(relative);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, contents).unwrap();
+        path
+    };
+    write_test_instance(&root, "a", &["org/a/a/1/a-1.jar"], "idx");
+    write(
+        "assets/indexes/idx.json",
+        br#"{"objects":{"a.ogg":{"hash":"aa11","size":4}}}"#,
+    );
+    let kept_library = write("libraries/org/a/a/1/a-1.jar", b"data");
+    let orphaned_library = write("libraries/org/b/b/1/b-1.jar", b"data");
+    let kept_object = write("assets/objects/aa/aa11", b"data");
+    let orphaned_object = write("assets/objects/bb/bb22", b"data");
+    // A Forge-style profile whose processors write outputs named by coordinate or data key.
+    write_test_instance(&root, "forge", &[], "idx");
+    let profile = serde_json::json!({
+        "data": {
+            "PATCHED": { "client": "[net.minecraftforge:forge:1.20.1-47:client]", "server": "" },
+            "PATCHED_SHA": { "client": "'0123'", "server": "'4567'" },
+        },
+        "processors": [{
+            "jar": "net.minecraftforge:installertools:1.3",
+            "args": ["--task", "MCP_DATA"],
+            "outputs": {
+                "[net.minecraft:client:1.20.1:srg]": "'89ab'",
+                "{PATCHED}": "{PATCHED_SHA}",
+            },
+        }],
+    });
+    let profile_path = install_profile_path(&root.join("versions/forge"), "forge");
+    write(
+        profile_path.strip_prefix(&root).unwrap().to_str().unwrap(),
+        &serde_json::to_vec(&profile).unwrap(),
+    );
+    let installer_tools = write(
+        "libraries/net/minecraftforge/installertools/1.3/installertools-1.3.jar",
+        b"data",
+    );
+    let srg_client = write(
+        "libraries/net/minecraft/client/1.20.1/client-1.20.1-srg.jar",
+        b"data",
+    );
+    let patched_client = write(
+        "libraries/net/minecraftforge/forge/1.20.1-47/forge-1.20.1-47-client.jar",
+        b"data",
+    );
+    let storage = Storage::new(&root, BaseRootLayout);
+
+    let report = scan_storage_garbage(&storage, BaseInstanceLayout).unwrap();
+    let mut instances = report.instances.clone();
+    instances.sort();
+    assert_eq!(instances, vec!["a", "forge"]);
+    assert_eq!(
+        report
+            .orphaned
+            .iter()
+            .map(|entry| entry.path.clone())
+            .collect::<Vec<_>>(),
+        vec![orphaned_object.clone(), orphaned_library.clone()]
+    );
+
+    // An instance whose index is missing may use any object, so none of them are swept.
+    write_test_instance(&root, "b", &[], "missing");
+    let report = collect_storage_garbage(&storage, BaseInstanceLayout, StorageGcMode::Delete)
+        .await
+        .unwrap();
+    assert_eq!(report.orphaned_count_of(SharedArtifactKind::AssetObject), 0);
+    assert_eq!(report.orphaned_count_of(SharedArtifactKind::Library), 1);
+    assert_eq!(report.deleted_bytes, 4);
+    assert!(!orphaned_library.exists());
+    assert!(!root.join("libraries/org/b").exists());
+    assert!(kept_library.exists() && kept_object.exists() && orphaned_object.exists());
+    assert!(installer_tools.exists() && srg_client.exists() && patched_client.exists());
+    let _ = std::fs::remove_dir_all(&root);
+}
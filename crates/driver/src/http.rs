@@ -1,12 +1,25 @@
-use std::time::Duration;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
-use anyhow::{Context, Result};
-use serde::de::DeserializeOwned;
+use anyhow::{Context, Result, anyhow, bail};
+use elemental_core::storage::write_atomic;
+use reqwest::{
+    StatusCode,
+    header::{ETAG, HeaderName, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED},
+};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use sha1_smol::Sha1;
+
+pub const DEFAULT_METADATA_TTL: Duration = Duration::from_secs(10 * 60);
 
 #[derive(Debug, Clone)]
 pub struct HttpSource<E> {
     client: reqwest::Client,
     endpoints: E,
+    cache: Option<HttpCache>,
 }
 
 impl<E> HttpSource<E> {
@@ -15,7 +28,16 @@ impl<E> HttpSource<E> {
     }
 
     pub fn with_client(endpoints: E, client: reqwest::Client) -> Self {
-        Self { client, endpoints }
+        Self {
+            client,
+            endpoints,
+            cache: None,
+        }
+    }
+
+    pub fn with_cache(mut self, cache: HttpCache) -> Self {
+        self.cache = Some(cache);
+        self
     }
 
     pub fn client(&self) -> &reqwest::Client {
@@ -25,6 +47,200 @@ impl<E> HttpSource<E> {
     pub fn endpoints(&self) -> &E {
         &self.endpoints
     }
+
+    pub fn cache(&self) -> Option<&HttpCache> {
+        self.cache.as_ref()
+    }
+
+    /// Fetches a metadata document through the cache, when one is set.
+    pub async fn fetch_json<T>(&self, url: &str, source_name: &str) -> Result<Fetched<T>>
+    where
+        T: DeserializeOwned,
+    {
+        fetch_cached(&self.client, self.cache.as_ref(), url, source_name)
+            .await?
+            .try_map(|bytes| {
+                serde_json::from_slice(&bytes)
+                    .with_context(|| format!("decode {source_name} resource failed: {url}"))
+            })
+    }
+
+    pub async fn fetch_text(&self, url: &str, source_name: &str) -> Result<Fetched<String>> {
+        fetch_cached(&self.client, self.cache.as_ref(), url, source_name)
+            .await?
+            .try_map(|bytes| {
+                String::from_utf8(bytes)
+                    .with_context(|| format!("decode {source_name} resource failed: {url}"))
+            })
+    }
+}
+
+/// Sources that fetch through an [`HttpSource`] and can adopt a shared, preconfigured client.
+pub trait HttpClientSource {
+    fn set_client(&mut self, client: reqwest::Client);
+    fn set_cache(&mut self, cache: HttpCache);
+}
+
+impl<E> HttpClientSource for HttpSource<E> {
+    fn set_client(&mut self, client: reqwest::Client) {
+        self.client = client;
+    }
+
+    fn set_cache(&mut self, cache: HttpCache) {
+        self.cache = Some(cache);
+    }
+}
+
+/// A metadata response, `stale` when it was served from the cache because the origin was
+/// unreachable.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fetched<T> {
+    pub value: T,
+    pub stale: bool,
+}
+
+impl<T> Fetched<T> {
+    pub fn fresh(value: T) -> Self {
+        Self {
+            value,
+            stale: false,
+        }
+    }
+
+    pub fn stale(value: T) -> Self {
+        Self { value, stale: true }
+    }
+
+    pub fn into_value(self) -> T {
+        self.value
+    }
+
+    pub fn map<U>(self, map: impl FnOnce(T) -> U) -> Fetched<U> {
+        Fetched {
+            value: map(self.value),
+            stale: self.stale,
+        }
+    }
+
+    pub fn try_map<U>(self, map: impl FnOnce(T) -> Result<U>) -> Result<Fetched<U>> {
+        Ok(Fetched {
+            value: map(self.value)?,
+            stale: self.stale,
+        })
+    }
+
+    /// Marks the value stale when `stale` is set, for values built from several responses.
+    pub fn or_stale(mut self, stale: bool) -> Self {
+        self.stale |= stale;
+        self
+    }
+}
+
+/// On-disk cache for metadata responses, revalidated with `ETag` and `Last-Modified`.
+#[derive(Debug, Clone)]
+pub struct HttpCache {
+    root: PathBuf,
+    default_ttl: Duration,
+    source_ttls: HashMap<String, Duration>,
+    offline_fallback: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheEntry {
+    url: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    /// Seconds since the unix epoch of the last successful fetch or revalidation.
+    fetched_at: u64,
+    sha1: String,
+}
+
+#[derive(Debug)]
+struct CachedResponse {
+    entry: CacheEntry,
+    body: Vec<u8>,
+}
+
+impl HttpCache {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            default_ttl: DEFAULT_METADATA_TTL,
+            source_ttls: HashMap::new(),
+            offline_fallback: true,
+        }
+    }
+
+    pub fn with_default_ttl(mut self, ttl: Duration) -> Self {
+        self.default_ttl = ttl;
+        self
+    }
+
+    /// Overrides the ttl of one source, keyed by its source name such as `"fabric source"`.
+    pub fn with_source_ttl(mut self, source_name: impl Into<String>, ttl: Duration) -> Self {
+        self.source_ttls.insert(source_name.into(), ttl);
+        self
+    }
+
+    /// Whether cached entries are served, marked stale, when the origin can't be reached.
+    pub fn with_offline_fallback(mut self, offline_fallback: bool) -> Self {
+        self.offline_fallback = offline_fallback;
+        self
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn ttl(&self, source_name: &str) -> Duration {
+        self.source_ttls
+            .get(source_name)
+            .copied()
+            .unwrap_or(self.default_ttl)
+    }
+
+    fn entry_paths(&self, url: &str) -> (PathBuf, PathBuf) {
+        let key = Sha1::from(url).digest().to_string();
+        (
+            self.root.join(format!("{key}.json")),
+            self.root.join(format!("{key}.body")),
+        )
+    }
+
+    // Missing, unreadable or mismatched entries are treated as a cache miss.
+    async fn load(&self, url: &str) -> Option<CachedResponse> {
+        let (entry_path, body_path) = self.entry_paths(url);
+        let entry = tokio::fs::read(&entry_path).await.ok()?;
+        let entry = serde_json::from_slice::<CacheEntry>(&entry).ok()?;
+        if entry.url != url {
+            return None;
+        }
+
+        let body = tokio::fs::read(&body_path).await.ok()?;
+        if Sha1::from(&body).digest().to_string() != entry.sha1 {
+            return None;
+        }
+
+        Some(CachedResponse { entry, body })
+    }
+
+    async fn store_entry(&self, entry: &CacheEntry) -> Result<()> {
+        let (entry_path, _) = self.entry_paths(entry.url.as_str());
+        let raw = serde_json::to_vec(entry).context("encode http cache entry failed")?;
+        write_atomic(&entry_path, &raw).await
+    }
+
+    async fn store(&self, entry: &CacheEntry, body: &[u8]) -> Result<()> {
+        let (_, body_path) = self.entry_paths(entry.url.as_str());
+        write_atomic(&body_path, body).await?;
+        self.store_entry(entry).await
+    }
+}
+
+impl CacheEntry {
+    fn age(&self) -> Duration {
+        Duration::from_secs(unix_now().saturating_sub(self.fetched_at))
+    }
 }
 
 pub fn build_default_client(source_name: &str) -> reqwest::Client {
@@ -39,6 +255,11 @@ pub async fn fetch_json<T>(client: &reqwest::Client, url: &str, source_name: &st
 where
     T: DeserializeOwned,
 {
+    if let Some(bytes) = read_local(url, source_name).await? {
+        return serde_json::from_slice(&bytes)
+            .with_context(|| format!("decode {source_name} resource failed: {url}"));
+    }
+
     send_ok(client, url, source_name)
         .await?
         .json::<T>()
@@ -47,6 +268,11 @@ where
 }
 
 pub async fn fetch_text(client: &reqwest::Client, url: &str, source_name: &str) -> Result<String> {
+    if let Some(bytes) = read_local(url, source_name).await? {
+        return String::from_utf8(bytes)
+            .with_context(|| format!("decode {source_name} resource failed: {url}"));
+    }
+
     send_ok(client, url, source_name)
         .await?
         .text()
@@ -59,6 +285,10 @@ pub async fn fetch_bytes(
     url: &str,
     source_name: &str,
 ) -> Result<Vec<u8>> {
+    if let Some(bytes) = read_local(url, source_name).await? {
+        return Ok(bytes);
+    }
+
     send_ok(client, url, source_name)
         .await?
         .bytes()
@@ -67,6 +297,114 @@ pub async fn fetch_bytes(
         .with_context(|| format!("decode {source_name} resource failed: {url}"))
 }
 
+async fn fetch_cached(
+    client: &reqwest::Client,
+    cache: Option<&HttpCache>,
+    url: &str,
+    source_name: &str,
+) -> Result<Fetched<Vec<u8>>> {
+    let Some(cache) = cache else {
+        return fetch_bytes(client, url, source_name)
+            .await
+            .map(Fetched::fresh);
+    };
+    if let Some(bytes) = read_local(url, source_name).await? {
+        return Ok(Fetched::fresh(bytes));
+    }
+
+    let mut cached = cache.load(url).await;
+    let ttl = cache.ttl(source_name);
+    if let Some(cached) = cached.take_if(|cached| cached.entry.age() < ttl) {
+        return Ok(Fetched::fresh(cached.body));
+    }
+
+    let mut request = client.get(url);
+    if let Some(cached) = &cached {
+        if let Some(etag) = &cached.entry.etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &cached.entry.last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
+        }
+    }
+
+    // Connection failures and server errors fall back to the cached copy, client errors don't.
+    let outcome = request
+        .send()
+        .await
+        .with_context(|| format!("request {source_name} resource failed: {url}"))
+        .and_then(|response| {
+            if response.status().is_server_error() {
+                return response
+                    .error_for_status()
+                    .with_context(|| format!("{source_name} returned error status: {url}"));
+            }
+            Ok(response)
+        });
+    let response = match outcome {
+        Ok(response) => response,
+        Err(error) => {
+            return match cached {
+                Some(cached) if cache.offline_fallback => Ok(Fetched::stale(cached.body)),
+                _ => Err(error),
+            };
+        }
+    };
+
+    if response.status() == StatusCode::NOT_MODIFIED {
+        let Some(mut cached) = cached else {
+            bail!("{source_name} answered an unconditional request with 304: {url}");
+        };
+        cached.entry.fetched_at = unix_now();
+        if let Some(etag) = header_value(&response, ETAG) {
+            cached.entry.etag = Some(etag);
+        }
+        if let Some(last_modified) = header_value(&response, LAST_MODIFIED) {
+            cached.entry.last_modified = Some(last_modified);
+        }
+        // A cache that can't be written only costs another full fetch.
+        let _ = cache.store_entry(&cached.entry).await;
+        return Ok(Fetched::fresh(cached.body));
+    }
+
+    let response = response
+        .error_for_status()
+        .with_context(|| format!("{source_name} returned error status: {url}"))?;
+    let etag = header_value(&response, ETAG);
+    let last_modified = header_value(&response, LAST_MODIFIED);
+    let body = response
+        .bytes()
+        .await
+        .with_context(|| format!("decode {source_name} resource failed: {url}"))?
+        .to_vec();
+    let entry = CacheEntry {
+        url: url.to_owned(),
+        etag,
+        last_modified,
+        fetched_at: unix_now(),
+        sha1: Sha1::from(&body).digest().to_string(),
+    };
+    let _ = cache.store(&entry, &body).await;
+
+    Ok(Fetched::fresh(body))
+}
+
+fn header_value(response: &reqwest::Response, name: HeaderName) -> Option<String> {
+    response
+        .headers()
+        .get(name)?
+        .to_str()
+        .ok()
+        .map(str::to_owned)
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
 async fn send_ok(
     client: &reqwest::Client,
     url: &str,
@@ -80,3 +418,97 @@ async fn send_ok(
         .error_for_status()
         .with_context(|| format!("{source_name} returned error status: {url}"))
 }
+
+// Origins overridden with a local directory resolve to `file://` urls, which reqwest can't fetch.
+async fn read_local(url: &str, source_name: &str) -> Result<Option<Vec<u8>>> {
+    let Ok(parsed) = reqwest::Url::parse(url) else {
+        return Ok(None);
+    };
+    if parsed.scheme() != "file" {
+        return Ok(None);
+    }
+
+    let path = parsed
+        .to_file_path()
+        .map_err(|_| anyhow!("{source_name} local url is not a file path: {url}"))?;
+    tokio::fs::read(&path)
+        .await
+        .with_context(|| format!("read {source_name} local resource failed: {url}"))
+        .map(Some)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_http_cache_serves_fresh_entries_revalidates_and_falls_back_when_offline() {
+    use std::io::{BufRead, BufReader, Write};
+
+    let responses = [
+        "HTTP/1.1 200 OK\r\nContent-Length: 3\r\nETag: \"v1\"\r\nConnection: close\r\n\r\none",
+        "HTTP/1.1 304 Not Modified\r\nETag: \"v1\"\r\nConnection: close\r\n\r\n",
+        "HTTP/1.1 500 Internal Server Error\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        "HTTP/1.1 500 Internal Server Error\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+    ];
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("bind test server");
+    let url = format!("http://{}/meta.json", listener.local_addr().unwrap());
+    let seen = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let requests = seen.clone();
+    std::thread::spawn(move || {
+        for (stream, response) in listener.incoming().zip(responses) {
+            let mut stream = stream.expect("accept test connection");
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut if_none_match = None;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':')
+                    && name.eq_ignore_ascii_case("if-none-match")
+                {
+                    if_none_match = Some(value.trim().to_owned());
+                }
+            }
+            requests.lock().unwrap().push(if_none_match);
+            let _ = stream.write_all(response.as_bytes());
+        }
+    });
+    let root = std::env::temp_dir().join(format!("elemental-http-cache-{}", std::process::id()));
+    let _ = tokio::fs::remove_dir_all(&root).await;
+    let source =
+        |cache: HttpCache| HttpSource::with_client((), reqwest::Client::new()).with_cache(cache);
+
+    // Within the ttl the second fetch never reaches the origin.
+    let cached = source(HttpCache::new(&root).with_default_ttl(Duration::from_secs(3600)));
+    assert_eq!(
+        cached.fetch_text(&url, "test source").await.unwrap(),
+        Fetched::fresh("one".to_owned())
+    );
+    assert_eq!(
+        cached.fetch_text(&url, "test source").await.unwrap(),
+        Fetched::fresh("one".to_owned())
+    );
+    assert_eq!(seen.lock().unwrap().clone(), vec![None]);
+
+    // Past the ttl the entry is revalidated with its etag.
+    let expired = source(HttpCache::new(&root).with_default_ttl(Duration::ZERO));
+    assert_eq!(
+        expired.fetch_text(&url, "test source").await.unwrap(),
+        Fetched::fresh("one".to_owned())
+    );
+    assert_eq!(seen.lock().unwrap()[1].as_deref(), Some("\"v1\""));
+
+    // A failing origin serves the cached copy marked stale, unless the fallback is off.
+    assert_eq!(
+        expired.fetch_text(&url, "test source").await.unwrap(),
+        Fetched::stale("one".to_owned())
+    );
+    let strict = source(
+        HttpCache::new(&root)
+            .with_default_ttl(Duration::ZERO)
+            .with_offline_fallback(false),
+    );
+    assert!(strict.fetch_text(&url, "test source").await.is_err());
+    assert_eq!(seen.lock().unwrap().len(), 4);
+
+    tokio::fs::remove_dir_all(&root).await.unwrap();
+}
@@ -1,10 +1,14 @@
+pub mod archive;
 pub mod catalog;
 pub mod descriptors;
 pub mod driver;
 pub mod drivers;
 pub mod families;
-mod http;
+pub mod gc;
+pub mod http;
 pub mod inspect;
 pub mod loader_version;
 mod maven;
+mod references;
 pub mod url;
+pub mod usage;
@@ -2,7 +2,7 @@ use anyhow::{Context, Result, bail};
 use elemental_schema::forge::MavenMetadataBody;
 use quick_xml::de::from_str;
 
-use crate::http::fetch_text;
+use crate::http::{Fetched, HttpSource};
 
 pub fn artifact_path(notation: &str, invalid_label: &str) -> Result<String> {
     let (coordinates, extension) = notation.split_once('@').unwrap_or((notation, "jar"));
@@ -28,11 +28,16 @@ pub fn classifier_notation(notation: &str, classifier: &str) -> String {
     format!("{coordinates}:{classifier}@{extension}")
 }
 
-pub async fn fetch_maven_metadata(
-    client: &reqwest::Client,
+pub async fn fetch_maven_metadata<E>(
+    source: &HttpSource<E>,
     url: String,
     source_label: &str,
-) -> Result<MavenMetadataBody> {
-    let raw = fetch_text(client, url.as_str(), source_label).await?;
-    from_str(&raw).with_context(|| format!("decode {source_label} maven metadata failed: {url}"))
+) -> Result<Fetched<MavenMetadataBody>> {
+    source
+        .fetch_text(url.as_str(), source_label)
+        .await?
+        .try_map(|raw| {
+            from_str(&raw)
+                .with_context(|| format!("decode {source_label} maven metadata failed: {url}"))
+        })
 }
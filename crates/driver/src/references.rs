@@ -0,0 +1,405 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use elemental_core::storage::{Storage, layout::Layoutable};
+use elemental_schema::mojang::piston::{PistonMetaData, PistonMetaLibraries};
+
+use crate::{
+    families::{
+        installer::{
+            install_profile_path, installer_coordinate_path, load_persisted_installer_state,
+        },
+        version_json::{
+            VersionJsonGameStorageExt, VersionJsonInstanceLayout, VersionJsonInstanceResource,
+            VersionJsonRootLayout, VersionJsonRootResource, VersionJsonVersionStorageExt,
+        },
+    },
+    maven::artifact_path,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SharedArtifactKind {
+    Library,
+    AssetObject,
+    AssetIndex,
+    AssetLogConfig,
+    /// A Java runtime directory, found on the system rather than stored under the game root.
+    Runtime,
+    /// A resource directory outside the instance root that several instances may point at.
+    GameDirectory,
+}
+
+impl SharedArtifactKind {
+    pub fn all() -> [Self; 6] {
+        [
+            Self::Library,
+            Self::AssetObject,
+            Self::AssetIndex,
+            Self::AssetLogConfig,
+            Self::Runtime,
+            Self::GameDirectory,
+        ]
+    }
+
+    /// The game root directory holding this kind, `None` for kinds living elsewhere.
+    pub fn root_resource(self) -> Option<VersionJsonRootResource> {
+        match self {
+            Self::Library => Some(VersionJsonRootResource::Libraries(None)),
+            Self::AssetObject => Some(VersionJsonRootResource::AssetObjects(None)),
+            Self::AssetIndex => Some(VersionJsonRootResource::AssetIndexes(None)),
+            Self::AssetLogConfig => Some(VersionJsonRootResource::AssetLogConfigs(None)),
+            Self::Runtime | Self::GameDirectory => None,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct SharedReferences {
+    paths: HashMap<PathBuf, SharedArtifactKind>,
+    library_directories: HashSet<PathBuf>,
+    /// Kinds an instance may use without the references being known, every file of them is kept.
+    pinned: HashSet<SharedArtifactKind>,
+}
+
+impl SharedReferences {
+    pub(crate) fn paths(&self) -> impl Iterator<Item = (&PathBuf, SharedArtifactKind)> + '_ {
+        self.paths.iter().map(|(path, kind)| (path, *kind))
+    }
+
+    pub(crate) fn contains(&self, kind: SharedArtifactKind, path: &Path) -> bool {
+        if self.pinned.contains(&kind) || self.paths.contains_key(path) {
+            return true;
+        }
+
+        // Installer jars are not recorded in persisted state; keep them next to the loader artifacts they produced.
+        kind == SharedArtifactKind::Library
+            && path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().ends_with("-installer.jar"))
+            && path
+                .parent()
+                .is_some_and(|parent| self.library_directories.contains(parent))
+    }
+
+    fn pin(&mut self, kind: SharedArtifactKind) {
+        self.pinned.insert(kind);
+    }
+
+    fn mark(&mut self, kind: SharedArtifactKind, path: PathBuf) {
+        self.paths.insert(path, kind);
+    }
+
+    fn mark_library<L>(&mut self, storage: &Storage<L>, relative_path: PathBuf) -> Result<()>
+    where
+        L: VersionJsonRootLayout,
+    {
+        let path =
+            storage.try_get_resource(VersionJsonRootResource::Libraries(Some(relative_path)))?;
+        if let Some(parent) = path.parent() {
+            self.library_directories.insert(parent.to_path_buf());
+        }
+        self.mark(SharedArtifactKind::Library, path);
+        Ok(())
+    }
+
+    fn mark_library_notation<L>(&mut self, storage: &Storage<L>, notation: &str) -> Result<()>
+    where
+        L: VersionJsonRootLayout,
+    {
+        match artifact_path(notation, "library") {
+            Ok(path) => self.mark_library(storage, PathBuf::from(path)),
+            Err(_) => Ok(()),
+        }
+    }
+
+    fn mark_libraries<L>(
+        &mut self,
+        storage: &Storage<L>,
+        libraries: &[PistonMetaLibraries],
+    ) -> Result<()>
+    where
+        L: VersionJsonRootLayout,
+    {
+        for library in libraries {
+            let artifacts = library.downloads.artifact.iter().chain(
+                library
+                    .downloads
+                    .classifiers
+                    .iter()
+                    .flat_map(|classifiers| classifiers.values()),
+            );
+            for artifact in artifacts {
+                self.mark_library(storage, PathBuf::from(artifact.path.as_str()))?;
+            }
+            self.mark_library_notation(storage, &library.name)?;
+        }
+
+        Ok(())
+    }
+
+    fn mark_embedded_libraries<L>(
+        &mut self,
+        storage: &Storage<L>,
+        version: &serde_json::Value,
+    ) -> Result<()>
+    where
+        L: VersionJsonRootLayout,
+    {
+        let Some(libraries) = version.get("libraries").and_then(|value| value.as_array()) else {
+            return Ok(());
+        };
+
+        for library in libraries {
+            if let Some(name) = library.get("name").and_then(|value| value.as_str()) {
+                self.mark_library_notation(storage, name)?;
+            }
+
+            let Some(downloads) = library.get("downloads") else {
+                continue;
+            };
+            let classifiers = downloads
+                .get("classifiers")
+                .and_then(|value| value.as_object())
+                .into_iter()
+                .flat_map(|classifiers| classifiers.values());
+            for artifact in downloads.get("artifact").into_iter().chain(classifiers) {
+                if let Some(path) = artifact.get("path").and_then(|value| value.as_str()) {
+                    self.mark_library(storage, PathBuf::from(path))?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct SharedReferenceCollector {
+    asset_objects: HashMap<PathBuf, Vec<PathBuf>>,
+}
+
+impl SharedReferenceCollector {
+    pub(crate) fn collect_instance<L, VL>(
+        &mut self,
+        references: &mut SharedReferences,
+        instance: &Storage<VL, Storage<L>>,
+    ) -> Result<()>
+    where
+        L: VersionJsonRootLayout,
+        VL: VersionJsonInstanceLayout,
+    {
+        let metadata = instance.metadata()?;
+        self.collect_metadata(references, &instance.parent, &metadata)?;
+        collect_installer_state(references, instance)
+    }
+
+    fn collect_metadata<L>(
+        &mut self,
+        references: &mut SharedReferences,
+        storage: &Storage<L>,
+        metadata: &PistonMetaData,
+    ) -> Result<()>
+    where
+        L: VersionJsonRootLayout,
+    {
+        references.mark_libraries(storage, &metadata.libraries)?;
+
+        let asset_index_id = metadata.asset_index.id.clone();
+        let asset_index_path = storage.try_get_resource(VersionJsonRootResource::AssetIndexes(
+            Some(asset_index_id.clone()),
+        ))?;
+        if !self.asset_objects.contains_key(&asset_index_path) {
+            // An index that can't be read leaves the objects this instance uses unknown.
+            match storage.asset_index_objects(&asset_index_id) {
+                Ok(objects) => {
+                    let paths = objects
+                        .objects
+                        .into_values()
+                        .map(|object| {
+                            storage.try_get_resource(VersionJsonRootResource::AssetObjects(Some(
+                                object.hash,
+                            )))
+                        })
+                        .collect::<Result<Vec<PathBuf>>>()?;
+                    self.asset_objects.insert(asset_index_path.clone(), paths);
+                }
+                Err(_) => references.pin(SharedArtifactKind::AssetObject),
+            }
+        }
+        for path in self
+            .asset_objects
+            .get(&asset_index_path)
+            .into_iter()
+            .flatten()
+        {
+            references.mark(SharedArtifactKind::AssetObject, path.clone());
+        }
+        references.mark(SharedArtifactKind::AssetIndex, asset_index_path);
+
+        if let Some(client) = metadata
+            .logging
+            .as_ref()
+            .and_then(|logging| logging.client.as_ref())
+        {
+            references.mark(
+                SharedArtifactKind::AssetLogConfig,
+                storage.try_get_resource(VersionJsonRootResource::AssetLogConfigs(Some(
+                    client.file.id.clone(),
+                )))?,
+            );
+        }
+
+        Ok(())
+    }
+}
+
+fn collect_installer_state<L, VL>(
+    references: &mut SharedReferences,
+    instance: &Storage<VL, Storage<L>>,
+) -> Result<()>
+where
+    L: VersionJsonRootLayout,
+    VL: VersionJsonInstanceLayout,
+{
+    let state_root = instance.try_get_resource(VersionJsonInstanceResource::Elemental(None))?;
+    if !state_root.is_dir() {
+        return Ok(());
+    }
+
+    for entry in state_root.read_dir()? {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+
+        let family_name = entry.file_name().to_string_lossy().to_string();
+        if !install_profile_path(&instance.path, &family_name).exists() {
+            continue;
+        }
+
+        let state = load_persisted_installer_state(&instance.path, &family_name)?;
+        let storage = &instance.parent;
+        let install_profile = &state.install_profile;
+        references.mark_libraries(storage, &install_profile.libraries)?;
+
+        for processor in &install_profile.processors {
+            for coordinate in std::iter::once(&processor.jar).chain(&processor.classpath) {
+                references.mark_library(storage, installer_coordinate_path(coordinate)?)?;
+            }
+            // Output keys are either a `{DATA}` reference or a bracketed coordinate.
+            for key in processor.outputs.keys() {
+                let data_key = key.strip_prefix('{').and_then(|key| key.strip_suffix('}'));
+                match data_key.and_then(|data_key| install_profile.data.get(data_key)) {
+                    Some(entry) => {
+                        for raw in entry.client.iter().chain(entry.server.iter()) {
+                            mark_installer_data_library(references, storage, raw)?;
+                        }
+                    }
+                    None => mark_installer_data_library(references, storage, key)?,
+                }
+            }
+        }
+
+        for entry in install_profile.data.values() {
+            for raw in entry.client.iter().chain(entry.server.iter()) {
+                mark_installer_data_library(references, storage, raw)?;
+            }
+        }
+
+        if let Some(path) = install_profile.path.as_deref() {
+            references.mark_library_notation(storage, path)?;
+        }
+        for version in state
+            .embedded_version
+            .iter()
+            .chain(install_profile.version_info.iter())
+        {
+            references.mark_embedded_libraries(storage, version)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Marks the library behind a `[group:artifact:version]` installer value, other values are
+/// literals or paths inside the installer.
+fn mark_installer_data_library<L>(
+    references: &mut SharedReferences,
+    storage: &Storage<L>,
+    raw: &str,
+) -> Result<()>
+where
+    L: VersionJsonRootLayout,
+{
+    if let Some(notation) = raw.strip_prefix('[').and_then(|raw| raw.strip_suffix(']')) {
+        references.mark_library(storage, installer_coordinate_path(notation)?)?;
+    }
+    Ok(())
+}
+
+pub(crate) fn collect_files(root: &Path, files: &mut Vec<(PathBuf, u64)>) -> Result<()> {
+    if !root.is_dir() {
+        return Ok(());
+    }
+
+    for entry in root
+        .read_dir()
+        .with_context(|| format!("read directory failed: {}", root.display()))?
+    {
+        let entry = entry?;
+        // Hidden entries are in-flight downloads or foreign bookkeeping, never shared artifacts.
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            collect_files(&entry.path(), files)?;
+        } else if file_type.is_file() {
+            files.push((entry.path(), entry.metadata()?.len()));
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes an instance whose metadata lists `libraries` and the asset index `asset_index_id`.
+#[cfg(test)]
+pub(crate) fn write_test_instance(
+    root: &Path,
+    name: &str,
+    libraries: &[&str],
+    asset_index_id: &str,
+) {
+    let instance_root = root.join("versions").join(name);
+    std::fs::create_dir_all(&instance_root).unwrap();
+    let libraries = libraries
+        .iter()
+        .map(|path| {
+            serde_json::json!({
+                "name": format!("test:{name}:1"),
+                "downloads": { "artifact": { "path": path, "url": format!("https://example.invalid/{path}") } },
+            })
+        })
+        .collect::<Vec<_>>();
+    let metadata = serde_json::json!({
+        "assetIndex": { "id": asset_index_id, "sha1": "", "size": 0, "totalSize": 0, "url": "" },
+        "assets": asset_index_id,
+        "downloads": { "client": { "sha1": "", "size": 0, "url": "" } },
+        "id": name,
+        "libraries": libraries,
+        "mainClass": "net.minecraft.client.main.Main",
+        "type": "release",
+        "time": "2013-04-18T15:00:00+00:00",
+        "releaseTime": "2013-04-18T15:00:00+00:00",
+    });
+    std::fs::write(
+        instance_root.join(format!("{name}.json")),
+        serde_json::to_vec(&metadata).unwrap(),
+    )
+    .unwrap();
+    std::fs::write(instance_root.join(format!("{name}.jar")), b"jar").unwrap();
+}
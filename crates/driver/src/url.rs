@@ -1,6 +1,6 @@
-use std::{collections::HashMap, fmt::Debug, hash::Hash};
+use std::{collections::HashMap, fmt::Debug, hash::Hash, path::Path};
 
-use anyhow::{Context, Result, bail};
+use anyhow::{Context, Result, anyhow, bail};
 use reqwest::Url;
 
 pub trait Origin: Copy + Debug + Eq + Hash + Send + Sync + 'static {
@@ -11,19 +11,24 @@ pub trait Origin: Copy + Debug + Eq + Hash + Send + Sync + 'static {
 #[derive(Debug, Clone)]
 pub struct OriginPolicy<O: Origin> {
     overrides: HashMap<O, Url>,
+    mirrors: HashMap<O, Vec<Url>>,
 }
 
 impl<O: Origin> Default for OriginPolicy<O> {
     fn default() -> Self {
         Self {
             overrides: HashMap::new(),
+            mirrors: HashMap::new(),
         }
     }
 }
 
 impl<O: Origin> OriginPolicy<O> {
     pub fn new(overrides: HashMap<O, Url>) -> Self {
-        Self { overrides }
+        Self {
+            overrides,
+            mirrors: HashMap::new(),
+        }
     }
 
     pub fn with_override(mut self, origin: O, raw_url: String) -> Result<Self> {
@@ -33,6 +38,43 @@ impl<O: Origin> OriginPolicy<O> {
         Ok(self)
     }
 
+    /// Serves `origin` from a local directory, such as an offline Maven repository.
+    pub fn with_local_override(mut self, origin: O, directory: impl AsRef<Path>) -> Result<Self> {
+        self.overrides
+            .insert(origin, local_directory_url(directory.as_ref())?);
+        Ok(self)
+    }
+
+    pub fn with_mirror(mut self, origin: O, raw_url: String) -> Result<Self> {
+        let url = Url::parse(&raw_url)
+            .with_context(|| format!("parse origin mirror failed: {raw_url}"))?;
+        self.mirrors.entry(origin).or_default().push(url);
+        Ok(self)
+    }
+
+    pub fn with_mirrors(
+        self,
+        origin: O,
+        raw_urls: impl IntoIterator<Item = String>,
+    ) -> Result<Self> {
+        raw_urls
+            .into_iter()
+            .try_fold(self, |policy, raw_url| policy.with_mirror(origin, raw_url))
+    }
+
+    pub fn with_local_mirror(mut self, origin: O, directory: impl AsRef<Path>) -> Result<Self> {
+        let url = local_directory_url(directory.as_ref())?;
+        self.mirrors.entry(origin).or_default().push(url);
+        Ok(self)
+    }
+
+    pub fn mirrors(&self, origin: O) -> &[Url] {
+        self.mirrors
+            .get(&origin)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
     pub fn base_url(&self, origin: O) -> Result<Url> {
         if let Some(url) = self.overrides.get(&origin) {
             return Ok(url.clone());
@@ -43,24 +85,20 @@ impl<O: Origin> OriginPolicy<O> {
     }
 
     pub fn resolve(&self, origin: O, path: &str) -> Result<String> {
-        let base = self.base_url(origin)?;
-        let suffix = path.trim_start_matches('/');
-        if suffix.is_empty() {
-            return Ok(trim_trailing_slash(base.as_str()).to_owned());
+        join_origin_path(&self.base_url(origin)?, path)
+    }
+
+    /// Resolves `path` against the primary base of `origin` followed by its mirrors.
+    pub fn resolve_candidates(&self, origin: O, path: &str) -> Result<Vec<String>> {
+        let mut candidates = vec![self.resolve(origin, path)?];
+        for mirror in self.mirrors(origin) {
+            let candidate = join_origin_path(mirror, path)?;
+            if !candidates.contains(&candidate) {
+                candidates.push(candidate);
+            }
         }
 
-        Url::parse(&format!(
-            "{}/{}",
-            trim_trailing_slash(base.as_str()),
-            suffix
-        ))
-        .with_context(|| {
-            format!(
-                "resolve origin path failed for base '{}' and path '{}'",
-                base, path
-            )
-        })
-        .map(|url| url.to_string())
+        Ok(candidates)
     }
 
     pub fn resolve_segments<const N: usize>(
@@ -90,6 +128,30 @@ impl<O: Origin> OriginPolicy<O> {
     }
 
     pub fn try_rewrite_origin_url(&self, raw_url: &str) -> Result<Option<String>> {
+        let Some((origin, suffix)) = self.match_origin(raw_url)? else {
+            return Ok(None);
+        };
+
+        self.resolve(origin, &suffix).map(Some)
+    }
+
+    pub fn rewrite_origin_url_candidates(&self, raw_url: &str) -> Result<Vec<String>> {
+        let Some(candidates) = self.try_rewrite_origin_url_candidates(raw_url)? else {
+            bail!("can't map url to a known origin: {raw_url}")
+        };
+
+        Ok(candidates)
+    }
+
+    pub fn try_rewrite_origin_url_candidates(&self, raw_url: &str) -> Result<Option<Vec<String>>> {
+        let Some((origin, suffix)) = self.match_origin(raw_url)? else {
+            return Ok(None);
+        };
+
+        self.resolve_candidates(origin, &suffix).map(Some)
+    }
+
+    fn match_origin(&self, raw_url: &str) -> Result<Option<(O, String)>> {
         let parsed = Url::parse(raw_url).with_context(|| format!("parse url failed: {raw_url}"))?;
 
         for origin in O::all() {
@@ -97,13 +159,21 @@ impl<O: Origin> OriginPolicy<O> {
                 format!("parse canonical origin failed: {}", origin.canonical())
             })?;
             if let Some(suffix) = origin_suffix(&parsed, &canonical) {
-                return self.resolve(*origin, &suffix).map(Some);
+                return Ok(Some((*origin, suffix)));
             }
 
             if let Some(override_base) = self.overrides.get(origin)
                 && let Some(suffix) = origin_suffix(&parsed, override_base)
             {
-                return self.resolve(*origin, &suffix).map(Some);
+                return Ok(Some((*origin, suffix)));
+            }
+
+            if let Some(suffix) = self
+                .mirrors(*origin)
+                .iter()
+                .find_map(|mirror| origin_suffix(&parsed, mirror))
+            {
+                return Ok(Some((*origin, suffix)));
             }
         }
 
@@ -152,10 +222,41 @@ impl<O: Origin> OriginPolicy<O> {
     }
 }
 
+fn local_directory_url(directory: &Path) -> Result<Url> {
+    let directory = std::path::absolute(directory)
+        .with_context(|| format!("resolve local origin failed: {}", directory.display()))?;
+    Url::from_directory_path(&directory).map_err(|_| {
+        anyhow!(
+            "local origin is not a directory path: {}",
+            directory.display()
+        )
+    })
+}
+
 fn trim_trailing_slash(raw_url: &str) -> &str {
     raw_url.trim_end_matches('/')
 }
 
+fn join_origin_path(base: &Url, path: &str) -> Result<String> {
+    let suffix = path.trim_start_matches('/');
+    if suffix.is_empty() {
+        return Ok(trim_trailing_slash(base.as_str()).to_owned());
+    }
+
+    Url::parse(&format!(
+        "{}/{}",
+        trim_trailing_slash(base.as_str()),
+        suffix
+    ))
+    .with_context(|| {
+        format!(
+            "resolve origin path failed for base '{}' and path '{}'",
+            base, path
+        )
+    })
+    .map(|url| url.to_string())
+}
+
 fn origin_suffix(raw_url: &Url, base_url: &Url) -> Option<String> {
     if !same_origin(raw_url, base_url) {
         return None;
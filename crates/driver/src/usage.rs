@@ -0,0 +1,369 @@
+use std::{
+    collections::{HashMap, hash_map::Entry},
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use elemental_core::{
+    runtime::distribution::Distribution,
+    storage::{Storage, layout::Layoutable},
+};
+
+use crate::{
+    families::version_json::{
+        PistonMetaDataExt, VersionJsonGameStorageExt, VersionJsonInstanceLayout,
+        VersionJsonInstanceResource, VersionJsonRootLayout, VersionJsonVersionStorageExt,
+    },
+    references::{SharedArtifactKind, SharedReferenceCollector, SharedReferences},
+};
+
+const PRIVATE_RESOURCES: [VersionJsonInstanceResource; 6] = [
+    VersionJsonInstanceResource::Saves,
+    VersionJsonInstanceResource::Mods,
+    VersionJsonInstanceResource::Configs,
+    VersionJsonInstanceResource::ResourcePacks,
+    VersionJsonInstanceResource::ShaderPacks,
+    VersionJsonInstanceResource::Logs,
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstanceDiskUsage {
+    pub instance_name: String,
+    pub instance_root: PathBuf,
+    pub private_bytes: u64,
+    pub saves_bytes: u64,
+    pub mods_bytes: u64,
+    pub shared_artifacts: usize,
+    pub shared_bytes: u64,
+    pub exclusive_shared_bytes: u64,
+}
+
+impl InstanceDiskUsage {
+    pub fn total_bytes(&self) -> u64 {
+        self.private_bytes + self.shared_bytes
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SharedArtifactUsage {
+    pub kind: SharedArtifactKind,
+    pub path: PathBuf,
+    pub size: u64,
+    pub instances: Vec<String>,
+}
+
+impl SharedArtifactUsage {
+    pub fn share_count(&self) -> usize {
+        self.instances.len()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorageDiskUsage {
+    pub instances: Vec<InstanceDiskUsage>,
+    pub shared: Vec<SharedArtifactUsage>,
+    pub shared_bytes: u64,
+}
+
+impl StorageDiskUsage {
+    pub fn instance(&self, instance_name: &str) -> Option<&InstanceDiskUsage> {
+        self.instances
+            .iter()
+            .find(|usage| usage.instance_name == instance_name)
+    }
+
+    pub fn shared_bytes_of(&self, kind: SharedArtifactKind) -> u64 {
+        self.shared
+            .iter()
+            .filter(|usage| usage.kind == kind)
+            .map(|usage| usage.size)
+            .sum()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct DiskUsageScanner {
+    collector: SharedReferenceCollector,
+    // Asset objects are named by their hash, so a size seen once holds until the file is removed.
+    object_sizes: HashMap<PathBuf, u64>,
+}
+
+impl DiskUsageScanner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn clear_cache(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Runtimes are attributed to the instances whose Java major version they match, the same
+    /// way a launch without an explicit executable picks one.
+    pub fn scan<L, VL>(
+        &mut self,
+        storage: &Storage<L>,
+        version_layout: VL,
+        runtimes: &[Distribution],
+    ) -> Result<StorageDiskUsage>
+    where
+        L: VersionJsonRootLayout + Clone,
+        VL: VersionJsonInstanceLayout + Clone,
+    {
+        let mut instances = Vec::new();
+        let mut shared = HashMap::<PathBuf, SharedArtifactUsage>::new();
+
+        for instance in storage.instances(version_layout)? {
+            let instance_name = instance
+                .name()
+                .unwrap_or_else(|| instance.path.display().to_string());
+            let (mut usage, external) = private_usage(&instance, instance_name.clone())
+                .with_context(|| format!("measure instance '{instance_name}' failed"))?;
+
+            let mut references = SharedReferences::default();
+            self.collector
+                .collect_instance(&mut references, &instance)
+                .with_context(|| {
+                    format!("collect references of instance '{instance_name}' failed")
+                })?;
+
+            let mut artifacts = Vec::new();
+            for (path, kind) in references.paths() {
+                if let Some(size) = self.shared_size(kind, path)? {
+                    artifacts.push((kind, path.clone(), Some(size)));
+                }
+            }
+            for path in external {
+                artifacts.push((SharedArtifactKind::GameDirectory, path, None));
+            }
+            let java_major_version = instance.metadata()?.java_major_version();
+            if let Some(runtime) = runtimes
+                .iter()
+                .find(|runtime| runtime.matches_java_major_version(java_major_version))
+            {
+                artifacts.push((SharedArtifactKind::Runtime, runtime.path.clone(), None));
+            }
+
+            for (kind, path, size) in artifacts {
+                let artifact = match shared.entry(path) {
+                    Entry::Occupied(entry) => entry.into_mut(),
+                    Entry::Vacant(entry) => {
+                        // Directories are measured once per scan, however many instances use them.
+                        let path = entry.key().clone();
+                        let size = match size {
+                            Some(size) => size,
+                            None => directory_size(&path)?,
+                        };
+                        entry.insert(SharedArtifactUsage {
+                            kind,
+                            path,
+                            size,
+                            instances: Vec::new(),
+                        })
+                    }
+                };
+                usage.shared_artifacts += 1;
+                usage.shared_bytes += artifact.size;
+                artifact.instances.push(instance_name.clone());
+            }
+
+            instances.push(usage);
+        }
+
+        let mut shared = shared.into_values().collect::<Vec<SharedArtifactUsage>>();
+        shared.sort_by(|left, right| left.path.cmp(&right.path));
+        for artifact in shared.iter().filter(|artifact| artifact.share_count() == 1) {
+            if let Some(usage) = instances
+                .iter_mut()
+                .find(|usage| usage.instance_name == artifact.instances[0])
+            {
+                usage.exclusive_shared_bytes += artifact.size;
+            }
+        }
+        instances.sort_by(|left, right| left.instance_name.cmp(&right.instance_name));
+
+        Ok(StorageDiskUsage {
+            instances,
+            shared_bytes: shared.iter().map(|artifact| artifact.size).sum(),
+            shared,
+        })
+    }
+
+    fn shared_size(&mut self, kind: SharedArtifactKind, path: &Path) -> Result<Option<u64>> {
+        if let Some(size) = self.object_sizes.get(path) {
+            return Ok(Some(*size));
+        }
+
+        let size = match std::fs::metadata(path) {
+            Ok(metadata) if metadata.is_file() => metadata.len(),
+            Ok(_) => return Ok(None),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => {
+                return Err(error).with_context(|| format!("stat failed: {}", path.display()));
+            }
+        };
+        if kind == SharedArtifactKind::AssetObject {
+            self.object_sizes.insert(path.to_path_buf(), size);
+        }
+        Ok(Some(size))
+    }
+}
+
+/// Resource directories outside the instance root are returned instead of counted, since other
+/// instances may point at them too.
+fn private_usage<L, VL>(
+    instance: &Storage<VL, Storage<L>>,
+    instance_name: String,
+) -> Result<(InstanceDiskUsage, Vec<PathBuf>)>
+where
+    L: VersionJsonRootLayout,
+    VL: VersionJsonInstanceLayout,
+{
+    let children = child_usage(&instance.path)?;
+    let mut usage = InstanceDiskUsage {
+        instance_name,
+        instance_root: instance.path.clone(),
+        private_bytes: children.values().sum(),
+        saves_bytes: 0,
+        mods_bytes: 0,
+        shared_artifacts: 0,
+        shared_bytes: 0,
+        exclusive_shared_bytes: 0,
+    };
+    let mut external = Vec::new();
+
+    for resource in PRIVATE_RESOURCES {
+        let path = instance.try_get_resource(resource.clone())?;
+        let size = match (path.parent(), path.file_name()) {
+            (Some(parent), Some(name)) if parent == instance.path => {
+                children.get(name).copied().unwrap_or_default()
+            }
+            _ if path.starts_with(&instance.path) => directory_size(&path)?,
+            _ => {
+                let size = directory_size(&path)?;
+                if path.is_dir() {
+                    external.push(path);
+                }
+                size
+            }
+        };
+
+        match resource {
+            VersionJsonInstanceResource::Saves => usage.saves_bytes = size,
+            VersionJsonInstanceResource::Mods => usage.mods_bytes = size,
+            _ => {}
+        }
+    }
+
+    Ok((usage, external))
+}
+
+fn child_usage(root: &Path) -> Result<HashMap<OsString, u64>> {
+    let mut children = HashMap::new();
+    if !root.is_dir() {
+        return Ok(children);
+    }
+
+    for entry in root
+        .read_dir()
+        .with_context(|| format!("read directory failed: {}", root.display()))?
+    {
+        let entry = entry?;
+        children.insert(entry.file_name(), entry_size(&entry)?);
+    }
+
+    Ok(children)
+}
+
+fn directory_size(root: &Path) -> Result<u64> {
+    if !root.is_dir() {
+        return Ok(0);
+    }
+
+    let mut size = 0;
+    for entry in root
+        .read_dir()
+        .with_context(|| format!("read directory failed: {}", root.display()))?
+    {
+        size += entry_size(&entry?)?;
+    }
+
+    Ok(size)
+}
+
+fn entry_size(entry: &std::fs::DirEntry) -> Result<u64> {
+    let file_type = entry.file_type()?;
+    if file_type.is_dir() {
+        directory_size(&entry.path())
+    } else if file_type.is_file() {
+        Ok(entry.metadata()?.len())
+    } else {
+        Ok(0)
+    }
+}
+
+#[test]
+fn test_scan_counts_outside_directories_and_runtimes_once() {
+    use elemental_core::runtime::distribution::DistributionReleaseData;
+
+    use crate::{
+        families::version_json::{BaseRootLayout, IsolatedInstanceLayout},
+        references::write_test_instance,
+    };
+
+    let root = std::env::temp_dir().join(format!("elemental-usage-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    let write = |relative: &str, contents: &[u8]| {
+        let path = root.join(relative);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, contents).unwrap();
+        path
+    };
+    write_test_instance(&root, "a", &["org/a/a/1/a-1.jar"], "idx");
+    write_test_instance(&root, "b", &["org/a/a/1/a-1.jar"], "idx");
+    write(
+        "assets/indexes/idx.json",
+        br#"{"objects":{"a.ogg":{"hash":"aa11","size":4}}}"#,
+    );
+    write("libraries/org/a/a/1/a-1.jar", b"library");
+    write("assets/objects/aa/aa11", b"data");
+    write("game/mods/mod.jar", b"mod-bytes");
+    write("runtime/bin/java", b"java");
+    let runtime = Distribution {
+        release: Some(DistributionReleaseData {
+            major_version: Some("1.8".to_owned()),
+            ..DistributionReleaseData::default()
+        }),
+        path: root.join("runtime"),
+        executable_override: None,
+        provider: "test",
+    };
+    let storage = Storage::new(&root, BaseRootLayout);
+    let layout = IsolatedInstanceLayout::shared(root.join("game"));
+
+    let mut scanner = DiskUsageScanner::new();
+    let usage = scanner
+        .scan(&storage, layout.clone(), std::slice::from_ref(&runtime))
+        .unwrap();
+    let a = usage.instance("a").unwrap();
+    assert_eq!(a.mods_bytes, 9);
+    assert_eq!(a.private_bytes, usage.instance("b").unwrap().private_bytes);
+    assert_eq!(a.shared_bytes, usage.shared_bytes);
+    assert_eq!(a.exclusive_shared_bytes, 0);
+    assert_eq!(usage.shared_bytes_of(SharedArtifactKind::GameDirectory), 9);
+    assert_eq!(usage.shared_bytes_of(SharedArtifactKind::Runtime), 4);
+    assert!(
+        usage
+            .shared
+            .iter()
+            .all(|artifact| artifact.share_count() == 2)
+    );
+
+    // Non-object sizes are read again on every scan.
+    write("libraries/org/a/a/1/a-1.jar", b"lib");
+    let usage = scanner.scan(&storage, layout, &[]).unwrap();
+    assert_eq!(usage.shared_bytes_of(SharedArtifactKind::Library), 3);
+    assert_eq!(usage.shared_bytes_of(SharedArtifactKind::Runtime), 0);
+
+    std::fs::remove_dir_all(&root).unwrap();
+}
@@ -0,0 +1,160 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+
+/// Magic bytes that open every pack200 archive.
+pub const PACK200_MAGIC: [u8; 4] = [0xCA, 0xFE, 0xD0, 0x0D];
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    /// The legacy `.lzma` container, as served for Mojang's Java runtime files.
+    Lzma,
+    Xz,
+}
+
+impl Compression {
+    /// Guesses the compression from a `.lzma`, `.xz` or `.pack.xz` file name.
+    pub fn from_file_name(name: &str) -> Option<Self> {
+        let (_, extension) = name.rsplit_once('.')?;
+        match extension.to_ascii_lowercase().as_str() {
+            "lzma" => Some(Self::Lzma),
+            "xz" => Some(Self::Xz),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Compression::Lzma => "lzma",
+            Compression::Xz => "xz",
+        }
+    }
+
+    /// Streams `input` through the decoder into `output`.
+    pub fn decompress<R: BufRead, W: Write>(self, input: &mut R, output: &mut W) -> Result<()> {
+        match self {
+            Compression::Lzma => lzma_rs::lzma_decompress(input, output),
+            Compression::Xz => lzma_rs::xz_decompress(input, output),
+        }
+        .with_context(|| format!("decode {} stream failed", self.name()))
+    }
+
+    /// Decodes `source` into `target`, refusing pack200 payloads. Returns the decoded size.
+    pub fn decompress_file_blocking(self, source: &Path, target: &Path) -> Result<u64> {
+        let mut input = BufReader::new(
+            File::open(source)
+                .with_context(|| format!("open compressed file failed: {}", source.display()))?,
+        );
+        let output = File::create(target)
+            .with_context(|| format!("create decompressed file failed: {}", target.display()))?;
+        let mut output = CountingWriter::new(BufWriter::new(output));
+        self.decompress(&mut input, &mut output)
+            .with_context(|| format!("decompress file failed: {}", source.display()))?;
+        output
+            .inner
+            .flush()
+            .with_context(|| format!("write decompressed file failed: {}", target.display()))?;
+        if output.header == PACK200_MAGIC {
+            bail!(
+                "'{}' decompressed to a pack200 archive, which can't be unpacked; use the plain jar artifact instead",
+                source.display()
+            );
+        }
+
+        Ok(output.written)
+    }
+}
+
+/// Whether `path` starts with the pack200 magic, as legacy `.pack` and decoded `.pack.xz` files do.
+pub fn is_pack200_file(path: &Path) -> Result<bool> {
+    let mut header = [0u8; 4];
+    let mut file =
+        File::open(path).with_context(|| format!("open file failed: {}", path.display()))?;
+    match file.read_exact(&mut header) {
+        Ok(()) => Ok(header == PACK200_MAGIC),
+        Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(error) => Err(error).with_context(|| format!("read file failed: {}", path.display())),
+    }
+}
+
+struct CountingWriter<W> {
+    inner: W,
+    written: u64,
+    header: [u8; 4],
+}
+
+impl<W> CountingWriter<W> {
+    fn new(inner: W) -> Self {
+        Self {
+            inner,
+            written: 0,
+            header: [0; 4],
+        }
+    }
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        let offset = self.written as usize;
+        if offset < self.header.len() {
+            let len = (self.header.len() - offset).min(written);
+            self.header[offset..offset + len].copy_from_slice(&buf[..len]);
+        }
+        self.written += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[test]
+fn test_decompress_reads_lzma_and_xz_and_refuses_pack200() {
+    // `printf 'hello' | xz --format=lzma`
+    const HELLO_LZMA: &[u8] = &[
+        0x5d, 0x00, 0x00, 0x80, 0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00, 0x34,
+        0x19, 0x49, 0xee, 0x8e, 0x68, 0x21, 0xff, 0xff, 0xff, 0xb9, 0xe0, 0x00, 0x00,
+    ];
+    let mut output = Vec::new();
+    Compression::Lzma
+        .decompress(&mut &HELLO_LZMA[..], &mut output)
+        .unwrap();
+    assert_eq!(output, b"hello");
+    assert!(
+        Compression::Xz
+            .decompress(&mut &HELLO_LZMA[..], &mut Vec::new())
+            .is_err()
+    );
+    assert_eq!(
+        Compression::from_file_name("forge-1.7.10.jar.pack.xz"),
+        Some(Compression::Xz)
+    );
+    assert_eq!(Compression::from_file_name("java.exe"), None);
+
+    let root = std::env::temp_dir().join(format!("elemental-compression-{}", std::process::id()));
+    std::fs::create_dir_all(&root).unwrap();
+    let mut packed = Vec::new();
+    lzma_rs::xz_compress(
+        &mut &[PACK200_MAGIC.as_slice(), b"payload"].concat()[..],
+        &mut packed,
+    )
+    .unwrap();
+    std::fs::write(root.join("library.jar.pack.xz"), packed).unwrap();
+    let error = Compression::Xz
+        .decompress_file_blocking(
+            &root.join("library.jar.pack.xz"),
+            &root.join("library.jar.pack"),
+        )
+        .unwrap_err();
+    assert!(error.to_string().contains("pack200"));
+    assert!(is_pack200_file(&root.join("library.jar.pack")).unwrap());
+    let _ = std::fs::remove_dir_all(&root);
+}
@@ -88,6 +88,10 @@ impl ConcurrencyController {
             .await
             .context("failed to acquire downloader concurrency permit")
     }
+
+    pub(crate) fn try_acquire_owned(&self) -> Option<OwnedSemaphorePermit> {
+        self.semaphore.clone().try_acquire_owned().ok()
+    }
 }
 
 #[derive(Debug)]
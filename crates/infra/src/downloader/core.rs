@@ -1,32 +1,51 @@
 use anyhow::{Context, Result, bail};
 use futures::StreamExt;
-use reqwest::{ClientBuilder, header::HeaderMap, retry};
+use reqwest::{
+    ClientBuilder, StatusCode,
+    header::{CONTENT_RANGE, ETAG, HeaderMap, IF_RANGE, LAST_MODIFIED, RANGE},
+    retry,
+};
 use scc::HashMap;
 use std::{
     fmt,
+    io::SeekFrom,
+    path::{Path, PathBuf},
     sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
     sync::{Arc, Weak},
     time::{Duration, Instant},
 };
 use tokio::{
-    io::{AsyncWriteExt, BufWriter},
-    sync::{Mutex as AsyncMutex, Notify, mpsc},
+    io::{AsyncSeekExt, AsyncWriteExt, BufWriter},
+    sync::{Mutex as AsyncMutex, Notify, OwnedSemaphorePermit, broadcast, watch},
 };
 use tokio_util::{sync::CancellationToken, task::TaskTracker};
 
 use super::anyhost::ANY_HOST;
 use super::control::{BandwidthLimiter, ConcurrencyController};
-use super::materializer::StagedDownload;
-pub use super::materializer::{HardlinkCachedMaterializer, Materializer, NoCachedMaterializer};
+use super::event::DOWNLOAD_EVENT_CAPACITY;
+pub use super::event::{DownloadEvent, DownloadEventKind, DownloadProgress};
+use super::health::HostHealth;
+pub use super::journal::DownloadJournal;
+use super::journal::JournalSession;
+pub use super::materializer::{
+    CacheEvictionReport, CacheLimits, CacheLinkStrategy, HardlinkCachedMaterializer, Materializer,
+    MaterializerCacheStats, NoCachedMaterializer,
+};
+use super::materializer::{StagedDownload, cleanup_file, range_validator_path};
 use super::plan::DownloadPlanner;
-pub use super::session::{DownloadSessionSnapshot, TaskExecutionFailure};
+use super::queue::TaskQueue;
+use super::scope::SessionScope;
+pub use super::session::{DownloadSessionSnapshot, HostHealthSnapshot, TaskExecutionFailure};
 pub use super::task::{
-    ByteRate, DownloadExecutionPolicy, DownloadPlan, DownloadRateLimit, DownloadSessionRequest,
-    DownloadTask, SessionId,
+    ByteRate, DigestAlgorithm, DownloadExecutionPolicy, DownloadPlan, DownloadPriority,
+    DownloadRateLimit, DownloadSegmentation, DownloadSessionRequest, DownloadTask, FileDigest,
+    HostLimit, LocalFileMode, SessionId,
 };
+use super::throttle::{DEFAULT_RETRY_AFTER, HostControl, HostThrottle, retry_after};
 use super::tracking::build_task_id;
 pub use super::tracking::{TaskId, TrackedInfo, TrackedTaskStatus};
-use super::validation::StreamingValidator;
+use super::validation::{DownloadValidation, StreamingValidator, validate_file};
+use crate::compression::Compression;
 
 #[derive(Debug)]
 pub struct ElementalDownloader {
@@ -38,6 +57,13 @@ pub struct ElementalDownloader {
     materializer: Arc<dyn Materializer>,
     session_parallelism: usize,
     session_queue_capacity: usize,
+    resume_attempts: u32,
+    segmentation: Option<DownloadSegmentation>,
+    local_file_mode: LocalFileMode,
+    hosts: HostThrottle,
+    max_retry_after: Duration,
+    journal: Option<DownloadJournal>,
+    events: broadcast::Sender<DownloadEvent>,
     next_session_id: AtomicU64,
     me: Weak<Self>,
 }
@@ -47,6 +73,8 @@ pub struct ElementalDownloaderBuilder {
     config: ElementalDownloaderConfig,
     materializer: Arc<dyn Materializer>,
     client: Option<reqwest::Client>,
+    client_builder: Option<ClientBuilder>,
+    journal: Option<DownloadJournal>,
 }
 
 #[derive(Debug, Clone)]
@@ -57,15 +85,23 @@ pub struct DownloadSession {
 }
 
 pub struct SessionHandler {
+    id: SessionId,
     request: DownloadSessionRequest,
     workers: TaskTracker,
-    sender: std::sync::Mutex<Option<mpsc::Sender<QueuedDownloadTask>>>,
+    queue: TaskQueue<QueuedDownloadTask>,
+    task_tokens: std::sync::Mutex<std::collections::HashMap<TaskId, (CancellationToken, usize)>>,
     state: std::sync::Mutex<SessionProgressState>,
     submission: AsyncMutex<()>,
     bandwidth_limiter: Arc<BandwidthLimiter>,
+    host_health: HostHealth,
+    journal: Option<JournalSession>,
+    events: broadcast::Sender<DownloadEvent>,
+    downloader_events: broadcast::Sender<DownloadEvent>,
+    progress: watch::Sender<DownloadProgress>,
     pending: AtomicUsize,
     idle: Notify,
     closed: AtomicBool,
+    finished: AtomicBool,
 }
 
 impl fmt::Debug for SessionHandler {
@@ -83,6 +119,7 @@ struct QueuedDownloadTask {
     task_id: TaskId,
     task: DownloadTask,
     headers: HeaderMap,
+    token: CancellationToken,
 }
 
 #[derive(Debug, Default)]
@@ -91,6 +128,7 @@ struct SessionProgressState {
     enqueued: usize,
     downloaded: usize,
     skipped: usize,
+    completed_task_ids: Vec<TaskId>,
     failures: Vec<TaskExecutionFailure>,
     cancelled_task_ids: Vec<TaskId>,
 }
@@ -106,6 +144,7 @@ struct SessionSnapshotData {
     cancelled: usize,
     pending: usize,
     is_closed: bool,
+    completed_task_ids: Vec<TaskId>,
     failures: Vec<TaskExecutionFailure>,
     cancelled_task_ids: Vec<TaskId>,
 }
@@ -130,6 +169,13 @@ pub struct ElementalDownloaderConfig {
     pub connect_timeout: Duration,
     pub retry_times: u32,
     pub rate_limit: DownloadRateLimit,
+    pub segmentation: Option<DownloadSegmentation>,
+    pub local_file_mode: LocalFileMode,
+    /// Per-host overrides of `default_host_limit`, keyed by `host[:port]`.
+    pub host_limits: std::collections::HashMap<String, HostLimit>,
+    pub default_host_limit: HostLimit,
+    /// Longer `Retry-After` delays fail the candidate instead of waiting.
+    pub max_retry_after: Duration,
 }
 
 impl Default for ElementalDownloaderConfig {
@@ -141,6 +187,11 @@ impl Default for ElementalDownloaderConfig {
             connect_timeout: Duration::from_secs(10),
             retry_times: 3,
             rate_limit: DownloadRateLimit::Unlimited,
+            segmentation: None,
+            local_file_mode: LocalFileMode::default(),
+            host_limits: std::collections::HashMap::new(),
+            default_host_limit: HostLimit::default(),
+            max_retry_after: Duration::from_secs(60),
         }
     }
 }
@@ -151,11 +202,13 @@ impl Default for ElementalDownloaderBuilder {
             config: ElementalDownloaderConfig::default(),
             materializer: Arc::new(NoCachedMaterializer),
             client: None,
+            client_builder: None,
+            journal: None,
         }
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 struct ValidatedDownloaderConfig {
     max_connections: usize,
     session_parallelism: usize,
@@ -163,6 +216,11 @@ struct ValidatedDownloaderConfig {
     connect_timeout: Duration,
     retry_times: u32,
     rate_limit: DownloadRateLimit,
+    segmentation: Option<DownloadSegmentation>,
+    local_file_mode: LocalFileMode,
+    host_limits: std::collections::HashMap<String, HostLimit>,
+    default_host_limit: HostLimit,
+    max_retry_after: Duration,
 }
 
 impl DownloadSession {
@@ -196,10 +254,36 @@ impl DownloadSession {
         self.downloader()?.add_plan(self.id, plan).await
     }
 
+    pub fn task_id(&self, task: &DownloadTask) -> TaskId {
+        build_task_id(self.id, task)
+    }
+
+    pub async fn cancel_task(&self, task_id: &TaskId) -> Result<bool> {
+        self.downloader()?.cancel_task(self.id, task_id).await
+    }
+
+    pub async fn set_task_priority(
+        &self,
+        task_id: &TaskId,
+        priority: DownloadPriority,
+    ) -> Result<bool> {
+        self.downloader()?
+            .set_task_priority(self.id, task_id, priority)
+            .await
+    }
+
     pub async fn snapshot(&self) -> Result<DownloadSessionSnapshot> {
         self.downloader()?.session_snapshot(self.id).await
     }
 
+    pub async fn subscribe(&self) -> Result<broadcast::Receiver<DownloadEvent>> {
+        self.downloader()?.subscribe_session(self.id).await
+    }
+
+    pub async fn watch_progress(&self) -> Result<watch::Receiver<DownloadProgress>> {
+        self.downloader()?.watch_session_progress(self.id).await
+    }
+
     pub async fn close(&self) -> Result<bool> {
         self.downloader()?.close_session(self.id).await
     }
@@ -249,45 +333,140 @@ impl ElementalDownloaderBuilder {
         self
     }
 
+    /// Base for the default client, the downloader still adds its retry policy and connect timeout.
+    pub fn client_builder(mut self, client_builder: ClientBuilder) -> Self {
+        self.client_builder = Some(client_builder);
+        self
+    }
+
+    pub fn journal(mut self, root: impl Into<std::path::PathBuf>) -> Self {
+        self.journal = Some(DownloadJournal::new(root));
+        self
+    }
+
     pub fn build(self) -> Result<Arc<ElementalDownloader>> {
         let config = validate_downloader_config(self.config)?;
         let client = match self.client {
             Some(client) => client,
-            None => build_downloader_client(&config)?,
+            None => build_downloader_client(&config, self.client_builder.unwrap_or_default())?,
         };
 
         Ok(ElementalDownloader::from_parts(
             client,
             self.materializer,
             config,
+            self.journal,
         ))
     }
 }
 
 impl SessionHandler {
     fn new(
+        id: SessionId,
         request: DownloadSessionRequest,
-        sender: mpsc::Sender<QueuedDownloadTask>,
+        queue_capacity: usize,
         bandwidth_limiter: Arc<BandwidthLimiter>,
+        journal: Option<JournalSession>,
+        downloader_events: broadcast::Sender<DownloadEvent>,
     ) -> Self {
         Self {
+            id,
             request,
             workers: TaskTracker::new(),
-            sender: std::sync::Mutex::new(Some(sender)),
+            queue: TaskQueue::new(queue_capacity),
+            task_tokens: std::sync::Mutex::new(std::collections::HashMap::new()),
             state: std::sync::Mutex::new(SessionProgressState::default()),
             submission: AsyncMutex::new(()),
             bandwidth_limiter,
+            host_health: HostHealth::default(),
+            journal,
+            events: broadcast::channel(DOWNLOAD_EVENT_CAPACITY).0,
+            downloader_events,
+            progress: watch::Sender::new(DownloadProgress::default()),
             pending: AtomicUsize::new(0),
             idle: Notify::new(),
             closed: AtomicBool::new(false),
+            finished: AtomicBool::new(false),
         }
     }
 
-    fn clone_sender(&self) -> Option<mpsc::Sender<QueuedDownloadTask>> {
-        self.sender
-            .lock()
-            .expect("session sender mutex poisoned")
-            .clone()
+    fn emit(&self, kind: DownloadEventKind) {
+        let event = DownloadEvent {
+            session_id: self.id,
+            session_name: self.request.name.clone(),
+            kind,
+        };
+        let _ = self.events.send(event.clone());
+        let _ = self.downloader_events.send(event);
+    }
+
+    fn emit_queued(&self, task_id: TaskId, expected_size: Option<u64>) {
+        self.progress.send_modify(|progress| {
+            progress.total_tasks += 1;
+            progress.expected_bytes += expected_size.unwrap_or_default();
+        });
+        self.emit(DownloadEventKind::Queued {
+            task_id,
+            expected_size,
+        });
+    }
+
+    fn emit_received(&self, task_id: &TaskId, received: u64, bytes: usize, bps: usize) {
+        self.progress.send_modify(|progress| {
+            progress.received_bytes += bytes as u64;
+            progress.bytes_per_second = bps;
+            progress.refresh_eta();
+        });
+        self.emit(DownloadEventKind::Progress {
+            task_id: task_id.clone(),
+            received,
+        });
+    }
+
+    fn finish_progress(&self, skipped_bytes: u64) {
+        self.progress.send_modify(|progress| {
+            progress.finished_tasks += 1;
+            progress.received_bytes += skipped_bytes;
+            progress.refresh_eta();
+        });
+    }
+
+    // Queued copies of one task share a token, so cancelling the id reaches all of them.
+    fn acquire_task_token(
+        &self,
+        task_id: &TaskId,
+        parent: &CancellationToken,
+    ) -> CancellationToken {
+        let mut tokens = self.task_tokens.lock().expect("task token mutex poisoned");
+        let (token, holders) = tokens
+            .entry(task_id.clone())
+            .or_insert_with(|| (parent.child_token(), 0));
+        if token.is_cancelled() && !parent.is_cancelled() {
+            *token = parent.child_token();
+        }
+        *holders += 1;
+        token.clone()
+    }
+
+    fn release_task_token(&self, task_id: &TaskId) {
+        let mut tokens = self.task_tokens.lock().expect("task token mutex poisoned");
+        if let Some((_, holders)) = tokens.get_mut(task_id) {
+            *holders -= 1;
+            if *holders == 0 {
+                tokens.remove(task_id);
+            }
+        }
+    }
+
+    fn cancel_task_token(&self, task_id: &TaskId) -> bool {
+        let tokens = self.task_tokens.lock().expect("task token mutex poisoned");
+        match tokens.get(task_id) {
+            Some((token, _)) if !token.is_cancelled() => {
+                token.cancel();
+                true
+            }
+            _ => false,
+        }
     }
 
     fn start_task(&self) {
@@ -300,24 +479,38 @@ impl SessionHandler {
         state.enqueued += 1;
     }
 
-    fn mark_downloaded(&self) {
-        self.state
-            .lock()
-            .expect("session state mutex poisoned")
-            .downloaded += 1;
+    fn mark_downloaded(&self, task_id: TaskId) {
+        {
+            let mut state = self.state.lock().expect("session state mutex poisoned");
+            state.downloaded += 1;
+            state.completed_task_ids.push(task_id.clone());
+        }
+        self.finish_progress(0);
+        self.emit(DownloadEventKind::Completed { task_id });
     }
 
-    fn mark_skipped(&self) {
+    fn mark_skipped(&self, task_id: TaskId, task: &DownloadTask) {
         self.state
             .lock()
             .expect("session state mutex poisoned")
             .skipped += 1;
+        self.finish_progress(task.expected_size.unwrap_or_default());
+        self.emit(DownloadEventKind::Skipped {
+            task_id,
+            path: task.path.clone(),
+        });
     }
 
-    fn mark_accepted_skip(&self) {
-        let mut state = self.state.lock().expect("session state mutex poisoned");
-        state.accepted += 1;
-        state.skipped += 1;
+    fn mark_accepted_skip(&self, task_id: TaskId, task: &DownloadTask) {
+        {
+            let mut state = self.state.lock().expect("session state mutex poisoned");
+            state.accepted += 1;
+            state.skipped += 1;
+        }
+        self.emit(DownloadEventKind::Skipped {
+            task_id,
+            path: task.path.clone(),
+        });
     }
 
     fn mark_failed(&self, task_id: TaskId, error: String) {
@@ -325,7 +518,12 @@ impl SessionHandler {
             .lock()
             .expect("session state mutex poisoned")
             .failures
-            .push(TaskExecutionFailure { task_id, error });
+            .push(TaskExecutionFailure {
+                task_id: task_id.clone(),
+                error: error.clone(),
+            });
+        self.finish_progress(0);
+        self.emit(DownloadEventKind::Failed { task_id, error });
     }
 
     fn mark_cancelled(&self, task_id: TaskId) {
@@ -333,7 +531,9 @@ impl SessionHandler {
             .lock()
             .expect("session state mutex poisoned")
             .cancelled_task_ids
-            .push(task_id);
+            .push(task_id.clone());
+        self.finish_progress(0);
+        self.emit(DownloadEventKind::Cancelled { task_id });
     }
 
     fn finish_task(&self) {
@@ -360,16 +560,37 @@ impl SessionHandler {
         self.close().await;
         self.wait_idle().await;
         self.wait_workers().await;
+
+        if !self.finished.swap(true, Ordering::AcqRel) {
+            let (downloaded, skipped, failed, cancelled) = {
+                let state = self.state.lock().expect("session state mutex poisoned");
+                (
+                    state.downloaded,
+                    state.skipped,
+                    state.failures.len(),
+                    state.cancelled_task_ids.len(),
+                )
+            };
+            if failed == 0
+                && cancelled == 0
+                && let Some(journal) = &self.journal
+            {
+                let _ = journal.finish();
+            }
+            self.emit(DownloadEventKind::SessionFinished {
+                downloaded,
+                skipped,
+                failed,
+                cancelled,
+            });
+        }
     }
 
     async fn close(&self) -> bool {
         let _submission_guard = self.submission.lock().await;
         let was_closed = self.closed.swap(true, Ordering::AcqRel);
         self.workers.close();
-        self.sender
-            .lock()
-            .expect("session sender mutex poisoned")
-            .take();
+        self.queue.close();
         !was_closed
     }
 
@@ -389,6 +610,7 @@ impl SessionHandler {
             cancelled: state.cancelled_task_ids.len(),
             pending: self.pending.load(Ordering::Acquire),
             is_closed: self.is_closed(),
+            completed_task_ids: state.completed_task_ids.clone(),
             failures: state.failures.clone(),
             cancelled_task_ids: state.cancelled_task_ids.clone(),
         }
@@ -523,10 +745,24 @@ impl ElementalDownloader {
             .expect("default downloader builder must be valid")
     }
 
+    pub fn journal(&self) -> Option<&DownloadJournal> {
+        self.journal.as_ref()
+    }
+
+    /// Runs the plans of journaled sessions that were interrupted before they finished.
+    pub async fn resume_journaled_sessions(&self) -> Result<()> {
+        let Some(journal) = &self.journal else {
+            return Ok(());
+        };
+
+        self.execute_plans(journal.unfinished_plans()?).await
+    }
+
     fn from_parts(
         client: reqwest::Client,
         materializer: Arc<dyn Materializer>,
         config: ValidatedDownloaderConfig,
+        journal: Option<DownloadJournal>,
     ) -> Arc<Self> {
         let concurrency = Arc::new(
             ConcurrencyController::new(config.max_connections)
@@ -545,6 +781,13 @@ impl ElementalDownloader {
             materializer,
             session_parallelism: config.session_parallelism,
             session_queue_capacity: config.session_queue_capacity,
+            resume_attempts: config.retry_times,
+            segmentation: config.segmentation,
+            local_file_mode: config.local_file_mode,
+            hosts: HostThrottle::new(config.host_limits, config.default_host_limit),
+            max_retry_after: config.max_retry_after,
+            journal,
+            events: broadcast::channel(DOWNLOAD_EVENT_CAPACITY).0,
             next_session_id: AtomicU64::new(1),
             me: me.clone(),
         })
@@ -570,31 +813,32 @@ impl ElementalDownloader {
     pub async fn create_session(&self, request: DownloadSessionRequest) -> Result<DownloadSession> {
         let session_id = self.next_session_id.fetch_add(1, Ordering::Relaxed);
         self.tracker.create_session(session_id).await?;
+        if let Some(scope) = SessionScope::current() {
+            scope.record(session_id);
+        }
 
         let downloader = self.me.upgrade().context("unexpected downloader drop")?;
-        let (sender, receiver) = mpsc::channel(self.session_queue_capacity);
-        let receiver = Arc::new(AsyncMutex::new(receiver));
         let parallelism = request.effective_parallelism(self.session_parallelism);
         let session_rate_limit = request.effective_rate_limit();
+        let journal = match (&self.journal, &request.name) {
+            (Some(journal), Some(name)) => Some(journal.open_session(name)?),
+            _ => None,
+        };
         let handler = Arc::new(SessionHandler::new(
+            session_id,
             request.clone(),
-            sender,
+            self.session_queue_capacity,
             Arc::new(BandwidthLimiter::new(session_rate_limit)?),
+            journal,
+            self.events.clone(),
         ));
 
         for _ in 0..parallelism {
             let session_id_cloned = session_id;
             let handler_cloned = handler.clone();
-            let receiver_cloned = receiver.clone();
             let downloader_cloned = downloader.clone();
             handler.workers.spawn(async move {
-                run_session_worker(
-                    downloader_cloned,
-                    session_id_cloned,
-                    handler_cloned,
-                    receiver_cloned,
-                )
-                .await;
+                run_session_worker(downloader_cloned, session_id_cloned, handler_cloned).await;
             });
         }
 
@@ -617,6 +861,11 @@ impl ElementalDownloader {
     pub async fn remove_session(&self, session_id: SessionId) -> Result<()> {
         let handler = self.session_handler(session_id).await?;
         handler.close_and_wait().await;
+        if let Some(scope) = SessionScope::current()
+            && scope.contains(session_id)
+        {
+            scope.record_finished(self.session_snapshot(session_id).await?);
+        }
         self.sessions.remove_async(&session_id).await;
         self.tracker.remove_session(session_id).await;
         Ok(())
@@ -649,11 +898,32 @@ impl ElementalDownloader {
             pending: snapshot.pending,
             bytes_per_second,
             is_closed: snapshot.is_closed,
+            completed_task_ids: snapshot.completed_task_ids,
             failures: snapshot.failures,
             cancelled_task_ids: snapshot.cancelled_task_ids,
+            host_health: handler.host_health.snapshot(&self.hosts),
         })
     }
 
+    /// Events of every session created by this downloader.
+    pub fn subscribe(&self) -> broadcast::Receiver<DownloadEvent> {
+        self.events.subscribe()
+    }
+
+    pub async fn subscribe_session(
+        &self,
+        session_id: SessionId,
+    ) -> Result<broadcast::Receiver<DownloadEvent>> {
+        Ok(self.session_handler(session_id).await?.events.subscribe())
+    }
+
+    pub async fn watch_session_progress(
+        &self,
+        session_id: SessionId,
+    ) -> Result<watch::Receiver<DownloadProgress>> {
+        Ok(self.session_handler(session_id).await?.progress.subscribe())
+    }
+
     pub async fn add_task(&self, session_id: SessionId, task: DownloadTask) -> Result<()> {
         self.add_task_with_headers(session_id, task, None).await
     }
@@ -672,25 +942,46 @@ impl ElementalDownloader {
             bail!("download session '{}' is closed", session_id);
         }
 
+        let task_id = build_task_id(session_id, &task);
+        if let Some(journal) = &handler.journal
+            && journal.is_verified(&task)
+        {
+            handler.mark_accepted_skip(task_id, &task);
+            return Ok(());
+        }
+
+        if let Some(journal) = &handler.journal {
+            journal.record_task(&task)?;
+        }
+
         if self.materializer.resolve(&task).await? {
-            handler.mark_accepted_skip();
+            if let Some(journal) = &handler.journal {
+                journal.record_verified(&task)?;
+            }
+            handler.mark_accepted_skip(task_id, &task);
             return Ok(());
         }
 
-        let sender = handler
-            .clone_sender()
-            .context("download session is closed")?;
-        let task_id = build_task_id(session_id, &task);
+        let session_token = self
+            .tracker
+            .session_token(session_id)
+            .await
+            .context("download session not found")?;
         self.tracker.create_task(session_id, task_id.clone()).await;
         handler.start_task();
 
+        let expected_size = task.expected_size;
+        let priority = task.priority;
         let queued = QueuedDownloadTask {
             task_id: task_id.clone(),
             task,
             headers,
+            token: handler.acquire_task_token(&task_id, &session_token),
         };
 
-        if let Err(error) = sender.send(queued).await {
+        handler.emit_queued(task_id.clone(), expected_size);
+        if let Err(error) = handler.queue.push(task_id.clone(), priority, queued).await {
+            handler.release_task_token(&task_id);
             self.tracker.remove_task(session_id, &task_id).await;
             handler.finish_task();
             bail!("failed to enqueue task '{}': {}", task_id, error);
@@ -700,6 +991,34 @@ impl ElementalDownloader {
         Ok(())
     }
 
+    /// Cancels one queued or running task, the rest of the session keeps going.
+    pub async fn cancel_task(&self, session_id: SessionId, task_id: &TaskId) -> Result<bool> {
+        let handler = self.session_handler(session_id).await?;
+        let queued = handler.queue.remove(task_id);
+        for _ in &queued {
+            handler.release_task_token(task_id);
+            finish_task_cancelled(self.tracker.as_ref(), handler.as_ref(), session_id, task_id)
+                .await;
+        }
+
+        let running = handler.cancel_task_token(task_id);
+        Ok(!queued.is_empty() || running)
+    }
+
+    /// Moves a task that is still queued, returns `false` once it has started.
+    pub async fn set_task_priority(
+        &self,
+        session_id: SessionId,
+        task_id: &TaskId,
+        priority: DownloadPriority,
+    ) -> Result<bool> {
+        Ok(self
+            .session_handler(session_id)
+            .await?
+            .queue
+            .set_priority(task_id, priority))
+    }
+
     pub async fn add_tasks(&self, session_id: SessionId, tasks: Vec<DownloadTask>) -> Result<()> {
         for task in tasks {
             self.add_task(session_id, task).await?;
@@ -766,6 +1085,10 @@ impl ElementalDownloader {
         self.execute_plans(plans).await
     }
 
+    pub fn cache_stats(&self) -> Option<MaterializerCacheStats> {
+        self.materializer.cache_stats()
+    }
+
     pub fn max_connections(&self) -> usize {
         self.concurrency.max_connections()
     }
@@ -794,19 +1117,11 @@ async fn run_session_worker(
     downloader: Arc<ElementalDownloader>,
     session_id: SessionId,
     handler: Arc<SessionHandler>,
-    receiver: Arc<AsyncMutex<mpsc::Receiver<QueuedDownloadTask>>>,
 ) {
-    loop {
-        let next = {
-            let mut receiver = receiver.lock().await;
-            receiver.recv().await
-        };
-
-        let Some(queued_task) = next else {
-            break;
-        };
-
+    while let Some(queued_task) = handler.queue.pop().await {
+        let task_id = queued_task.task_id.clone();
         execute_queued_task(downloader.clone(), session_id, handler.clone(), queued_task).await;
+        handler.release_task_token(&task_id);
     }
 }
 
@@ -820,12 +1135,16 @@ async fn execute_queued_task(
         task_id,
         task,
         headers,
+        token,
     } = queued_task;
 
     match downloader.materializer.resolve(&task).await {
         Ok(true) => {
             downloader.tracker.remove_task(session_id, &task_id).await;
-            handler.mark_skipped();
+            if let Some(journal) = &handler.journal {
+                let _ = journal.record_verified(&task);
+            }
+            handler.mark_skipped(task_id, &task);
             handler.finish_task();
             return;
         }
@@ -843,116 +1162,23 @@ async fn execute_queued_task(
         }
     }
 
-    let token = match downloader
-        .tracker
-        .sessions
-        .read_async(&session_id, |_, state| state.token.child_token())
-        .await
-    {
-        Some(token) => token,
-        None => {
-            handler.mark_cancelled(task_id.clone());
-            handler.finish_task();
-            return;
-        }
-    };
-
-    let permit = tokio::select! {
-        biased;
-        () = token.cancelled() => {
-            finish_task_cancelled(
-                downloader.tracker.as_ref(),
-                handler.as_ref(),
-                session_id,
-                &task_id,
-            )
-            .await;
-            return;
-        }
-        permit = downloader.concurrency.acquire_owned() => permit,
-    };
-
-    let permit = match permit {
-        Ok(permit) => permit,
-        Err(error) => {
-            finish_task_error(
-                downloader.tracker.as_ref(),
-                handler.as_ref(),
-                session_id,
-                &task_id,
-                error.to_string(),
-            )
-            .await;
-            return;
-        }
-    };
-
     downloader
         .tracker
         .update_task(session_id, &task_id, TrackedTaskStatus::ACTIVE)
         .await;
 
-    let staged_output = downloader.materializer.create_staging(&task).await;
-    let staged = match staged_output {
-        Ok(output) => output,
-        Err(error) => {
-            mark_task_error(
-                downloader.tracker.as_ref(),
-                handler.as_ref(),
-                session_id,
-                &task_id,
-                error.to_string(),
-            )
-            .await;
-            drop(permit);
-            handler.finish_task();
-            return;
-        }
-    };
-    let staged_path = staged.path.clone();
-    let client = downloader.client.clone();
     let materializer = downloader.materializer.clone();
-    let materializer_for_execute = materializer.clone();
-    let tracker = downloader.tracker.clone();
-    let global_bandwidth_limiter = downloader.bandwidth_limiter.clone();
-    let session_bandwidth_limiter = handler.bandwidth_limiter.clone();
-    let session_id_cloned = session_id;
-    let task_id_cloned = task_id.clone();
-    let mut validator = StreamingValidator::from_task(&task);
-    let executer = async move {
-        let StagedDownload { path, file } = staged;
-        let file = file.context("staged download missing file handle")?;
-        let mut stream = client
-            .get(task.url.clone())
-            .headers(headers)
-            .send()
-            .await?
-            .error_for_status()?
-            .bytes_stream();
-
-        let mut output = BufWriter::with_capacity(128 * 1024, file);
-        while let Some(item) = stream.next().await {
-            let data = item?;
-            session_bandwidth_limiter.throttle(data.len()).await;
-            global_bandwidth_limiter.throttle(data.len()).await;
-            validator.update(&data);
-            if let Some(mut state) = tracker.sessions.get_async(&session_id_cloned).await {
-                if let Some(mut tracked) = state.tasks.get_async(&task_id_cloned).await {
-                    tracked.recv += data.len();
-                }
-                state.bps.record(data.len(), Instant::now());
-            }
-
-            output.write_all(&data).await?;
-        }
-        output.flush().await?;
-        drop(output);
-        validator.finish(&path)?;
-        materializer_for_execute
-            .commit(StagedDownload { path, file: None }, &task)
-            .await?;
-        anyhow::Ok(())
-    };
+    let staging_path = std::sync::Mutex::new(None);
+    let executer = TaskTransfer {
+        downloader: downloader.as_ref(),
+        handler: handler.as_ref(),
+        session_id,
+        task_id: &task_id,
+        task: &task,
+        headers: &headers,
+        host: None,
+    }
+    .download_candidates(&staging_path);
 
     tokio::select! {
         biased;
@@ -960,15 +1186,12 @@ async fn execute_queued_task(
             match result {
                 Ok(_) => {
                     downloader.tracker.remove_task(session_id, &task_id).await;
-                    handler.mark_downloaded();
+                    if let Some(journal) = &handler.journal {
+                        let _ = journal.record_verified(&task);
+                    }
+                    handler.mark_downloaded(task_id.clone());
                 }
                 Err(error) => {
-                    let _ = materializer
-                        .abort(StagedDownload {
-                            path: staged_path.clone(),
-                            file: None,
-                        })
-                        .await;
                     mark_task_error(
                         downloader.tracker.as_ref(),
                         handler.as_ref(),
@@ -988,19 +1211,767 @@ async fn execute_queued_task(
                 &task_id,
             )
             .await;
-            let _ = materializer
-                .abort(StagedDownload {
-                    path: staged_path,
-                    file: None,
-                })
-                .await;
+            let staged_path = staging_path
+                .lock()
+                .expect("staging path mutex poisoned")
+                .take();
+            if let Some(path) = staged_path {
+                let _ = materializer
+                    .abort(StagedDownload {
+                        path,
+                        file: None,
+                        resume_from: 0,
+                    })
+                    .await;
+            }
         }
     }
 
-    drop(permit);
     handler.finish_task();
 }
 
+enum StagedTransferError {
+    Interrupted(anyhow::Error),
+    Rejected(anyhow::Error),
+    /// The host answered 429 or 503 and asked to be retried later.
+    Throttled {
+        error: anyhow::Error,
+        retry_after: Duration,
+    },
+}
+
+impl StagedTransferError {
+    fn from_status(response: &reqwest::Response, error: reqwest::Error) -> Self {
+        let status = response.status();
+        if matches!(
+            status,
+            StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE
+        ) {
+            return Self::Throttled {
+                error: error.into(),
+                retry_after: retry_after(response.headers()).unwrap_or(DEFAULT_RETRY_AFTER),
+            };
+        }
+
+        if status.is_server_error() {
+            Self::Interrupted(error.into())
+        } else {
+            Self::Rejected(error.into())
+        }
+    }
+}
+
+/// A global connection permit plus the host permit when the host is capped.
+type SegmentPermit = (OwnedSemaphorePermit, Option<OwnedSemaphorePermit>);
+
+#[derive(Clone, Copy)]
+struct TaskTransfer<'a> {
+    downloader: &'a ElementalDownloader,
+    handler: &'a SessionHandler,
+    session_id: SessionId,
+    task_id: &'a TaskId,
+    task: &'a DownloadTask,
+    headers: &'a HeaderMap,
+    host: Option<&'a HostControl>,
+}
+
+impl TaskTransfer<'_> {
+    async fn download_candidates(
+        self,
+        staging_path: &std::sync::Mutex<Option<PathBuf>>,
+    ) -> Result<()> {
+        let Self {
+            downloader,
+            handler,
+            task,
+            ..
+        } = self;
+        let mut errors = Vec::new();
+
+        for url in handler.host_health.order(task.candidate_urls()) {
+            let host = downloader.hosts.control(&url);
+            let transfer = TaskTransfer {
+                host: host.as_deref(),
+                ..self
+            };
+            let mut throttled = 0;
+            let mut restarted = false;
+
+            let error = loop {
+                let _host_permit = match &host {
+                    Some(host) => host.acquire().await?,
+                    None => None,
+                };
+                // Taken after the host slot and its backoff, so a capped or throttled host never
+                // holds connections that other hosts could use.
+                let permit = downloader.concurrency.acquire_owned().await?;
+                let staged = downloader.materializer.create_staging(task).await?;
+                let path = staged.path.clone();
+                let resume_from = staged.resume_from;
+                *staging_path.lock().expect("staging path mutex poisoned") = Some(path.clone());
+
+                match transfer.transfer(&url, staged).await {
+                    Ok(()) => {
+                        handler.host_health.record_success(&url);
+                        let staged = StagedDownload {
+                            path: path.clone(),
+                            file: None,
+                            resume_from: 0,
+                        };
+                        let result = downloader.materializer.commit(staged, task).await;
+                        if result.is_err() {
+                            let _ = downloader
+                                .materializer
+                                .abort(StagedDownload {
+                                    path,
+                                    file: None,
+                                    resume_from: 0,
+                                })
+                                .await;
+                        }
+                        staging_path
+                            .lock()
+                            .expect("staging path mutex poisoned")
+                            .take();
+                        return result;
+                    }
+                    // Keep the staged prefix so the next candidate or a later attempt can resume it.
+                    Err(StagedTransferError::Interrupted(error)) => break error,
+                    Err(StagedTransferError::Rejected(error)) => {
+                        let _ = downloader
+                            .materializer
+                            .abort(StagedDownload {
+                                path,
+                                file: None,
+                                resume_from: 0,
+                            })
+                            .await;
+                        // A stale staged prefix fails validation too, so start over once from zero.
+                        if resume_from > 0 && !restarted {
+                            restarted = true;
+                            continue;
+                        }
+                        break error;
+                    }
+                    Err(StagedTransferError::Throttled { error, retry_after }) => {
+                        handler.host_health.record_throttled(&url, retry_after);
+                        throttled += 1;
+                        if retry_after > downloader.max_retry_after
+                            || throttled > downloader.resume_attempts
+                        {
+                            break anyhow::anyhow!(
+                                "{error}, host asked to retry after {}s",
+                                retry_after.as_secs()
+                            );
+                        }
+                        drop(permit);
+                        // The backoff is shared, so other tasks for this host wait as well.
+                        match &host {
+                            Some(host) => host.back_off(retry_after),
+                            None => tokio::time::sleep(retry_after).await,
+                        }
+                    }
+                }
+            };
+            handler.host_health.record_failure(&url);
+            errors.push((url, error));
+        }
+
+        if errors.len() == 1 {
+            return Err(errors.remove(0).1);
+        }
+
+        bail!(
+            "all candidate urls failed:\n{}",
+            errors
+                .iter()
+                .map(|(url, error)| format!("{url}: {error}"))
+                .collect::<Vec<String>>()
+                .join("\n")
+        )
+    }
+
+    async fn transfer(
+        self,
+        url: &str,
+        staged: StagedDownload,
+    ) -> std::result::Result<(), StagedTransferError> {
+        let path = staged.path.clone();
+        self.transfer_body(url, staged).await?;
+        match self.task.decompression {
+            Some(compression) => decompress_staged(compression, &path, self.task)
+                .await
+                .map_err(StagedTransferError::Rejected),
+            None => Ok(()),
+        }
+    }
+
+    async fn transfer_body(
+        self,
+        url: &str,
+        mut staged: StagedDownload,
+    ) -> std::result::Result<(), StagedTransferError> {
+        if let Some(source) = local_file_path(url) {
+            return self.transfer_local(url, &source, staged).await;
+        }
+
+        // Resumed downloads keep a single connection so the staged prefix stays contiguous.
+        if staged.resume_from == 0
+            && let Some(permits) = self.segment_permits()
+        {
+            let file = staged
+                .file
+                .take()
+                .context("staged download missing file handle")
+                .map_err(StagedTransferError::Rejected)?;
+            match self
+                .transfer_segmented(url, &staged.path, file, permits)
+                .await
+            {
+                Ok(None) => return Ok(()),
+                Ok(Some(file)) => staged.file = Some(file),
+                Err(error) => return Err(error),
+            }
+        }
+
+        self.transfer_staged(url, staged).await
+    }
+
+    async fn transfer_local(
+        self,
+        url: &str,
+        source: &Path,
+        staged: StagedDownload,
+    ) -> std::result::Result<(), StagedTransferError> {
+        use StagedTransferError::Rejected;
+
+        let len = tokio::fs::metadata(source)
+            .await
+            .with_context(|| format!("read local source failed: {}", source.display()))
+            .map_err(Rejected)?
+            .len();
+        self.emit_started(url, Some(len));
+
+        // Local sources are never resumed, so the staging file is replaced wholesale.
+        drop(staged.file);
+        cleanup_file(&staged.path).await;
+        let linked = self.downloader.local_file_mode == LocalFileMode::HardlinkOrCopy
+            && tokio::fs::hard_link(source, &staged.path).await.is_ok();
+        if !linked {
+            tokio::fs::copy(source, &staged.path)
+                .await
+                .with_context(|| {
+                    format!(
+                        "copy local source failed: {} -> {}",
+                        source.display(),
+                        staged.path.display()
+                    )
+                })
+                .map_err(Rejected)?;
+        }
+
+        let mut validator = StreamingValidator::for_transfer(self.task);
+        validator
+            .update_from_file(&staged.path, len)
+            .await
+            .map_err(Rejected)?;
+        validator.finish(&staged.path).map_err(Rejected)?;
+        self.record_received(len as usize).await;
+        Ok(())
+    }
+
+    fn segment_permits(self) -> Option<Vec<SegmentPermit>> {
+        // The expected size of a decompressed task is not the length of the transferred body.
+        if self.task.decompression.is_some() {
+            return None;
+        }
+        let segments = self
+            .downloader
+            .segmentation?
+            .segment_count(self.task.expected_size?);
+        // Extra connections are only taken when idle so segmented tasks never starve the queue.
+        let permits = (1..segments)
+            .map_while(|_| {
+                let host = match self.host {
+                    Some(host) => host.try_acquire()?,
+                    None => None,
+                };
+                Some((self.downloader.concurrency.try_acquire_owned()?, host))
+            })
+            .collect::<Vec<SegmentPermit>>();
+        (!permits.is_empty()).then_some(permits)
+    }
+
+    /// Returns the file back when the server does not serve byte ranges for `url`.
+    async fn transfer_segmented(
+        self,
+        url: &str,
+        path: &Path,
+        file: tokio::fs::File,
+        permits: Vec<SegmentPermit>,
+    ) -> std::result::Result<Option<tokio::fs::File>, StagedTransferError> {
+        use StagedTransferError::{Interrupted, Rejected};
+
+        let size = self
+            .task
+            .expected_size
+            .context("segmented download requires an expected size")
+            .map_err(Rejected)?;
+        let segment_size = size.div_ceil(permits.len() as u64 + 1);
+        let segments = (0..size)
+            .step_by(segment_size as usize)
+            .map(|start| start..(start + segment_size).min(size))
+            .collect::<Vec<std::ops::Range<u64>>>();
+
+        let probe = self
+            .request_segment(url, &segments[0], None)
+            .await
+            .map_err(|error| Interrupted(error.into()))?;
+        let status = probe.status();
+        if let Err(error) = probe.error_for_status_ref() {
+            return Err(StagedTransferError::from_status(&probe, error));
+        }
+        if status != StatusCode::PARTIAL_CONTENT
+            || content_range_start(probe.headers()) != Some(0)
+            || content_range_total(probe.headers()) != Some(size)
+        {
+            return Ok(Some(file));
+        }
+
+        let range_validator = response_range_validator(probe.headers());
+        let validator_path = range_validator_path(path);
+        match &range_validator {
+            Some(range_validator) => tokio::fs::write(&validator_path, range_validator)
+                .await
+                .map_err(|error| Rejected(error.into()))?,
+            None => {
+                let _ = tokio::fs::remove_file(&validator_path).await;
+            }
+        }
+        file.set_len(size)
+            .await
+            .map_err(|error| Rejected(error.into()))?;
+        drop(file);
+        self.emit_started(url, Some(size));
+
+        let contiguous = AtomicU64::new(0);
+        let mut probe = Some(probe);
+        let result =
+            futures::future::try_join_all(segments.iter().enumerate().map(|(index, range)| {
+                let response = if index == 0 { probe.take() } else { None };
+                let written = (index == 0).then_some(&contiguous);
+                self.transfer_segment(
+                    url,
+                    path,
+                    range.clone(),
+                    response,
+                    range_validator.as_deref(),
+                    written,
+                )
+            }))
+            .await;
+        drop(permits);
+
+        if let Err(error) = result {
+            // Only the leading segment is a valid prefix for a later single-connection resume.
+            let file = tokio::fs::OpenOptions::new()
+                .write(true)
+                .open(path)
+                .await
+                .map_err(|error| Rejected(error.into()))?;
+            file.set_len(contiguous.load(Ordering::Acquire))
+                .await
+                .map_err(|error| Rejected(error.into()))?;
+            return Err(error);
+        }
+
+        let mut validator = StreamingValidator::for_transfer(self.task);
+        validator
+            .update_from_file(path, size)
+            .await
+            .map_err(Rejected)?;
+        validator.finish(path).map_err(Rejected)?;
+        Ok(None)
+    }
+
+    async fn request_segment(
+        self,
+        url: &str,
+        range: &std::ops::Range<u64>,
+        range_validator: Option<&str>,
+    ) -> reqwest::Result<reqwest::Response> {
+        let mut request = self
+            .downloader
+            .client
+            .get(url)
+            .headers(self.headers.clone())
+            .header(RANGE, format!("bytes={}-{}", range.start, range.end - 1));
+        if let Some(range_validator) = range_validator {
+            request = request.header(IF_RANGE, range_validator);
+        }
+        request.send().await
+    }
+
+    async fn transfer_segment(
+        self,
+        url: &str,
+        path: &Path,
+        range: std::ops::Range<u64>,
+        mut response: Option<reqwest::Response>,
+        range_validator: Option<&str>,
+        written: Option<&AtomicU64>,
+    ) -> std::result::Result<(), StagedTransferError> {
+        use StagedTransferError::{Interrupted, Rejected};
+
+        let mut file = tokio::fs::OpenOptions::new()
+            .write(true)
+            .open(path)
+            .await
+            .map_err(|error| Rejected(error.into()))?;
+        let mut position = range.start;
+        let mut interruptions = 0;
+
+        while position < range.end {
+            let response = match response.take() {
+                Some(response) => response,
+                None => {
+                    let response = self
+                        .request_segment(url, &(position..range.end), range_validator)
+                        .await
+                        .map_err(|error| Interrupted(error.into()))?;
+                    let status = response.status();
+                    if let Err(error) = response.error_for_status_ref() {
+                        return Err(StagedTransferError::from_status(&response, error));
+                    }
+                    if status != StatusCode::PARTIAL_CONTENT
+                        || content_range_start(response.headers()) != Some(position)
+                    {
+                        return Err(Interrupted(anyhow::anyhow!(
+                            "server stopped serving byte ranges for '{}'",
+                            url
+                        )));
+                    }
+                    response
+                }
+            };
+
+            file.seek(SeekFrom::Start(position))
+                .await
+                .map_err(|error| Rejected(error.into()))?;
+            let mut stream = response.bytes_stream();
+            let mut output = BufWriter::with_capacity(128 * 1024, file);
+            let mut stream_error = None;
+            while let Some(item) = stream.next().await {
+                let data = match item {
+                    Ok(data) => data,
+                    Err(error) => {
+                        stream_error = Some(error);
+                        break;
+                    }
+                };
+                if position + data.len() as u64 > range.end {
+                    return Err(Rejected(anyhow::anyhow!(
+                        "server sent more data than requested for '{}'",
+                        url
+                    )));
+                }
+                self.record_transfer(data.len()).await;
+                output
+                    .write_all(&data)
+                    .await
+                    .map_err(|error| Rejected(error.into()))?;
+                position += data.len() as u64;
+            }
+            output
+                .flush()
+                .await
+                .map_err(|error| Rejected(error.into()))?;
+            file = output.into_inner();
+            if let Some(written) = written {
+                written.store(position - range.start, Ordering::Release);
+            }
+
+            let Some(error) = stream_error else {
+                if position < range.end {
+                    return Err(Interrupted(anyhow::anyhow!(
+                        "segment {}-{} of '{}' ended early",
+                        range.start,
+                        range.end - 1,
+                        url
+                    )));
+                }
+                break;
+            };
+            interruptions += 1;
+            if interruptions > self.downloader.resume_attempts {
+                return Err(Interrupted(error.into()));
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn record_transfer(self, bytes: usize) {
+        if let Some(host) = self.host {
+            host.throttle(bytes).await;
+        }
+        self.handler.bandwidth_limiter.throttle(bytes).await;
+        self.downloader.bandwidth_limiter.throttle(bytes).await;
+        self.record_received(bytes).await;
+    }
+
+    async fn record_received(self, bytes: usize) {
+        if let Some(mut state) = self
+            .downloader
+            .tracker
+            .sessions
+            .get_async(&self.session_id)
+            .await
+        {
+            let received = match state.tasks.get_async(self.task_id).await {
+                Some(mut tracked) => {
+                    tracked.recv += bytes;
+                    tracked.recv
+                }
+                None => bytes,
+            };
+            let now = Instant::now();
+            state.bps.record(bytes, now);
+            self.handler
+                .emit_received(self.task_id, received as u64, bytes, state.bps.value(now));
+        }
+    }
+
+    fn emit_started(self, url: &str, content_length: Option<u64>) {
+        self.handler.emit(DownloadEventKind::Started {
+            task_id: self.task_id.clone(),
+            url: url.to_owned(),
+            content_length,
+        });
+    }
+
+    async fn transfer_staged(
+        self,
+        url: &str,
+        staged: StagedDownload,
+    ) -> std::result::Result<(), StagedTransferError> {
+        use StagedTransferError::{Interrupted, Rejected};
+
+        let Self {
+            downloader,
+            task,
+            headers,
+            ..
+        } = self;
+
+        let StagedDownload {
+            path,
+            file,
+            resume_from,
+        } = staged;
+        let mut file = file
+            .context("staged download missing file handle")
+            .map_err(Rejected)?;
+        let validator_path = range_validator_path(&path);
+        let mut range_validator = tokio::fs::read_to_string(&validator_path).await.ok();
+        // Without an entity validator or a hash nothing proves the staged prefix still matches.
+        let mut offset = if range_validator.is_some() || !task.digests.is_empty() {
+            resume_from
+        } else {
+            0
+        };
+        let mut interruptions = 0;
+
+        loop {
+            let mut validator = StreamingValidator::for_transfer(task);
+            if offset > 0 {
+                validator
+                    .update_from_file(&path, offset)
+                    .await
+                    .map_err(Rejected)?;
+            }
+            reset_staged_file(&mut file, offset)
+                .await
+                .map_err(Rejected)?;
+
+            let mut request = downloader.client.get(url).headers(headers.clone());
+            if offset > 0 {
+                request = request.header(RANGE, format!("bytes={offset}-"));
+                if let Some(range_validator) = &range_validator {
+                    request = request.header(IF_RANGE, range_validator.as_str());
+                }
+            }
+            let response = request
+                .send()
+                .await
+                .map_err(|error| Interrupted(error.into()))?;
+
+            let status = response.status();
+            if offset > 0 && status == StatusCode::RANGE_NOT_SATISFIABLE {
+                offset = 0;
+                continue;
+            }
+            if let Err(error) = response.error_for_status_ref() {
+                return Err(StagedTransferError::from_status(&response, error));
+            }
+
+            if status != StatusCode::PARTIAL_CONTENT {
+                if offset > 0 {
+                    // The server ignored the range or the entity changed, so this is the whole body.
+                    validator = StreamingValidator::for_transfer(task);
+                    reset_staged_file(&mut file, 0).await.map_err(Rejected)?;
+                }
+
+                range_validator = response_range_validator(response.headers());
+                match &range_validator {
+                    Some(range_validator) => tokio::fs::write(&validator_path, range_validator)
+                        .await
+                        .map_err(|error| Rejected(error.into()))?,
+                    None => {
+                        let _ = tokio::fs::remove_file(&validator_path).await;
+                    }
+                }
+            } else if content_range_start(response.headers()) != Some(offset) {
+                return Err(Rejected(anyhow::anyhow!(
+                    "server answered '{}' with an unexpected content range",
+                    url
+                )));
+            }
+
+            if interruptions == 0 {
+                let content_length = if status == StatusCode::PARTIAL_CONTENT {
+                    content_range_total(response.headers())
+                } else {
+                    response.content_length()
+                };
+                self.emit_started(url, content_length);
+            }
+
+            let mut stream = response.bytes_stream();
+            let mut output = BufWriter::with_capacity(128 * 1024, file);
+            let mut stream_error = None;
+            while let Some(item) = stream.next().await {
+                let data = match item {
+                    Ok(data) => data,
+                    Err(error) => {
+                        stream_error = Some(error);
+                        break;
+                    }
+                };
+                self.record_transfer(data.len()).await;
+                validator.update(&data);
+
+                output
+                    .write_all(&data)
+                    .await
+                    .map_err(|error| Rejected(error.into()))?;
+            }
+            output
+                .flush()
+                .await
+                .map_err(|error| Rejected(error.into()))?;
+            file = output.into_inner();
+
+            let Some(error) = stream_error else {
+                drop(file);
+                return validator.finish(&path).map_err(Rejected);
+            };
+            interruptions += 1;
+            if interruptions > downloader.resume_attempts {
+                return Err(Interrupted(error.into()));
+            }
+            offset = file
+                .metadata()
+                .await
+                .map_err(|error| Interrupted(error.into()))?
+                .len();
+        }
+    }
+}
+
+// The decoded file replaces the staged download, so a failed check never leaves it resumable.
+async fn decompress_staged(
+    compression: Compression,
+    path: &Path,
+    task: &DownloadTask,
+) -> Result<()> {
+    let mut decoded_path = path.as_os_str().to_owned();
+    decoded_path.push(".decoded");
+    let decoded_path = PathBuf::from(decoded_path);
+
+    let (source, target) = (path.to_path_buf(), decoded_path.clone());
+    let decoded =
+        tokio::task::spawn_blocking(move || compression.decompress_file_blocking(&source, &target))
+            .await
+            .context("decompress task panicked");
+    let validated = match decoded {
+        Ok(Ok(_)) => validate_file(&decoded_path, &DownloadValidation::from_task(task)).await,
+        Ok(Err(error)) | Err(error) => Err(error),
+    };
+    if let Err(error) = validated {
+        let _ = tokio::fs::remove_file(&decoded_path).await;
+        return Err(error);
+    }
+
+    tokio::fs::rename(&decoded_path, path)
+        .await
+        .with_context(|| format!("replace staged download failed: {}", path.display()))
+}
+
+async fn reset_staged_file(file: &mut tokio::fs::File, len: u64) -> Result<()> {
+    file.set_len(len).await?;
+    file.seek(SeekFrom::Start(len)).await?;
+    Ok(())
+}
+
+fn response_range_validator(headers: &HeaderMap) -> Option<String> {
+    // Weak entity tags are not allowed in `If-Range`, fall back to the modification date instead.
+    headers
+        .get(ETAG)
+        .and_then(|value| value.to_str().ok())
+        .filter(|etag| !etag.starts_with("W/"))
+        .or_else(|| {
+            headers
+                .get(LAST_MODIFIED)
+                .and_then(|value| value.to_str().ok())
+        })
+        .map(ToOwned::to_owned)
+}
+
+fn content_range_start(headers: &HeaderMap) -> Option<u64> {
+    headers
+        .get(CONTENT_RANGE)?
+        .to_str()
+        .ok()?
+        .strip_prefix("bytes ")?
+        .split_once('-')?
+        .0
+        .trim()
+        .parse()
+        .ok()
+}
+
+fn local_file_path(url: &str) -> Option<PathBuf> {
+    let url = reqwest::Url::parse(url).ok()?;
+    if url.scheme() != "file" {
+        return None;
+    }
+
+    url.to_file_path().ok()
+}
+
+fn content_range_total(headers: &HeaderMap) -> Option<u64> {
+    headers
+        .get(CONTENT_RANGE)?
+        .to_str()
+        .ok()?
+        .rsplit_once('/')?
+        .1
+        .trim()
+        .parse()
+        .ok()
+}
+
 fn build_queue_capacity(worker_count: usize) -> usize {
     (worker_count.saturating_mul(32)).max(128)
 }
@@ -1050,6 +2021,22 @@ fn validate_downloader_config(
         bail!("downloader session_queue_capacity must be greater than zero");
     }
 
+    if let Some(segmentation) = config.segmentation
+        && (segmentation.max_segments < 2 || segmentation.min_segment_size == 0)
+    {
+        bail!("downloader segmentation needs at least two segments of a non-zero size");
+    }
+
+    if let Some((host, _)) = config
+        .host_limits
+        .iter()
+        .map(|(host, limit)| (host.as_str(), limit))
+        .chain([("default", &config.default_host_limit)])
+        .find(|(_, limit)| limit.max_connections == Some(0))
+    {
+        bail!("downloader host limit for {host} must allow at least one connection");
+    }
+
     Ok(ValidatedDownloaderConfig {
         max_connections: config.max_connections,
         session_parallelism: config.session_parallelism,
@@ -1057,15 +2044,27 @@ fn validate_downloader_config(
         connect_timeout: config.connect_timeout,
         retry_times: config.retry_times,
         rate_limit: config.rate_limit,
+        segmentation: config.segmentation,
+        local_file_mode: config.local_file_mode,
+        host_limits: config.host_limits,
+        default_host_limit: config.default_host_limit,
+        max_retry_after: config.max_retry_after,
     })
 }
 
-fn build_downloader_client(config: &ValidatedDownloaderConfig) -> Result<reqwest::Client> {
+fn build_downloader_client(
+    config: &ValidatedDownloaderConfig,
+    client_builder: ClientBuilder,
+) -> Result<reqwest::Client> {
     let retry_policy = retry::for_host(ANY_HOST)
         .max_retries_per_request(config.retry_times)
         .classify_fn(|req_rep| {
             if req_rep.error().is_some()
-                || matches!(req_rep.status(), Some(status) if status.is_server_error())
+                || matches!(
+                    req_rep.status(),
+                    // Throttling responses are handled by the downloader, which honors `Retry-After`.
+                    Some(status) if status.is_server_error() && status != StatusCode::SERVICE_UNAVAILABLE
+                )
             {
                 req_rep.retryable()
             } else {
@@ -1073,7 +2072,7 @@ fn build_downloader_client(config: &ValidatedDownloaderConfig) -> Result<reqwest
             }
         });
 
-    ClientBuilder::new()
+    client_builder
         .retry(retry_policy)
         .connect_timeout(config.connect_timeout)
         .build()
@@ -1129,3 +2128,1022 @@ async fn finish_task_cancelled(
     mark_task_cancelled(tracker, handler, session_id, task_id).await;
     handler.finish_task();
 }
+
+#[cfg(test)]
+fn spawn_flaky_server(
+    payload: Arc<Vec<u8>>,
+    honor_range: bool,
+) -> (String, Arc<std::sync::Mutex<Vec<Option<String>>>>) {
+    use std::io::{BufRead, BufReader, Write};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("bind test server");
+    let url = format!("http://{}/artifact.jar", listener.local_addr().unwrap());
+    let ranges = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let seen = ranges.clone();
+
+    std::thread::spawn(move || {
+        for (index, stream) in listener.incoming().enumerate() {
+            let mut stream = stream.expect("accept test connection");
+            let mut range = None;
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':')
+                    && name.eq_ignore_ascii_case("range")
+                {
+                    range = Some(value.trim().to_owned());
+                }
+            }
+            seen.lock().unwrap().push(range.clone());
+
+            let start = range
+                .as_deref()
+                .filter(|_| honor_range && index > 0)
+                .and_then(|range| range.strip_prefix("bytes="))
+                .and_then(|range| range.trim_end_matches('-').parse::<usize>().ok());
+            let (head, body) = match start {
+                Some(start) => (
+                    format!(
+                        "HTTP/1.1 206 Partial Content\r\nContent-Length: {}\r\nContent-Range: bytes {}-{}/{}\r\nETag: \"v1\"\r\nConnection: close\r\n\r\n",
+                        payload.len() - start,
+                        start,
+                        payload.len() - 1,
+                        payload.len()
+                    ),
+                    &payload[start..],
+                ),
+                None => (
+                    format!(
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nETag: \"v1\"\r\nConnection: close\r\n\r\n",
+                        payload.len()
+                    ),
+                    &payload[..],
+                ),
+            };
+            stream.write_all(head.as_bytes()).unwrap();
+            // The first connection drops halfway through the body.
+            let body = if index == 0 {
+                &body[..body.len() / 2]
+            } else {
+                body
+            };
+            let _ = stream.write_all(body);
+            let _ = stream.flush();
+        }
+    });
+
+    (url, ranges)
+}
+
+#[cfg(test)]
+async fn download_from_flaky_server(honor_range: bool) -> Vec<Option<String>> {
+    let payload = Arc::new(
+        (0..512 * 1024)
+            .map(|index| (index % 251) as u8)
+            .collect::<Vec<u8>>(),
+    );
+    let (url, ranges) = spawn_flaky_server(payload.clone(), honor_range);
+    let root = std::env::temp_dir().join(format!(
+        "elemental-resume-{}-{}",
+        std::process::id(),
+        honor_range
+    ));
+    let target = root.join("artifact.jar");
+    let _ = tokio::fs::remove_dir_all(&root).await;
+
+    let downloader = ElementalDownloader::new();
+    let session = downloader
+        .create_unnamed_session(DownloadExecutionPolicy::ServiceDefault)
+        .await
+        .unwrap();
+    session
+        .add_task(DownloadTask::new(
+            url,
+            &target,
+            Some(payload.len() as u64),
+            Some(
+                sha1_smol::Sha1::from(payload.as_slice())
+                    .digest()
+                    .to_string(),
+            ),
+        ))
+        .await
+        .unwrap();
+    let snapshot = session.finish_snapshot().await.unwrap();
+
+    assert!(snapshot.failures.is_empty(), "{:?}", snapshot.failures);
+    assert_eq!(tokio::fs::read(&target).await.unwrap(), *payload);
+    let _ = tokio::fs::remove_dir_all(&root).await;
+    ranges.lock().unwrap().clone()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_download_resumes_after_dropped_connection() {
+    let ranges = download_from_flaky_server(true).await;
+    assert_eq!(ranges.len(), 2);
+    assert_eq!(ranges[0], None);
+    assert_eq!(ranges[1].as_deref(), Some("bytes=262144-"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_download_restarts_when_range_is_ignored() {
+    let ranges = download_from_flaky_server(false).await;
+    assert_eq!(ranges.len(), 2);
+    assert!(ranges[1].is_some());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_download_restarts_from_zero_when_resumed_prefix_is_corrupt() {
+    use std::io::{BufRead, BufReader, Write};
+
+    let payload = Arc::new(
+        (0..64 * 1024)
+            .map(|index| (index % 251) as u8)
+            .collect::<Vec<u8>>(),
+    );
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("bind test server");
+    let url = format!("http://{}/artifact.jar", listener.local_addr().unwrap());
+    let ranges = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let seen = ranges.clone();
+    let body = payload.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.expect("accept test connection");
+            let mut range = None;
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':')
+                    && name.eq_ignore_ascii_case("range")
+                {
+                    range = Some(value.trim().to_owned());
+                }
+            }
+            seen.lock().unwrap().push(range.clone());
+
+            let start = range
+                .as_deref()
+                .and_then(|range| range.strip_prefix("bytes="))
+                .and_then(|range| range.trim_end_matches('-').parse::<usize>().ok())
+                .unwrap_or(0);
+            let head = if start > 0 {
+                format!(
+                    "HTTP/1.1 206 Partial Content\r\nContent-Length: {}\r\nContent-Range: bytes {}-{}/{}\r\nConnection: close\r\n\r\n",
+                    body.len() - start,
+                    start,
+                    body.len() - 1,
+                    body.len()
+                )
+            } else {
+                format!(
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    body.len()
+                )
+            };
+            stream.write_all(head.as_bytes()).unwrap();
+            let _ = stream.write_all(&body[start..]);
+            let _ = stream.flush();
+        }
+    });
+    let root = std::env::temp_dir().join(format!("elemental-restart-{}", std::process::id()));
+    let target = root.join("artifact.jar");
+    let _ = tokio::fs::remove_dir_all(&root).await;
+    let task = DownloadTask::new(
+        url,
+        &target,
+        Some(payload.len() as u64),
+        Some(
+            sha1_smol::Sha1::from(payload.as_slice())
+                .digest()
+                .to_string(),
+        ),
+    );
+
+    // A prefix left behind by an earlier run that no longer matches the artifact.
+    let staged = NoCachedMaterializer.create_staging(&task).await.unwrap();
+    tokio::fs::write(&staged.path, vec![0u8; 1024])
+        .await
+        .unwrap();
+    drop(staged);
+
+    let downloader = ElementalDownloader::new();
+    let session = downloader
+        .create_unnamed_session(DownloadExecutionPolicy::ServiceDefault)
+        .await
+        .unwrap();
+    session.add_task(task).await.unwrap();
+    let snapshot = session.finish_snapshot().await.unwrap();
+
+    assert!(snapshot.failures.is_empty(), "{:?}", snapshot.failures);
+    assert_eq!(tokio::fs::read(&target).await.unwrap(), *payload);
+    assert_eq!(
+        *ranges.lock().unwrap(),
+        vec![Some("bytes=1024-".to_owned()), None]
+    );
+    let _ = tokio::fs::remove_dir_all(&root).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_download_falls_back_to_next_mirror() {
+    let payload = Arc::new(vec![7u8; 64 * 1024]);
+    let (broken_url, _) = spawn_flaky_server(Arc::new(vec![0u8; 64 * 1024]), true);
+    let (mirror_url, _) = spawn_flaky_server(payload.clone(), true);
+    let root = std::env::temp_dir().join(format!("elemental-mirror-{}", std::process::id()));
+    let target = root.join("artifact.jar");
+    let _ = tokio::fs::remove_dir_all(&root).await;
+
+    let downloader = ElementalDownloader::new();
+    let session = downloader
+        .create_unnamed_session(DownloadExecutionPolicy::ServiceDefault)
+        .await
+        .unwrap();
+    session
+        .add_task(
+            DownloadTask::from_candidates(
+                vec![broken_url, mirror_url],
+                &target,
+                Some(payload.len() as u64),
+                Some(
+                    sha1_smol::Sha1::from(payload.as_slice())
+                        .digest()
+                        .to_string(),
+                ),
+            )
+            .unwrap(),
+        )
+        .await
+        .unwrap();
+    let snapshot = session.finish_snapshot().await.unwrap();
+
+    assert!(snapshot.failures.is_empty(), "{:?}", snapshot.failures);
+    assert_eq!(tokio::fs::read(&target).await.unwrap(), *payload);
+    assert_eq!(snapshot.host_health.len(), 2);
+    assert_eq!(
+        snapshot
+            .host_health
+            .iter()
+            .map(|host| (host.successes, host.failures))
+            .filter(|score| *score == (0, 1) || *score == (1, 0))
+            .count(),
+        2
+    );
+    let _ = tokio::fs::remove_dir_all(&root).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_download_backs_off_on_retry_after() {
+    use std::io::{BufRead, BufReader, Write};
+
+    let payload = Arc::new(vec![3u8; 16 * 1024]);
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("bind test server");
+    let host = listener.local_addr().unwrap().to_string();
+    let url = format!("http://{host}/artifact.jar");
+    let body = payload.clone();
+    std::thread::spawn(move || {
+        for (index, stream) in listener.incoming().enumerate() {
+            let mut stream = stream.expect("accept test connection");
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim().is_empty() {
+                    break;
+                }
+            }
+            if index == 0 {
+                let _ = stream.write_all(
+                    b"HTTP/1.1 429 Too Many Requests\r\nRetry-After: 1\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                );
+                continue;
+            }
+            let head = format!(
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                body.len()
+            );
+            let _ = stream.write_all(head.as_bytes());
+            let _ = stream.write_all(&body);
+        }
+    });
+    let root = std::env::temp_dir().join(format!("elemental-retry-after-{}", std::process::id()));
+    let target = root.join("artifact.jar");
+    let _ = tokio::fs::remove_dir_all(&root).await;
+
+    let downloader = ElementalDownloaderBuilder::new()
+        .config(ElementalDownloaderConfig {
+            host_limits: [(
+                host.clone(),
+                HostLimit {
+                    max_connections: Some(1),
+                    rate_limit: DownloadRateLimit::Unlimited,
+                },
+            )]
+            .into(),
+            ..ElementalDownloaderConfig::default()
+        })
+        .build()
+        .unwrap();
+    let session = downloader
+        .create_unnamed_session(DownloadExecutionPolicy::ServiceDefault)
+        .await
+        .unwrap();
+    let started = Instant::now();
+    session
+        .add_task(DownloadTask::new(
+            url,
+            &target,
+            Some(payload.len() as u64),
+            None,
+        ))
+        .await
+        .unwrap();
+    let snapshot = session.finish_snapshot().await.unwrap();
+
+    assert!(snapshot.failures.is_empty(), "{:?}", snapshot.failures);
+    assert!(started.elapsed() >= Duration::from_secs(1));
+    assert_eq!(tokio::fs::read(&target).await.unwrap(), *payload);
+    assert_eq!(snapshot.host_health.len(), 1);
+    assert_eq!(snapshot.host_health[0].host, host);
+    assert_eq!(snapshot.host_health[0].throttled, 1);
+    assert_eq!(
+        snapshot.host_health[0].retry_after,
+        Some(Duration::from_secs(1))
+    );
+    assert_eq!(snapshot.host_health[0].successes, 1);
+    let _ = tokio::fs::remove_dir_all(&root).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_capped_host_does_not_hold_connections_of_other_hosts() {
+    use std::io::{BufRead, BufReader, Write};
+
+    let serve = |delay: Duration| {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("bind test server");
+        let host = listener.local_addr().unwrap().to_string();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.expect("accept test connection");
+                std::thread::spawn(move || {
+                    let mut reader = BufReader::new(stream.try_clone().unwrap());
+                    loop {
+                        let mut line = String::new();
+                        reader.read_line(&mut line).unwrap();
+                        if line.trim().is_empty() {
+                            break;
+                        }
+                    }
+                    std::thread::sleep(delay);
+                    let _ = stream.write_all(
+                        b"HTTP/1.1 200 OK\r\nContent-Length: 4\r\nConnection: close\r\n\r\ndata",
+                    );
+                });
+            }
+        });
+        host
+    };
+    let slow_host = serve(Duration::from_millis(1500));
+    let fast_host = serve(Duration::ZERO);
+    let root = std::env::temp_dir().join(format!("elemental-host-cap-{}", std::process::id()));
+    let _ = tokio::fs::remove_dir_all(&root).await;
+
+    let downloader = ElementalDownloaderBuilder::new()
+        .config(ElementalDownloaderConfig {
+            max_connections: 2,
+            host_limits: [(
+                slow_host.clone(),
+                HostLimit {
+                    max_connections: Some(1),
+                    rate_limit: DownloadRateLimit::Unlimited,
+                },
+            )]
+            .into(),
+            ..ElementalDownloaderConfig::default()
+        })
+        .build()
+        .unwrap();
+    let session = downloader
+        .create_unnamed_session(DownloadExecutionPolicy::ServiceDefault)
+        .await
+        .unwrap();
+    let mut events = session.subscribe().await.unwrap();
+    let fast_task = DownloadTask::new(
+        format!("http://{fast_host}/c.jar"),
+        root.join("c.jar"),
+        Some(4),
+        None,
+    );
+    let fast_task_id = session.task_id(&fast_task);
+    for name in ["a.jar", "b.jar"] {
+        session
+            .add_task(DownloadTask::new(
+                format!("http://{slow_host}/{name}"),
+                root.join(name),
+                Some(4),
+                None,
+            ))
+            .await
+            .unwrap();
+    }
+    session.add_task(fast_task).await.unwrap();
+    let snapshot = session.finish_snapshot().await.unwrap();
+
+    assert!(snapshot.failures.is_empty(), "{:?}", snapshot.failures);
+    let first_completed =
+        std::iter::from_fn(|| events.try_recv().ok()).find_map(|event| match event.kind {
+            DownloadEventKind::Completed { task_id } => Some(task_id),
+            _ => None,
+        });
+    // The second slow task waits for its host slot without the last global connection.
+    assert_eq!(first_completed, Some(fast_task_id));
+    let _ = tokio::fs::remove_dir_all(&root).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_session_runs_tasks_by_priority_and_cancels_by_id() {
+    use std::io::{BufRead, BufReader, Write};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("bind test server");
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    let requests = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let seen = requests.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.expect("accept test connection");
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim().is_empty() {
+                    break;
+                }
+            }
+            let path = request_line
+                .split_whitespace()
+                .nth(1)
+                .unwrap_or_default()
+                .trim_start_matches('/')
+                .to_owned();
+            seen.lock().unwrap().push(path.clone());
+            // Hold the only worker so every later task is queued before one is picked.
+            if path == "first" {
+                std::thread::sleep(Duration::from_millis(500));
+            }
+            let head = format!(
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                path.len()
+            );
+            let _ = stream.write_all(head.as_bytes());
+            let _ = stream.write_all(path.as_bytes());
+        }
+    });
+    let root = std::env::temp_dir().join(format!("elemental-priority-{}", std::process::id()));
+    let _ = tokio::fs::remove_dir_all(&root).await;
+    let task = |name: &str, priority: DownloadPriority| {
+        DownloadTask::new(format!("{base}/{name}"), root.join(name), None, None)
+            .with_priority(priority)
+    };
+
+    let downloader = ElementalDownloader::new();
+    let session = downloader
+        .create_unnamed_session(DownloadExecutionPolicy::Custom {
+            parallelism: 1,
+            rate_limit: DownloadRateLimit::Unlimited,
+        })
+        .await
+        .unwrap();
+    session
+        .add_task(task("first", DownloadPriority::Normal))
+        .await
+        .unwrap();
+    while requests.lock().unwrap().is_empty() {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    let asset = task("asset", DownloadPriority::Low);
+    let dropped = task("dropped", DownloadPriority::Normal);
+    session.add_task(asset.clone()).await.unwrap();
+    session
+        .add_task(task("library", DownloadPriority::Normal))
+        .await
+        .unwrap();
+    session.add_task(dropped.clone()).await.unwrap();
+    session
+        .add_task(task("client", DownloadPriority::Critical))
+        .await
+        .unwrap();
+    assert!(
+        session
+            .set_task_priority(&session.task_id(&asset), DownloadPriority::High)
+            .await
+            .unwrap()
+    );
+    let dropped_id = session.task_id(&dropped);
+    assert!(session.cancel_task(&dropped_id).await.unwrap());
+    assert!(!session.cancel_task(&dropped_id).await.unwrap());
+    let snapshot = session.finish_snapshot().await.unwrap();
+
+    assert!(snapshot.failures.is_empty(), "{:?}", snapshot.failures);
+    assert_eq!(snapshot.downloaded, 4);
+    assert_eq!(snapshot.cancelled_task_ids, vec![dropped_id]);
+    assert_eq!(
+        *requests.lock().unwrap(),
+        vec!["first", "client", "asset", "library"]
+    );
+    assert!(!root.join("dropped").exists());
+    let _ = tokio::fs::remove_dir_all(&root).await;
+}
+
+#[cfg(test)]
+fn spawn_range_server(payload: Arc<Vec<u8>>) -> (String, Arc<std::sync::Mutex<Vec<String>>>) {
+    use std::io::{BufRead, BufReader, Write};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("bind test server");
+    let url = format!("http://{}/client.jar", listener.local_addr().unwrap());
+    let ranges = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let seen = ranges.clone();
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.expect("accept test connection");
+            let mut range = String::new();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':')
+                    && name.eq_ignore_ascii_case("range")
+                {
+                    range = value.trim().to_owned();
+                }
+            }
+            seen.lock().unwrap().push(range.clone());
+
+            let (start, end) = range
+                .strip_prefix("bytes=")
+                .and_then(|range| range.split_once('-'))
+                .expect("segmented request without a range");
+            let start = start.parse::<usize>().unwrap();
+            let end = end
+                .parse::<usize>()
+                .map(|end| end + 1)
+                .unwrap_or(payload.len());
+            let head = format!(
+                "HTTP/1.1 206 Partial Content\r\nContent-Length: {}\r\nContent-Range: bytes {}-{}/{}\r\nETag: \"v1\"\r\nConnection: close\r\n\r\n",
+                end - start,
+                start,
+                end - 1,
+                payload.len()
+            );
+            stream.write_all(head.as_bytes()).unwrap();
+            let _ = stream.write_all(&payload[start..end]);
+            let _ = stream.flush();
+        }
+    });
+
+    (url, ranges)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_download_splits_large_files_into_segments() {
+    let payload = Arc::new(
+        (0..1024 * 1024)
+            .map(|index| (index % 241) as u8)
+            .collect::<Vec<u8>>(),
+    );
+    let (url, ranges) = spawn_range_server(payload.clone());
+    let root = std::env::temp_dir().join(format!("elemental-segments-{}", std::process::id()));
+    let target = root.join("client.jar");
+    let _ = tokio::fs::remove_dir_all(&root).await;
+
+    let downloader = ElementalDownloader::builder()
+        .config(ElementalDownloaderConfig {
+            segmentation: Some(DownloadSegmentation {
+                min_file_size: 512 * 1024,
+                min_segment_size: 256 * 1024,
+                max_segments: 4,
+            }),
+            ..ElementalDownloaderConfig::default()
+        })
+        .build()
+        .unwrap();
+    let session = downloader
+        .create_unnamed_session(DownloadExecutionPolicy::ServiceDefault)
+        .await
+        .unwrap();
+    let mut events = session.subscribe().await.unwrap();
+    let progress = session.watch_progress().await.unwrap();
+    session
+        .add_task(DownloadTask::new(
+            url,
+            &target,
+            Some(payload.len() as u64),
+            Some(
+                sha1_smol::Sha1::from(payload.as_slice())
+                    .digest()
+                    .to_string(),
+            ),
+        ))
+        .await
+        .unwrap();
+    let snapshot = session.finish_snapshot().await.unwrap();
+
+    assert!(snapshot.failures.is_empty(), "{:?}", snapshot.failures);
+    assert_eq!(tokio::fs::read(&target).await.unwrap(), *payload);
+    let mut ranges = ranges.lock().unwrap().clone();
+    ranges.sort();
+    assert_eq!(
+        ranges,
+        vec![
+            "bytes=0-262143",
+            "bytes=262144-524287",
+            "bytes=524288-786431",
+            "bytes=786432-1048575",
+        ]
+    );
+
+    let mut kinds = Vec::new();
+    while let Ok(event) = events.try_recv() {
+        kinds.push(event.kind);
+    }
+    assert!(matches!(
+        kinds.first(),
+        Some(DownloadEventKind::Queued {
+            expected_size: Some(1048576),
+            ..
+        })
+    ));
+    assert!(matches!(
+        kinds[1],
+        DownloadEventKind::Started {
+            content_length: Some(1048576),
+            ..
+        }
+    ));
+    assert!(matches!(
+        kinds[kinds.len() - 2],
+        DownloadEventKind::Completed { .. }
+    ));
+    assert!(matches!(
+        kinds.last(),
+        Some(DownloadEventKind::SessionFinished { downloaded: 1, .. })
+    ));
+    let progress = progress.borrow().clone();
+    assert_eq!(progress.finished_tasks, 1);
+    assert_eq!(progress.expected_bytes, 1048576);
+    assert_eq!(progress.received_bytes, 1048576);
+    let _ = tokio::fs::remove_dir_all(&root).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_journal_skips_verified_tasks_after_restart() {
+    let root = std::env::temp_dir().join(format!("elemental-journal-{}", std::process::id()));
+    let target = root.join("libraries").join("library.jar");
+    let _ = tokio::fs::remove_dir_all(&root).await;
+    tokio::fs::create_dir_all(target.parent().unwrap())
+        .await
+        .unwrap();
+    tokio::fs::write(&target, b"verified library")
+        .await
+        .unwrap();
+    let task = DownloadTask::new(
+        "http://127.0.0.1:9/library.jar",
+        &target,
+        Some(16),
+        Some(
+            sha1_smol::Sha1::from(b"verified library".as_slice())
+                .digest()
+                .to_string(),
+        ),
+    );
+
+    // What a run that died before finishing the session leaves behind.
+    let journal = DownloadJournal::new(root.join("journal"))
+        .open_session("1.20.1")
+        .unwrap();
+    journal.record_task(&task).unwrap();
+    journal.record_verified(&task).unwrap();
+    drop(journal);
+
+    let downloader = ElementalDownloader::builder()
+        .journal(root.join("journal"))
+        .build()
+        .unwrap();
+    let plans = downloader.journal().unwrap().unfinished_plans().unwrap();
+    assert_eq!(plans.len(), 1);
+    assert_eq!(plans[0].session.name.as_deref(), Some("1.20.1"));
+    assert_eq!(plans[0].tasks, vec![task]);
+
+    // Same size and modification time, so the journal trusts it without hashing.
+    let modified = std::fs::metadata(&target).unwrap().modified().unwrap();
+    std::fs::write(&target, b"tampered library").unwrap();
+    std::fs::File::options()
+        .write(true)
+        .open(&target)
+        .unwrap()
+        .set_modified(modified)
+        .unwrap();
+
+    downloader.resume_journaled_sessions().await.unwrap();
+    assert!(
+        downloader
+            .journal()
+            .unwrap()
+            .unfinished_plans()
+            .unwrap()
+            .is_empty()
+    );
+    let _ = tokio::fs::remove_dir_all(&root).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_download_materializes_local_file_urls() {
+    let root = std::env::temp_dir().join(format!("elemental-local-{}", std::process::id()));
+    let source = root.join("repository").join("library.jar");
+    let _ = tokio::fs::remove_dir_all(&root).await;
+    tokio::fs::create_dir_all(source.parent().unwrap())
+        .await
+        .unwrap();
+    tokio::fs::write(&source, b"local library").await.unwrap();
+    let url = reqwest::Url::from_file_path(&source).unwrap().to_string();
+    let sha1 = sha1_smol::Sha1::from(b"local library".as_slice())
+        .digest()
+        .to_string();
+
+    for (mode, name) in [
+        (LocalFileMode::Copy, "copied.jar"),
+        (LocalFileMode::HardlinkOrCopy, "linked.jar"),
+    ] {
+        let downloader = ElementalDownloader::builder()
+            .config(ElementalDownloaderConfig {
+                local_file_mode: mode,
+                ..ElementalDownloaderConfig::default()
+            })
+            .build()
+            .unwrap();
+        let target = root.join("libraries").join(name);
+        let session = downloader
+            .create_unnamed_session(DownloadExecutionPolicy::ServiceDefault)
+            .await
+            .unwrap();
+        session
+            .add_task(DownloadTask::new(
+                url.clone(),
+                &target,
+                Some(13),
+                Some(sha1.clone()),
+            ))
+            .await
+            .unwrap();
+        let snapshot = session.finish_snapshot().await.unwrap();
+
+        assert!(snapshot.failures.is_empty(), "{:?}", snapshot.failures);
+        assert_eq!(tokio::fs::read(&target).await.unwrap(), b"local library");
+    }
+    assert_eq!(tokio::fs::read(&source).await.unwrap(), b"local library");
+
+    let downloader = ElementalDownloader::new();
+    let session = downloader
+        .create_unnamed_session(DownloadExecutionPolicy::ServiceDefault)
+        .await
+        .unwrap();
+    session
+        .add_task(DownloadTask::new(
+            url,
+            root.join("libraries").join("mismatch.jar"),
+            Some(13),
+            Some("0".repeat(40)),
+        ))
+        .await
+        .unwrap();
+    let snapshot = session.finish_snapshot().await.unwrap();
+    assert_eq!(snapshot.failed, 1);
+    assert!(tokio::fs::metadata(&source).await.is_ok());
+    let _ = tokio::fs::remove_dir_all(&root).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_cache_evicts_only_unlinked_entries() {
+    let root = std::env::temp_dir().join(format!("elemental-cache-{}", std::process::id()));
+    let _ = tokio::fs::remove_dir_all(&root).await;
+    tokio::fs::create_dir_all(root.join("repository"))
+        .await
+        .unwrap();
+
+    let materializer = Arc::new(
+        HardlinkCachedMaterializer::new(root.join("cache")).with_limits(CacheLimits {
+            max_bytes: Some(0),
+            max_age: None,
+        }),
+    );
+    // Copy local sources so cache entries are not linked back to the repository.
+    let downloader = ElementalDownloader::builder()
+        .config(ElementalDownloaderConfig {
+            local_file_mode: LocalFileMode::Copy,
+            ..ElementalDownloaderConfig::default()
+        })
+        .materializer(materializer.clone())
+        .build()
+        .unwrap();
+    let mut tasks = Vec::new();
+    for name in ["kept.jar", "dropped.jar"] {
+        let source = root.join("repository").join(name);
+        tokio::fs::write(&source, name).await.unwrap();
+        tasks.push(DownloadTask::new(
+            reqwest::Url::from_file_path(&source).unwrap().to_string(),
+            root.join("instance").join(name),
+            Some(name.len() as u64),
+            Some(sha1_smol::Sha1::from(name).digest().to_string()),
+        ));
+    }
+    downloader
+        .run_plan(DownloadPlan::new(
+            DownloadSessionRequest::unnamed(DownloadExecutionPolicy::ServiceDefault).unwrap(),
+            tasks.clone(),
+        ))
+        .await
+        .unwrap();
+
+    // A second instance resolves the kept artifact straight from the cache.
+    let reused = DownloadTask {
+        path: root.join("other").join("kept.jar"),
+        ..tasks[0].clone()
+    };
+    assert!(materializer.resolve(&reused).await.unwrap());
+    let stats = downloader.cache_stats().unwrap();
+    assert_eq!((stats.hits, stats.misses), (1, 2));
+    assert_eq!(stats.bytes_saved, "kept.jar".len() as u64);
+
+    tokio::fs::remove_file(&tasks[1].path).await.unwrap();
+    let report = materializer.evict().await.unwrap();
+    assert_eq!(report.scanned_files, 2);
+    assert_eq!(report.evicted.len(), 1);
+    assert_eq!(report.skipped_linked, 1);
+    assert!(report.evicted[0].ends_with(sha1_smol::Sha1::from("dropped.jar").digest().to_string()));
+    assert_eq!(tokio::fs::read(&reused.path).await.unwrap(), b"kept.jar");
+    let _ = tokio::fs::remove_dir_all(&root).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_cache_evicts_after_commits_by_recorded_access_time() {
+    let root = std::env::temp_dir().join(format!("elemental-cache-commit-{}", std::process::id()));
+    let _ = tokio::fs::remove_dir_all(&root).await;
+    tokio::fs::create_dir_all(root.join("repository"))
+        .await
+        .unwrap();
+    let task = |name: &str| {
+        let source = root.join("repository").join(name);
+        std::fs::write(&source, name).unwrap();
+        DownloadTask::new(
+            reqwest::Url::from_file_path(&source).unwrap().to_string(),
+            root.join("instance").join(name),
+            Some(name.len() as u64),
+            Some(sha1_smol::Sha1::from(name).digest().to_string()),
+        )
+    };
+    let cache_path = |name: &str| {
+        let digest = sha1_smol::Sha1::from(name).digest().to_string();
+        root.join("cache").join(&digest[..2]).join(digest)
+    };
+    let limits = CacheLimits {
+        max_bytes: Some("new.jar".len() as u64 + "more.jar".len() as u64),
+        max_age: None,
+    };
+    let downloader = |materializer: Arc<HardlinkCachedMaterializer>| {
+        ElementalDownloader::builder()
+            .config(ElementalDownloaderConfig {
+                local_file_mode: LocalFileMode::Copy,
+                ..ElementalDownloaderConfig::default()
+            })
+            .materializer(materializer)
+            .build()
+            .unwrap()
+    };
+    let run = |downloader: Arc<ElementalDownloader>, tasks: Vec<DownloadTask>| async move {
+        downloader
+            .run_plan(DownloadPlan::new(
+                DownloadSessionRequest::unnamed(DownloadExecutionPolicy::ServiceDefault).unwrap(),
+                tasks,
+            ))
+            .await
+            .unwrap();
+    };
+
+    let materializer =
+        Arc::new(HardlinkCachedMaterializer::new(root.join("cache")).with_limits(limits));
+    run(
+        downloader(materializer.clone()),
+        vec![task("old.jar"), task("new.jar")],
+    )
+    .await;
+    for name in ["old.jar", "new.jar"] {
+        tokio::fs::remove_file(root.join("instance").join(name))
+            .await
+            .unwrap();
+    }
+    // Both entries were written long ago, only the second one was linked out since.
+    let long_ago = std::time::SystemTime::now() - Duration::from_secs(3600);
+    for name in ["old.jar", "new.jar"] {
+        std::fs::File::options()
+            .write(true)
+            .open(cache_path(name))
+            .unwrap()
+            .set_times(
+                std::fs::FileTimes::new()
+                    .set_modified(long_ago)
+                    .set_accessed(long_ago),
+            )
+            .unwrap();
+    }
+    std::fs::File::open(cache_path("new.jar"))
+        .unwrap()
+        .set_times(std::fs::FileTimes::new().set_accessed(std::time::SystemTime::now()))
+        .unwrap();
+
+    // A new process knows nothing of earlier hits, yet its first commit evicts by access time.
+    let materializer =
+        Arc::new(HardlinkCachedMaterializer::new(root.join("cache")).with_limits(limits));
+    run(downloader(materializer.clone()), vec![task("more.jar")]).await;
+    assert!(!cache_path("old.jar").exists());
+    assert!(cache_path("new.jar").exists() && cache_path("more.jar").exists());
+    let stats = materializer.stats();
+    assert_eq!((stats.evicted_files, stats.evicted_bytes), (1, 7));
+    let _ = tokio::fs::remove_dir_all(&root).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_download_decompresses_and_validates_decoded_output() {
+    let root = std::env::temp_dir().join(format!("elemental-decompress-{}", std::process::id()));
+    let source = root.join("repository").join("library.jar.xz");
+    let _ = tokio::fs::remove_dir_all(&root).await;
+    tokio::fs::create_dir_all(source.parent().unwrap())
+        .await
+        .unwrap();
+    let payload = b"decoded library".repeat(64);
+    let mut packed = Vec::new();
+    lzma_rs::xz_compress(&mut payload.as_slice(), &mut packed).unwrap();
+    tokio::fs::write(&source, &packed).await.unwrap();
+    let url = reqwest::Url::from_file_path(&source).unwrap().to_string();
+
+    let downloader = ElementalDownloader::new();
+    let session = downloader
+        .create_unnamed_session(DownloadExecutionPolicy::ServiceDefault)
+        .await
+        .unwrap();
+    let target = root.join("libraries").join("library.jar");
+    session
+        .add_task(
+            DownloadTask::new(
+                url.clone(),
+                &target,
+                Some(payload.len() as u64),
+                Some(
+                    sha1_smol::Sha1::from(payload.as_slice())
+                        .digest()
+                        .to_string(),
+                ),
+            )
+            .with_decompression(Compression::Xz),
+        )
+        .await
+        .unwrap();
+    // Digests of the compressed body don't describe the decoded file.
+    session
+        .add_task(
+            DownloadTask::new(
+                url,
+                root.join("libraries").join("mismatch.jar"),
+                None,
+                Some(
+                    sha1_smol::Sha1::from(packed.as_slice())
+                        .digest()
+                        .to_string(),
+                ),
+            )
+            .with_decompression(Compression::Xz),
+        )
+        .await
+        .unwrap();
+    let snapshot = session.finish_snapshot().await.unwrap();
+
+    assert_eq!(snapshot.failed, 1, "{:?}", snapshot.failures);
+    assert_eq!(tokio::fs::read(&target).await.unwrap(), payload);
+    assert_eq!(tokio::fs::read(&source).await.unwrap(), packed);
+    assert!(
+        tokio::fs::metadata(root.join("libraries").join("mismatch.jar"))
+            .await
+            .is_err()
+    );
+    let _ = tokio::fs::remove_dir_all(&root).await;
+}
@@ -0,0 +1,71 @@
+use std::{path::PathBuf, time::Duration};
+
+use super::{task::SessionId, tracking::TaskId};
+
+pub(crate) const DOWNLOAD_EVENT_CAPACITY: usize = 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DownloadEvent {
+    pub session_id: SessionId,
+    pub session_name: Option<String>,
+    pub kind: DownloadEventKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DownloadEventKind {
+    Queued {
+        task_id: TaskId,
+        expected_size: Option<u64>,
+    },
+    Started {
+        task_id: TaskId,
+        url: String,
+        content_length: Option<u64>,
+    },
+    Progress {
+        task_id: TaskId,
+        received: u64,
+    },
+    Skipped {
+        task_id: TaskId,
+        path: PathBuf,
+    },
+    Completed {
+        task_id: TaskId,
+    },
+    Failed {
+        task_id: TaskId,
+        error: String,
+    },
+    Cancelled {
+        task_id: TaskId,
+    },
+    SessionFinished {
+        downloaded: usize,
+        skipped: usize,
+        failed: usize,
+        cancelled: usize,
+    },
+}
+
+/// Aggregate progress of a session, published through a watch channel.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DownloadProgress {
+    pub total_tasks: usize,
+    pub finished_tasks: usize,
+    pub expected_bytes: u64,
+    pub received_bytes: u64,
+    pub bytes_per_second: usize,
+    pub eta: Option<Duration>,
+}
+
+impl DownloadProgress {
+    pub(crate) fn refresh_eta(&mut self) {
+        self.eta = (self.bytes_per_second > 0).then(|| {
+            Duration::from_secs_f64(
+                self.expected_bytes.saturating_sub(self.received_bytes) as f64
+                    / self.bytes_per_second as f64,
+            )
+        });
+    }
+}
@@ -0,0 +1,97 @@
+use std::{collections::HashMap, time::Duration};
+
+use reqwest::Url;
+
+use super::{session::HostHealthSnapshot, throttle::HostThrottle};
+
+#[derive(Debug, Default)]
+pub(crate) struct HostHealth {
+    hosts: std::sync::Mutex<HashMap<String, HostScore>>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct HostScore {
+    successes: usize,
+    failures: usize,
+    throttled: usize,
+    retry_after: Option<Duration>,
+}
+
+impl HostScore {
+    fn penalty(self) -> usize {
+        self.failures.saturating_sub(self.successes)
+    }
+}
+
+impl HostHealth {
+    pub(crate) fn record_success(&self, url: &str) {
+        self.update(url, |score| score.successes += 1);
+    }
+
+    pub(crate) fn record_failure(&self, url: &str) {
+        self.update(url, |score| score.failures += 1);
+    }
+
+    pub(crate) fn record_throttled(&self, url: &str, retry_after: Duration) {
+        self.update(url, |score| {
+            score.throttled += 1;
+            score.retry_after = Some(retry_after);
+        });
+    }
+
+    // Candidates keep their declared order unless a host has failed more often than it worked.
+    pub(crate) fn order<'a>(&self, candidates: impl Iterator<Item = &'a str>) -> Vec<String> {
+        let hosts = self.hosts.lock().expect("host health mutex poisoned");
+        let mut candidates = candidates
+            .map(|url| {
+                let penalty = host_key(url)
+                    .and_then(|host| hosts.get(&host).copied())
+                    .unwrap_or_default()
+                    .penalty();
+                (penalty, url.to_owned())
+            })
+            .collect::<Vec<(usize, String)>>();
+        candidates.sort_by_key(|(penalty, _)| *penalty);
+        candidates.into_iter().map(|(_, url)| url).collect()
+    }
+
+    pub(crate) fn snapshot(&self, throttle: &HostThrottle) -> Vec<HostHealthSnapshot> {
+        let hosts = self.hosts.lock().expect("host health mutex poisoned");
+        let mut snapshot = hosts
+            .iter()
+            .map(|(host, score)| HostHealthSnapshot {
+                host: host.clone(),
+                successes: score.successes,
+                failures: score.failures,
+                throttled: score.throttled,
+                retry_after: score.retry_after,
+                backoff_remaining: throttle.backoff_remaining(host),
+            })
+            .collect::<Vec<HostHealthSnapshot>>();
+        snapshot.sort_by(|left, right| left.host.cmp(&right.host));
+        snapshot
+    }
+
+    fn update(&self, url: &str, apply: impl FnOnce(&mut HostScore)) {
+        let Some(host) = host_key(url) else {
+            return;
+        };
+
+        apply(
+            self.hosts
+                .lock()
+                .expect("host health mutex poisoned")
+                .entry(host)
+                .or_default(),
+        );
+    }
+}
+
+pub(crate) fn host_key(url: &str) -> Option<String> {
+    let url = Url::parse(url).ok()?;
+    let host = url.host_str()?;
+    Some(match url.port() {
+        Some(port) => format!("{host}:{port}"),
+        None => host.to_owned(),
+    })
+}
@@ -0,0 +1,369 @@
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use sha1_smol::Sha1;
+use std::{
+    collections::HashMap,
+    fs::{File, OpenOptions, TryLockError},
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::UNIX_EPOCH,
+};
+
+use super::task::{
+    DownloadExecutionPolicy, DownloadPlan, DownloadPriority, DownloadTask, FileDigest,
+};
+use crate::compression::Compression;
+
+const JOURNAL_EXTENSION: &str = "journal";
+const MAX_JOURNAL_SLOTS: usize = 32;
+
+/// Records named session plans and verified tasks so an interrupted install can pick up again.
+///
+/// Each live session holds an exclusive lock on its file, sessions sharing a name at the same
+/// time get files of their own.
+#[derive(Debug, Clone)]
+pub struct DownloadJournal {
+    root: PathBuf,
+}
+
+#[derive(Debug)]
+pub(crate) struct JournalSession {
+    path: PathBuf,
+    file: Mutex<File>,
+    verified: Mutex<HashMap<PathBuf, VerifiedFile>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct VerifiedFile {
+    expected_size: Option<u64>,
+    digests: Vec<FileDigest>,
+    len: u64,
+    modified_ns: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "record", rename_all = "snake_case")]
+enum JournalRecord {
+    Session {
+        name: String,
+    },
+    Task {
+        url: String,
+        #[serde(default)]
+        mirrors: Vec<String>,
+        path: PathBuf,
+        expected_size: Option<u64>,
+        digests: Vec<FileDigest>,
+        #[serde(default)]
+        priority: DownloadPriority,
+        #[serde(default)]
+        decompression: Option<Compression>,
+    },
+    Verified {
+        path: PathBuf,
+        expected_size: Option<u64>,
+        digests: Vec<FileDigest>,
+        len: u64,
+        modified_ns: u64,
+    },
+}
+
+impl DownloadJournal {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Plans of named sessions that never finished cleanly, skipping the ones still running.
+    pub fn unfinished_plans(&self) -> Result<Vec<DownloadPlan>> {
+        let entries = match std::fs::read_dir(&self.root) {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(error) => {
+                return Err(error).with_context(|| {
+                    format!("read download journal failed: {}", self.root.display())
+                });
+            }
+        };
+
+        let mut plans = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().and_then(|extension| extension.to_str()) != Some(JOURNAL_EXTENSION)
+            {
+                continue;
+            }
+
+            let file = File::open(&path)
+                .with_context(|| format!("open download journal failed: {}", path.display()))?;
+            match file.try_lock_shared() {
+                Ok(()) => {}
+                Err(TryLockError::WouldBlock) => continue,
+                Err(TryLockError::Error(error)) => {
+                    return Err(error).with_context(|| {
+                        format!("lock download journal failed: {}", path.display())
+                    });
+                }
+            }
+
+            let mut name = None;
+            let mut tasks: Vec<DownloadTask> = Vec::new();
+            for record in read_records(&file, &path)? {
+                match record {
+                    JournalRecord::Session { name: session_name } => name = Some(session_name),
+                    JournalRecord::Task {
+                        url,
+                        mirrors,
+                        path,
+                        expected_size,
+                        digests,
+                        priority,
+                        decompression,
+                    } => {
+                        tasks.retain(|task| task.path != path);
+                        let mut task = DownloadTask::new(url, path, expected_size, None)
+                            .with_mirrors(mirrors)
+                            .with_digests(digests)
+                            .with_priority(priority);
+                        task.decompression = decompression;
+                        tasks.push(task);
+                    }
+                    JournalRecord::Verified { .. } => {}
+                }
+            }
+
+            if let Some(name) = name {
+                plans.push(DownloadPlan::named(
+                    name,
+                    DownloadExecutionPolicy::ServiceDefault,
+                    tasks,
+                )?);
+            }
+        }
+
+        plans.sort_by(|left, right| left.session.name.cmp(&right.session.name));
+        Ok(plans)
+    }
+
+    pub(crate) fn open_session(&self, name: &str) -> Result<JournalSession> {
+        std::fs::create_dir_all(&self.root)
+            .with_context(|| format!("create download journal failed: {}", self.root.display()))?;
+        let stem = journal_file_stem(name);
+        for slot in 0..MAX_JOURNAL_SLOTS {
+            let path = match slot {
+                0 => self.root.join(format!("{stem}.{JOURNAL_EXTENSION}")),
+                slot => self.root.join(format!("{stem}.{slot}.{JOURNAL_EXTENSION}")),
+            };
+            let file = OpenOptions::new()
+                .create(true)
+                .read(true)
+                .append(true)
+                .open(&path)
+                .with_context(|| format!("open download journal failed: {}", path.display()))?;
+            match file.try_lock() {
+                Ok(()) => return JournalSession::open(name, path, file),
+                Err(TryLockError::WouldBlock) => continue,
+                Err(TryLockError::Error(error)) => {
+                    return Err(error).with_context(|| {
+                        format!("lock download journal failed: {}", path.display())
+                    });
+                }
+            }
+        }
+
+        bail!("too many running download sessions named '{name}'")
+    }
+}
+
+impl JournalSession {
+    fn open(name: &str, path: PathBuf, file: File) -> Result<Self> {
+        let mut verified = HashMap::new();
+        let records = read_records(&file, &path)?;
+        let exists = !records.is_empty();
+        for record in records {
+            if let JournalRecord::Verified {
+                path,
+                expected_size,
+                digests,
+                len,
+                modified_ns,
+            } = record
+            {
+                verified.insert(
+                    path,
+                    VerifiedFile {
+                        expected_size,
+                        digests,
+                        len,
+                        modified_ns,
+                    },
+                );
+            }
+        }
+
+        let session = JournalSession {
+            path,
+            file: Mutex::new(file),
+            verified: Mutex::new(verified),
+        };
+        if !exists {
+            session.append(&JournalRecord::Session {
+                name: name.to_owned(),
+            })?;
+        }
+
+        Ok(session)
+    }
+
+    /// Checks the recorded fingerprint instead of hashing the target again.
+    pub(crate) fn is_verified(&self, task: &DownloadTask) -> bool {
+        let verified = self.verified.lock().expect("journal mutex poisoned");
+        let Some(recorded) = verified.get(&task.path) else {
+            return false;
+        };
+        if recorded.expected_size != task.expected_size || recorded.digests != task.digests {
+            return false;
+        }
+
+        file_fingerprint(&task.path).is_some_and(|(len, modified_ns)| {
+            recorded.len == len && recorded.modified_ns == modified_ns
+        })
+    }
+
+    pub(crate) fn record_task(&self, task: &DownloadTask) -> Result<()> {
+        self.append(&JournalRecord::Task {
+            url: task.url.clone(),
+            mirrors: task.mirrors.clone(),
+            path: task.path.clone(),
+            expected_size: task.expected_size,
+            digests: task.digests.clone(),
+            priority: task.priority,
+            decompression: task.decompression,
+        })
+    }
+
+    pub(crate) fn record_verified(&self, task: &DownloadTask) -> Result<()> {
+        if task.expected_size.is_none() && task.digests.is_empty() {
+            return Ok(());
+        }
+        let Some((len, modified_ns)) = file_fingerprint(&task.path) else {
+            return Ok(());
+        };
+
+        let verified = VerifiedFile {
+            expected_size: task.expected_size,
+            digests: task.digests.clone(),
+            len,
+            modified_ns,
+        };
+        self.append(&JournalRecord::Verified {
+            path: task.path.clone(),
+            expected_size: verified.expected_size,
+            digests: verified.digests.clone(),
+            len,
+            modified_ns,
+        })?;
+        self.verified
+            .lock()
+            .expect("journal mutex poisoned")
+            .insert(task.path.clone(), verified);
+        Ok(())
+    }
+
+    pub(crate) fn finish(&self) -> Result<()> {
+        match std::fs::remove_file(&self.path) {
+            Ok(()) => Ok(()),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(error) => Err(error).with_context(|| {
+                format!("remove download journal failed: {}", self.path.display())
+            }),
+        }
+    }
+
+    fn append(&self, record: &JournalRecord) -> Result<()> {
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+        self.file
+            .lock()
+            .expect("journal mutex poisoned")
+            .write_all(&line)
+            .with_context(|| format!("write download journal failed: {}", self.path.display()))
+    }
+}
+
+// Reads through the locked handle, other handles may not read a locked file on every platform.
+fn read_records(file: &File, path: &Path) -> Result<Vec<JournalRecord>> {
+    let mut records = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let line =
+            line.with_context(|| format!("read download journal failed: {}", path.display()))?;
+        // A crash can leave a torn last line behind, everything before it is still usable.
+        match serde_json::from_str(&line) {
+            Ok(record) => records.push(record),
+            Err(_) => break,
+        }
+    }
+
+    Ok(records)
+}
+
+fn file_fingerprint(path: &Path) -> Option<(u64, u64)> {
+    let metadata = std::fs::metadata(path).ok()?;
+    let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    Some((metadata.len(), modified.as_nanos() as u64))
+}
+
+fn journal_file_stem(name: &str) -> String {
+    let readable = name
+        .chars()
+        .map(|char| {
+            if char.is_ascii_alphanumeric() || matches!(char, '-' | '_' | '.') {
+                char
+            } else {
+                '_'
+            }
+        })
+        .take(48)
+        .collect::<String>();
+    let digest = Sha1::from(name).digest().to_string();
+    format!("{readable}-{}", &digest[..12])
+}
+
+#[test]
+fn test_sessions_sharing_a_name_keep_separate_journals() {
+    let root = std::env::temp_dir().join(format!("elemental-journal-slots-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    let journal = DownloadJournal::new(&root);
+    let task =
+        |name: &str| DownloadTask::new("http://127.0.0.1:9/a.jar", root.join(name), None, None);
+
+    let first = journal.open_session("1.20.1").unwrap();
+    let second = journal.open_session("1.20.1").unwrap();
+    assert_ne!(first.path, second.path);
+    first.record_task(&task("a.jar")).unwrap();
+    second.record_task(&task("b.jar")).unwrap();
+    // Running sessions are not offered for resuming.
+    assert!(journal.unfinished_plans().unwrap().is_empty());
+
+    first.finish().unwrap();
+    drop(first);
+    assert!(second.path.exists());
+    drop(second);
+    let plans = journal.unfinished_plans().unwrap();
+    assert_eq!(plans.len(), 1);
+    assert_eq!(plans[0].session.name.as_deref(), Some("1.20.1"));
+    assert_eq!(plans[0].tasks, vec![task("b.jar")]);
+
+    // A later session of that name writes to the first free file again.
+    let resumed = journal.open_session("1.20.1").unwrap();
+    assert_eq!(
+        resumed.path,
+        root.join(format!("{}.journal", journal_file_stem("1.20.1")))
+    );
+    resumed.finish().unwrap();
+    let _ = std::fs::remove_dir_all(&root);
+}
@@ -1,34 +1,264 @@
 use anyhow::{Context, Result};
 use async_trait::async_trait;
-use std::path::{Path, PathBuf};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, SystemTime},
+};
 
 use super::{
-    Materializer, NoCachedMaterializer, StagedDownload, cleanup_file, create_temp_output_file,
+    Materializer, NoCachedMaterializer, StagedDownload, cleanup_file, create_staging_file,
     replace_file,
 };
-use crate::downloader::{task::DownloadTask, validation::target_matches_task};
+use crate::downloader::{
+    task::{DigestAlgorithm, DownloadTask},
+    validation::target_matches_task,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CacheLinkStrategy {
+    /// Fails when the cache and the target live on different filesystems.
+    HardlinkOnly,
+    #[default]
+    HardlinkReflinkCopy,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CacheLimits {
+    pub max_bytes: Option<u64>,
+    pub max_age: Option<Duration>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MaterializerCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub bytes_saved: u64,
+    pub hardlinks: u64,
+    pub reflinks: u64,
+    pub copies: u64,
+    pub evicted_files: u64,
+    pub evicted_bytes: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CacheEvictionReport {
+    pub scanned_files: usize,
+    pub retained_bytes: u64,
+    pub evicted: Vec<PathBuf>,
+    pub evicted_bytes: u64,
+    pub skipped_linked: usize,
+}
 
 #[derive(Debug, Clone)]
 pub struct HardlinkCachedMaterializer {
     cache_root: PathBuf,
+    link_strategy: CacheLinkStrategy,
+    limits: CacheLimits,
+    state: Arc<CacheState>,
+}
+
+#[derive(Debug, Default)]
+struct CacheState {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    bytes_saved: AtomicU64,
+    hardlinks: AtomicU64,
+    reflinks: AtomicU64,
+    copies: AtomicU64,
+    evicted_files: AtomicU64,
+    evicted_bytes: AtomicU64,
+    last_used: Mutex<HashMap<PathBuf, SystemTime>>,
+    // `None` until the first scan.
+    usage: Mutex<Option<CacheUsage>>,
+    evicting: tokio::sync::Mutex<()>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct CacheUsage {
+    /// As of the last scan plus the commits since.
+    retained_bytes: u64,
+    /// Linked entries can keep the cache over its limit, so commits only trigger another scan
+    /// once it grew past what the last one left behind.
+    evict_above: u64,
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    path: PathBuf,
+    len: u64,
+    last_used: SystemTime,
+    linked: bool,
 }
 
 impl HardlinkCachedMaterializer {
     pub fn new(cache_root: impl Into<PathBuf>) -> Self {
         Self {
             cache_root: cache_root.into(),
+            link_strategy: CacheLinkStrategy::default(),
+            limits: CacheLimits::default(),
+            state: Arc::new(CacheState::default()),
+        }
+    }
+
+    pub fn with_link_strategy(mut self, link_strategy: CacheLinkStrategy) -> Self {
+        self.link_strategy = link_strategy;
+        self
+    }
+
+    pub fn with_limits(mut self, limits: CacheLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn cache_root(&self) -> &Path {
+        &self.cache_root
+    }
+
+    pub fn stats(&self) -> MaterializerCacheStats {
+        let state = &self.state;
+        MaterializerCacheStats {
+            hits: state.hits.load(Ordering::Relaxed),
+            misses: state.misses.load(Ordering::Relaxed),
+            bytes_saved: state.bytes_saved.load(Ordering::Relaxed),
+            hardlinks: state.hardlinks.load(Ordering::Relaxed),
+            reflinks: state.reflinks.load(Ordering::Relaxed),
+            copies: state.copies.load(Ordering::Relaxed),
+            evicted_files: state.evicted_files.load(Ordering::Relaxed),
+            evicted_bytes: state.evicted_bytes.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Applies the configured limits, least recently used entries go first.
+    pub async fn evict(&self) -> Result<CacheEvictionReport> {
+        let evicting = self.state.evicting.lock().await;
+        self.evict_locked(evicting).await
+    }
+
+    async fn evict_locked(
+        &self,
+        _evicting: tokio::sync::MutexGuard<'_, ()>,
+    ) -> Result<CacheEvictionReport> {
+        let cache_root = self.cache_root.clone();
+        let last_used = self
+            .state
+            .last_used
+            .lock()
+            .expect("cache state mutex poisoned")
+            .clone();
+        let mut entries = tokio::task::spawn_blocking(move || {
+            let mut entries = Vec::new();
+            scan_cache_entries(&cache_root, &last_used, &mut entries)?;
+            Ok::<_, anyhow::Error>(entries)
+        })
+        .await
+        .context("scan materializer cache failed")??;
+        entries.sort_by_key(|entry| entry.last_used);
+
+        let mut report = CacheEvictionReport {
+            scanned_files: entries.len(),
+            retained_bytes: entries.iter().map(|entry| entry.len).sum(),
+            ..CacheEvictionReport::default()
+        };
+        let expires_before = self
+            .limits
+            .max_age
+            .and_then(|max_age| SystemTime::now().checked_sub(max_age));
+
+        for entry in entries {
+            let expired =
+                expires_before.is_some_and(|expires_before| entry.last_used < expires_before);
+            let oversized = self
+                .limits
+                .max_bytes
+                .is_some_and(|max_bytes| report.retained_bytes > max_bytes);
+            if !expired && !oversized {
+                continue;
+            }
+            // Another link means a live instance still uses this content.
+            if entry.linked {
+                report.skipped_linked += 1;
+                continue;
+            }
+
+            match tokio::fs::remove_file(&entry.path).await {
+                Ok(()) => {}
+                Err(error) if error.kind() == std::io::ErrorKind::NotFound => {}
+                Err(error) => {
+                    return Err(error).with_context(|| {
+                        format!("evict cache entry failed: {}", entry.path.display())
+                    });
+                }
+            }
+            self.state
+                .last_used
+                .lock()
+                .expect("cache state mutex poisoned")
+                .remove(&entry.path);
+            report.retained_bytes -= entry.len;
+            report.evicted_bytes += entry.len;
+            report.evicted.push(entry.path);
+        }
+
+        self.state
+            .evicted_files
+            .fetch_add(report.evicted.len() as u64, Ordering::Relaxed);
+        self.state
+            .evicted_bytes
+            .fetch_add(report.evicted_bytes, Ordering::Relaxed);
+        let evict_above = match self.limits.max_bytes {
+            Some(max_bytes) => max_bytes.max(report.retained_bytes) + max_bytes / 10,
+            None => u64::MAX,
+        };
+        *self.state.usage.lock().expect("cache state mutex poisoned") = Some(CacheUsage {
+            retained_bytes: report.retained_bytes,
+            evict_above,
+        });
+        Ok(report)
+    }
+
+    /// Evicts once new content pushes the cache over its limits, the first commit scans it.
+    async fn evict_after_commit(&self, added_bytes: u64) {
+        if self.limits == CacheLimits::default() {
+            return;
+        }
+        let over_limits = match self
+            .state
+            .usage
+            .lock()
+            .expect("cache state mutex poisoned")
+            .as_mut()
+        {
+            Some(usage) => {
+                usage.retained_bytes += added_bytes;
+                usage.retained_bytes > usage.evict_above
+            }
+            None => true,
+        };
+        // A commit never fails over eviction, and a running eviction already covers it.
+        if over_limits && let Ok(evicting) = self.state.evicting.try_lock() {
+            let _ = self.evict_locked(evicting).await;
         }
     }
 
+    // The cache is content addressed by the strongest digest, SHA-1 keeps the original flat layout.
     fn cache_path_for_task(&self, task: &DownloadTask) -> Result<PathBuf> {
-        let sha1 = task
-            .sha1
-            .as_ref()
-            .context("hardlink cache requires task sha1")?;
-        let prefix = sha1
+        let digest = task
+            .strongest_digest()
+            .context("hardlink cache requires a task digest")?;
+        let prefix = digest
+            .hex
             .get(0..2)
-            .context("task sha1 is too short for cache path")?;
-        Ok(self.cache_root.join(prefix).join(sha1))
+            .context("task digest is too short for cache path")?;
+        let root = match digest.algorithm {
+            DigestAlgorithm::Sha1 => self.cache_root.clone(),
+            algorithm => self.cache_root.join(algorithm.name()),
+        };
+        Ok(root.join(prefix).join(&digest.hex))
     }
 
     async fn materialize_from_cache(&self, task: &DownloadTask, cache_path: &Path) -> Result<()> {
@@ -43,7 +273,46 @@ impl HardlinkCachedMaterializer {
             tokio::fs::remove_file(&task.path).await?;
         }
 
-        tokio::fs::hard_link(cache_path, &task.path).await?;
+        let now = SystemTime::now();
+        self.state
+            .last_used
+            .lock()
+            .expect("cache state mutex poisoned")
+            .insert(cache_path.to_path_buf(), now);
+        // The access time carries the recency over to later processes, the modification time
+        // is left alone as it is shared with every linked target.
+        let _ = std::fs::File::open(cache_path)
+            .and_then(|file| file.set_times(std::fs::FileTimes::new().set_accessed(now)));
+
+        let hardlink = tokio::fs::hard_link(cache_path, &task.path).await;
+        if hardlink.is_ok() {
+            self.state.hardlinks.fetch_add(1, Ordering::Relaxed);
+            return Ok(());
+        }
+        if self.link_strategy == CacheLinkStrategy::HardlinkOnly {
+            return hardlink.map_err(Into::into);
+        }
+
+        let source = cache_path.to_path_buf();
+        let target = task.path.clone();
+        let reflinked = tokio::task::spawn_blocking(move || reflink_copy::reflink(source, target))
+            .await
+            .is_ok_and(|result| result.is_ok());
+        if reflinked {
+            self.state.reflinks.fetch_add(1, Ordering::Relaxed);
+            return Ok(());
+        }
+
+        tokio::fs::copy(cache_path, &task.path)
+            .await
+            .with_context(|| {
+                format!(
+                    "copy cache entry failed: {} -> {}",
+                    cache_path.display(),
+                    task.path.display()
+                )
+            })?;
+        self.state.copies.fetch_add(1, Ordering::Relaxed);
         Ok(())
     }
 }
@@ -65,11 +334,14 @@ impl Materializer for HardlinkCachedMaterializer {
         }
 
         self.materialize_from_cache(task, &cache_path).await?;
+        self.state.hits.fetch_add(1, Ordering::Relaxed);
+        let len = tokio::fs::metadata(&cache_path).await?.len();
+        self.state.bytes_saved.fetch_add(len, Ordering::Relaxed);
         Ok(true)
     }
 
     async fn create_staging(&self, task: &DownloadTask) -> Result<StagedDownload> {
-        if task.sha1.is_none() {
+        if task.digests.is_empty() {
             return NoCachedMaterializer.create_staging(task).await;
         }
 
@@ -82,7 +354,7 @@ impl Materializer for HardlinkCachedMaterializer {
             .context("Can't get cache file name")?
             .to_string_lossy()
             .to_string();
-        create_temp_output_file(parent, file_name).await
+        create_staging_file(parent, file_name, task).await
     }
 
     async fn commit(&self, staged: StagedDownload, task: &DownloadTask) -> Result<()> {
@@ -97,12 +369,96 @@ impl Materializer for HardlinkCachedMaterializer {
             return Ok(());
         }
 
+        // Resolve runs more than once per task, so misses are counted when new content lands.
+        self.state.misses.fetch_add(1, Ordering::Relaxed);
         replace_file(&staged.path, &cache_path).await?;
-        self.materialize_from_cache(task, &cache_path).await
+        self.materialize_from_cache(task, &cache_path).await?;
+        let len = tokio::fs::metadata(&cache_path).await?.len();
+        self.evict_after_commit(len).await;
+        Ok(())
     }
 
     async fn abort(&self, staged: StagedDownload) -> Result<()> {
         cleanup_file(&staged.path).await;
         Ok(())
     }
+
+    fn cache_stats(&self) -> Option<MaterializerCacheStats> {
+        Some(self.stats())
+    }
+}
+
+fn scan_cache_entries(
+    directory: &Path,
+    last_used: &HashMap<PathBuf, SystemTime>,
+    entries: &mut Vec<CacheEntry>,
+) -> Result<()> {
+    let read_dir = match std::fs::read_dir(directory) {
+        Ok(read_dir) => read_dir,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(error) => {
+            return Err(error)
+                .with_context(|| format!("read cache directory failed: {}", directory.display()));
+        }
+    };
+
+    for entry in read_dir {
+        let entry = entry?;
+        let path = entry.path();
+        // Staging files and their range sidecars are dot files owned by running downloads.
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+
+        let metadata = entry.metadata()?;
+        if metadata.is_dir() {
+            scan_cache_entries(&path, last_used, entries)?;
+            continue;
+        }
+
+        let touched = [metadata.modified(), metadata.accessed()]
+            .into_iter()
+            .filter_map(Result::ok)
+            .chain(last_used.get(&path).copied())
+            .max()
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+        entries.push(CacheEntry {
+            last_used: touched,
+            len: metadata.len(),
+            linked: is_linked(&path, &metadata),
+            path,
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(unix)]
+fn is_linked(_path: &Path, metadata: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    metadata.nlink() > 1
+}
+
+#[cfg(windows)]
+fn is_linked(path: &Path, _metadata: &std::fs::Metadata) -> bool {
+    use std::os::windows::io::AsRawHandle;
+    use windows_sys::Win32::Storage::FileSystem::{
+        BY_HANDLE_FILE_INFORMATION, GetFileInformationByHandle,
+    };
+
+    // An entry whose link count can't be read is treated as in use.
+    let Ok(file) = std::fs::File::open(path) else {
+        return true;
+    };
+    let mut information = BY_HANDLE_FILE_INFORMATION::default();
+    // SAFETY: the handle stays open for the duration of the call and `information` is writable.
+    let read = unsafe { GetFileInformationByHandle(file.as_raw_handle(), &mut information) };
+    read == 0 || information.nNumberOfLinks > 1
+}
+
+// Link counts are not exposed on this platform, so treat every entry as possibly in use.
+#[cfg(not(any(unix, windows)))]
+fn is_linked(_path: &Path, _metadata: &std::fs::Metadata) -> bool {
+    true
 }
@@ -1,7 +1,9 @@
 use anyhow::{Context, Result, bail};
 use async_trait::async_trait;
+use sha1_smol::Sha1;
 use std::{
     fmt,
+    fs::TryLockError,
     path::{Path, PathBuf},
     sync::atomic::{AtomicU64, Ordering},
 };
@@ -12,7 +14,10 @@ use super::task::DownloadTask;
 mod hardlink_cached;
 mod no_cached;
 
-pub use hardlink_cached::HardlinkCachedMaterializer;
+pub use hardlink_cached::{
+    CacheEvictionReport, CacheLimits, CacheLinkStrategy, HardlinkCachedMaterializer,
+    MaterializerCacheStats,
+};
 pub use no_cached::NoCachedMaterializer;
 
 static TEMP_FILE_COUNTER: AtomicU64 = AtomicU64::new(0);
@@ -21,6 +26,7 @@ static TEMP_FILE_COUNTER: AtomicU64 = AtomicU64::new(0);
 pub struct StagedDownload {
     pub path: PathBuf,
     pub file: Option<tokio::fs::File>,
+    pub resume_from: u64,
 }
 
 #[async_trait]
@@ -29,14 +35,42 @@ pub trait Materializer: fmt::Debug + Send + Sync {
     async fn create_staging(&self, task: &DownloadTask) -> Result<StagedDownload>;
     async fn commit(&self, staged: StagedDownload, task: &DownloadTask) -> Result<()>;
     async fn abort(&self, staged: StagedDownload) -> Result<()>;
+
+    fn cache_stats(&self) -> Option<MaterializerCacheStats> {
+        None
+    }
 }
 
-pub(super) async fn create_temp_output_file(
+pub(super) async fn create_staging_file(
     parent: &Path,
     file_name: String,
+    task: &DownloadTask,
 ) -> Result<StagedDownload> {
     create_dir_all(parent).await?;
 
+    // Staging files are keyed by task so an interrupted download can pick up where it stopped.
+    let staging_path = parent.join(format!(".{}.part.{}", file_name, staging_key(task)));
+    let file = std::fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .read(true)
+        .write(true)
+        .open(&staging_path)?;
+    match file.try_lock() {
+        Ok(()) => {
+            let resume_from = file.metadata()?.len();
+            Ok(StagedDownload {
+                path: staging_path,
+                file: Some(tokio::fs::File::from_std(file)),
+                resume_from,
+            })
+        }
+        Err(TryLockError::WouldBlock) => create_temp_output_file(parent, file_name).await,
+        Err(TryLockError::Error(error)) => Err(error.into()),
+    }
+}
+
+async fn create_temp_output_file(parent: &Path, file_name: String) -> Result<StagedDownload> {
     for _ in 0..32u32 {
         let suffix = TEMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed);
         let temp_path = parent.join(format!(
@@ -47,6 +81,7 @@ pub(super) async fn create_temp_output_file(
         ));
         match OpenOptions::new()
             .create_new(true)
+            .read(true)
             .write(true)
             .open(&temp_path)
             .await
@@ -55,6 +90,7 @@ pub(super) async fn create_temp_output_file(
                 return Ok(StagedDownload {
                     path: temp_path,
                     file: Some(file),
+                    resume_from: 0,
                 });
             }
             Err(error) if error.kind() == std::io::ErrorKind::AlreadyExists => continue,
@@ -68,6 +104,20 @@ pub(super) async fn create_temp_output_file(
     )
 }
 
+pub(crate) fn range_validator_path(staging_path: &Path) -> PathBuf {
+    let mut path = staging_path.as_os_str().to_owned();
+    path.push(".range");
+    PathBuf::from(path)
+}
+
+fn staging_key(task: &DownloadTask) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(task.url.as_bytes());
+    hasher.update(b"\n");
+    hasher.update(task.path.to_string_lossy().as_bytes());
+    hasher.digest().to_string()[..16].to_owned()
+}
+
 pub(super) async fn replace_file(source_path: &Path, target_path: &Path) -> Result<()> {
     create_dir_all(
         target_path
@@ -81,9 +131,11 @@ pub(super) async fn replace_file(source_path: &Path, target_path: &Path) -> Resu
     }
 
     tokio::fs::rename(source_path, target_path).await?;
+    let _ = tokio::fs::remove_file(range_validator_path(source_path)).await;
     Ok(())
 }
 
 pub(super) async fn cleanup_file(path: &Path) {
     let _ = tokio::fs::remove_file(path).await;
+    let _ = tokio::fs::remove_file(range_validator_path(path)).await;
 }
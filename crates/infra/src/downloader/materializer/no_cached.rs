@@ -1,7 +1,7 @@
 use anyhow::{Context, Result};
 use async_trait::async_trait;
 
-use super::{Materializer, StagedDownload, create_temp_output_file, replace_file};
+use super::{Materializer, StagedDownload, create_staging_file, replace_file};
 use crate::downloader::{task::DownloadTask, validation::target_matches_task};
 
 #[derive(Debug, Clone, Default)]
@@ -14,7 +14,7 @@ impl Materializer for NoCachedMaterializer {
     }
 
     async fn create_staging(&self, task: &DownloadTask) -> Result<StagedDownload> {
-        create_temp_output_file(
+        create_staging_file(
             task.path
                 .parent()
                 .context("Can't get target parent directory")?,
@@ -23,6 +23,7 @@ impl Materializer for NoCachedMaterializer {
                 .context("Can't get target file name")?
                 .to_string_lossy()
                 .to_string(),
+            task,
         )
         .await
     }
@@ -1,9 +1,15 @@
 pub(crate) mod anyhost;
 pub(crate) mod control;
 pub mod core;
+pub mod event;
+pub(crate) mod health;
+pub mod journal;
 pub mod materializer;
 pub mod plan;
+pub(crate) mod queue;
+pub mod scope;
 pub mod session;
 pub mod task;
+pub(crate) mod throttle;
 pub(crate) mod tracking;
 pub(crate) mod validation;
@@ -0,0 +1,236 @@
+use anyhow::{Context, Result, bail};
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap},
+    sync::{Arc, Mutex},
+};
+use tokio::sync::{Notify, OwnedSemaphorePermit, Semaphore};
+
+use super::{task::DownloadPriority, tracking::TaskId};
+
+/// A bounded session queue that hands out the highest priority task first, in enqueue order
+/// within one priority.
+#[derive(Debug)]
+pub(crate) struct TaskQueue<T> {
+    state: Mutex<QueueState<T>>,
+    available: Notify,
+    capacity: Arc<Semaphore>,
+}
+
+#[derive(Debug)]
+struct QueueState<T> {
+    // Re-prioritized or removed tasks leave stale heap entries behind, `slots` is authoritative.
+    heap: BinaryHeap<QueueEntry>,
+    slots: HashMap<u64, QueueSlot<T>>,
+    next_sequence: u64,
+    closed: bool,
+}
+
+#[derive(Debug)]
+struct QueueSlot<T> {
+    task_id: TaskId,
+    priority: DownloadPriority,
+    item: T,
+    _permit: OwnedSemaphorePermit,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct QueueEntry {
+    priority: DownloadPriority,
+    sequence: u64,
+}
+
+impl Ord for QueueEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.sequence.cmp(&self.sequence))
+    }
+}
+
+impl PartialOrd for QueueEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> TaskQueue<T> {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            state: Mutex::new(QueueState {
+                heap: BinaryHeap::new(),
+                slots: HashMap::new(),
+                next_sequence: 0,
+                closed: false,
+            }),
+            available: Notify::new(),
+            capacity: Arc::new(Semaphore::new(capacity)),
+        }
+    }
+
+    /// Waits for a free slot when the queue is full.
+    pub(crate) async fn push(
+        &self,
+        task_id: TaskId,
+        priority: DownloadPriority,
+        item: T,
+    ) -> Result<()> {
+        let permit = self
+            .capacity
+            .clone()
+            .acquire_owned()
+            .await
+            .context("download queue is closed")?;
+
+        {
+            let mut state = self.state.lock().expect("task queue mutex poisoned");
+            if state.closed {
+                bail!("download queue is closed");
+            }
+
+            let sequence = state.next_sequence;
+            state.next_sequence += 1;
+            state.heap.push(QueueEntry { priority, sequence });
+            state.slots.insert(
+                sequence,
+                QueueSlot {
+                    task_id,
+                    priority,
+                    item,
+                    _permit: permit,
+                },
+            );
+        }
+
+        self.available.notify_one();
+        Ok(())
+    }
+
+    /// Returns `None` once the queue is closed and drained.
+    pub(crate) async fn pop(&self) -> Option<T> {
+        loop {
+            let notified = self.available.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+
+            {
+                let mut state = self.state.lock().expect("task queue mutex poisoned");
+                while let Some(entry) = state.heap.pop() {
+                    let current = state
+                        .slots
+                        .get(&entry.sequence)
+                        .is_some_and(|slot| slot.priority == entry.priority);
+                    if !current {
+                        continue;
+                    }
+
+                    let slot = state
+                        .slots
+                        .remove(&entry.sequence)
+                        .expect("queued slot must exist");
+                    // Another worker may be waiting for the entries that are left.
+                    if !state.slots.is_empty() {
+                        self.available.notify_one();
+                    }
+                    return Some(slot.item);
+                }
+
+                if state.closed {
+                    return None;
+                }
+            }
+
+            notified.await;
+        }
+    }
+
+    pub(crate) fn close(&self) {
+        self.state.lock().expect("task queue mutex poisoned").closed = true;
+        self.capacity.close();
+        self.available.notify_waiters();
+    }
+
+    /// Moves every queued task with `task_id` to `priority`, returns whether one was queued.
+    pub(crate) fn set_priority(&self, task_id: &TaskId, priority: DownloadPriority) -> bool {
+        let mut state = self.state.lock().expect("task queue mutex poisoned");
+        let QueueState { heap, slots, .. } = &mut *state;
+        let mut found = false;
+        for (sequence, slot) in slots.iter_mut() {
+            if slot.task_id != *task_id {
+                continue;
+            }
+
+            found = true;
+            if slot.priority != priority {
+                slot.priority = priority;
+                heap.push(QueueEntry {
+                    priority,
+                    sequence: *sequence,
+                });
+            }
+        }
+
+        found
+    }
+
+    /// Takes every queued task with `task_id` out of the queue.
+    pub(crate) fn remove(&self, task_id: &TaskId) -> Vec<T> {
+        let mut state = self.state.lock().expect("task queue mutex poisoned");
+        let mut sequences = state
+            .slots
+            .iter()
+            .filter(|(_, slot)| slot.task_id == *task_id)
+            .map(|(sequence, _)| *sequence)
+            .collect::<Vec<u64>>();
+        sequences.sort_unstable();
+        sequences
+            .into_iter()
+            .filter_map(|sequence| state.slots.remove(&sequence))
+            .map(|slot| slot.item)
+            .collect()
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_queue_orders_by_priority_then_enqueue_order() {
+    let queue = TaskQueue::new(8);
+    queue
+        .push("asset".to_owned(), DownloadPriority::Low, "asset")
+        .await
+        .unwrap();
+    queue
+        .push(
+            "library-a".to_owned(),
+            DownloadPriority::Normal,
+            "library-a",
+        )
+        .await
+        .unwrap();
+    queue
+        .push(
+            "library-b".to_owned(),
+            DownloadPriority::Normal,
+            "library-b",
+        )
+        .await
+        .unwrap();
+    queue
+        .push("client".to_owned(), DownloadPriority::High, "client")
+        .await
+        .unwrap();
+    queue
+        .push("dropped".to_owned(), DownloadPriority::Critical, "dropped")
+        .await
+        .unwrap();
+
+    assert!(queue.set_priority(&"asset".to_owned(), DownloadPriority::Critical));
+    assert_eq!(queue.remove(&"dropped".to_owned()), vec!["dropped"]);
+    assert!(!queue.set_priority(&"dropped".to_owned(), DownloadPriority::Low));
+    queue.close();
+
+    let mut order = Vec::new();
+    while let Some(item) = queue.pop().await {
+        order.push(item);
+    }
+    assert_eq!(order, vec!["asset", "client", "library-a", "library-b"]);
+}
@@ -0,0 +1,300 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+};
+
+use tokio::sync::broadcast::{self, error::TryRecvError};
+
+use super::{
+    core::ElementalDownloader,
+    event::{DownloadEvent, DownloadEventKind},
+    session::DownloadSessionSnapshot,
+    task::SessionId,
+    tracking::TaskId,
+};
+
+tokio::task_local! {
+    static CURRENT_SCOPE: SessionScope;
+}
+
+/// The sessions created while a future runs, used to pick its events out of the
+/// downloader-wide broadcast.
+#[derive(Debug, Clone, Default)]
+pub struct SessionScope {
+    state: Arc<Mutex<SessionScopeState>>,
+}
+
+#[derive(Debug, Default)]
+struct SessionScopeState {
+    sessions: Vec<SessionId>,
+    // Sessions are removed once they finish, so their last snapshot is kept for resyncing.
+    finished: HashMap<SessionId, DownloadSessionSnapshot>,
+}
+
+impl SessionScope {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Runs `future` with every session it creates on this task recorded in the scope.
+    pub async fn run<F: Future>(&self, future: F) -> F::Output {
+        CURRENT_SCOPE.scope(self.clone(), future).await
+    }
+
+    pub fn contains(&self, session_id: SessionId) -> bool {
+        self.state
+            .lock()
+            .expect("session scope mutex poisoned")
+            .sessions
+            .contains(&session_id)
+    }
+
+    pub fn session_ids(&self) -> Vec<SessionId> {
+        self.state
+            .lock()
+            .expect("session scope mutex poisoned")
+            .sessions
+            .clone()
+    }
+
+    pub fn finished_snapshot(&self, session_id: SessionId) -> Option<DownloadSessionSnapshot> {
+        self.state
+            .lock()
+            .expect("session scope mutex poisoned")
+            .finished
+            .get(&session_id)
+            .cloned()
+    }
+
+    pub(crate) fn current() -> Option<Self> {
+        CURRENT_SCOPE.try_with(Clone::clone).ok()
+    }
+
+    pub(crate) fn record(&self, session_id: SessionId) {
+        self.state
+            .lock()
+            .expect("session scope mutex poisoned")
+            .sessions
+            .push(session_id);
+    }
+
+    pub(crate) fn record_finished(&self, snapshot: DownloadSessionSnapshot) {
+        self.state
+            .lock()
+            .expect("session scope mutex poisoned")
+            .finished
+            .insert(snapshot.session_id, snapshot);
+    }
+}
+
+/// Forwards the events of a scope's sessions, keeping only the latest progress of a task within
+/// each batch and replaying terminal events from session snapshots when the receiver lagged.
+pub struct ScopedEventForwarder<F> {
+    scope: SessionScope,
+    on_event: F,
+    terminated: HashSet<TaskId>,
+    finished: HashSet<SessionId>,
+}
+
+impl<F> ScopedEventForwarder<F>
+where
+    F: FnMut(DownloadEvent),
+{
+    pub fn new(scope: SessionScope, on_event: F) -> Self {
+        Self {
+            scope,
+            on_event,
+            terminated: HashSet::new(),
+            finished: HashSet::new(),
+        }
+    }
+
+    /// Forwards `first` along with every event already buffered in `receiver`.
+    pub async fn forward_pending(
+        &mut self,
+        downloader: &ElementalDownloader,
+        receiver: &mut broadcast::Receiver<DownloadEvent>,
+        first: Option<DownloadEvent>,
+    ) {
+        let mut events = Vec::from_iter(first);
+        let lagged = loop {
+            match receiver.try_recv() {
+                Ok(event) => events.push(event),
+                Err(TryRecvError::Lagged(_)) => break true,
+                Err(TryRecvError::Empty | TryRecvError::Closed) => break false,
+            }
+        };
+
+        self.forward(events);
+        if lagged {
+            self.resync(downloader).await;
+        }
+    }
+
+    /// Emits the completions, failures and cancellations not forwarded yet, and the finish of
+    /// sessions that were already removed.
+    pub async fn resync(&mut self, downloader: &ElementalDownloader) {
+        for session_id in self.scope.session_ids() {
+            let (snapshot, finished) = match self.scope.finished_snapshot(session_id) {
+                Some(snapshot) => (snapshot, true),
+                None => match downloader.session_snapshot(session_id).await {
+                    Ok(snapshot) => (snapshot, false),
+                    Err(_) => continue,
+                },
+            };
+            let event = |kind| DownloadEvent {
+                session_id,
+                session_name: snapshot.session_name.clone(),
+                kind,
+            };
+
+            for task_id in &snapshot.completed_task_ids {
+                self.emit(event(DownloadEventKind::Completed {
+                    task_id: task_id.clone(),
+                }));
+            }
+            for failure in &snapshot.failures {
+                self.emit(event(DownloadEventKind::Failed {
+                    task_id: failure.task_id.clone(),
+                    error: failure.error.clone(),
+                }));
+            }
+            for task_id in &snapshot.cancelled_task_ids {
+                self.emit(event(DownloadEventKind::Cancelled {
+                    task_id: task_id.clone(),
+                }));
+            }
+            if finished {
+                self.emit(event(DownloadEventKind::SessionFinished {
+                    downloaded: snapshot.downloaded,
+                    skipped: snapshot.skipped,
+                    failed: snapshot.failed,
+                    cancelled: snapshot.cancelled,
+                }));
+            }
+        }
+    }
+
+    fn forward(&mut self, events: Vec<DownloadEvent>) {
+        let events = events
+            .into_iter()
+            .filter(|event| self.scope.contains(event.session_id))
+            .collect::<Vec<DownloadEvent>>();
+        let mut latest_progress = HashMap::new();
+        for (index, event) in events.iter().enumerate() {
+            if let DownloadEventKind::Progress { task_id, .. } = &event.kind {
+                latest_progress.insert(task_id.clone(), index);
+            }
+        }
+
+        for (index, event) in events.into_iter().enumerate() {
+            if let DownloadEventKind::Progress { task_id, .. } = &event.kind
+                && latest_progress.get(task_id) != Some(&index)
+            {
+                continue;
+            }
+            self.emit(event);
+        }
+    }
+
+    fn emit(&mut self, event: DownloadEvent) {
+        let first = match &event.kind {
+            DownloadEventKind::Completed { task_id }
+            | DownloadEventKind::Failed { task_id, .. }
+            | DownloadEventKind::Cancelled { task_id } => self.terminated.insert(task_id.clone()),
+            DownloadEventKind::SessionFinished { .. } => self.finished.insert(event.session_id),
+            _ => true,
+        };
+        if first {
+            (self.on_event)(event);
+        }
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_scoped_events_skip_other_sessions_and_resync_terminal_events() {
+    use super::{
+        core::DownloadExecutionPolicy,
+        task::{DownloadPlan, DownloadTask},
+    };
+
+    let root = std::env::temp_dir().join(format!("elemental-scope-{}", std::process::id()));
+    let source = root.join("repository").join("library.jar");
+    let _ = tokio::fs::remove_dir_all(&root).await;
+    tokio::fs::create_dir_all(source.parent().unwrap())
+        .await
+        .unwrap();
+    tokio::fs::write(&source, b"local library").await.unwrap();
+    let url = reqwest::Url::from_file_path(&source).unwrap().to_string();
+    let task = |name: &str| DownloadTask::new(url.clone(), root.join(name), Some(13), None);
+
+    let downloader = ElementalDownloader::new();
+    let mut receiver = downloader.subscribe();
+    let scope = SessionScope::new();
+    scope
+        .run(
+            downloader.run_plan(
+                DownloadPlan::named(
+                    "scoped",
+                    DownloadExecutionPolicy::ServiceDefault,
+                    vec![task("a.jar")],
+                )
+                .unwrap(),
+            ),
+        )
+        .await
+        .unwrap();
+    downloader
+        .run_plan(
+            DownloadPlan::named(
+                "other",
+                DownloadExecutionPolicy::ServiceDefault,
+                vec![task("b.jar")],
+            )
+            .unwrap(),
+        )
+        .await
+        .unwrap();
+    let [session_id] = scope.session_ids()[..] else {
+        panic!("expected one scoped session");
+    };
+    assert_eq!(
+        scope
+            .finished_snapshot(session_id)
+            .unwrap()
+            .completed_task_ids
+            .len(),
+        1
+    );
+
+    let mut forwarded = Vec::new();
+    let mut forwarder = ScopedEventForwarder::new(scope.clone(), |event| forwarded.push(event));
+    forwarder
+        .forward_pending(&downloader, &mut receiver, None)
+        .await;
+    forwarder.resync(&downloader).await;
+    drop(forwarder);
+    assert!(forwarded.iter().all(|event| event.session_id == session_id));
+    let terminal = |events: &[DownloadEvent]| {
+        events
+            .iter()
+            .filter(|event| {
+                matches!(
+                    event.kind,
+                    DownloadEventKind::Completed { .. } | DownloadEventKind::SessionFinished { .. }
+                )
+            })
+            .count()
+    };
+    assert_eq!(terminal(&forwarded), 2);
+
+    // A receiver that saw nothing still gets the completion and the finish from the snapshot.
+    let mut replayed = Vec::new();
+    ScopedEventForwarder::new(scope, |event| replayed.push(event))
+        .resync(&downloader)
+        .await;
+    assert_eq!(terminal(&replayed), 2);
+    assert_eq!(replayed.len(), 2);
+
+    let _ = tokio::fs::remove_dir_all(&root).await;
+}
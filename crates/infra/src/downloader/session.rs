@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use super::{task::SessionId, tracking::TaskId};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -6,6 +8,19 @@ pub struct TaskExecutionFailure {
     pub error: String,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostHealthSnapshot {
+    pub host: String,
+    pub successes: usize,
+    pub failures: usize,
+    /// Responses from this host that asked the session to slow down.
+    pub throttled: usize,
+    /// The delay the host last asked for.
+    pub retry_after: Option<Duration>,
+    /// Time left before requests to this host resume.
+    pub backoff_remaining: Option<Duration>,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct DownloadSessionSnapshot {
     pub session_id: SessionId,
@@ -19,6 +34,8 @@ pub struct DownloadSessionSnapshot {
     pub pending: usize,
     pub bytes_per_second: usize,
     pub is_closed: bool,
+    pub completed_task_ids: Vec<TaskId>,
     pub failures: Vec<TaskExecutionFailure>,
     pub cancelled_task_ids: Vec<TaskId>,
+    pub host_health: Vec<HostHealthSnapshot>,
 }
@@ -1,14 +1,93 @@
 use anyhow::{Result, bail};
-use std::{num::NonZeroUsize, path::PathBuf};
+use serde::{Deserialize, Serialize};
+use std::{fmt, num::NonZeroUsize, path::PathBuf};
+
+use crate::compression::Compression;
 
 pub type SessionId = u64;
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub struct DownloadTask {
     pub url: String,
+    pub mirrors: Vec<String>,
     pub path: PathBuf,
     pub expected_size: Option<u64>,
-    pub sha1: Option<String>,
+    pub digests: Vec<FileDigest>,
+    pub priority: DownloadPriority,
+    /// Decodes the transferred bytes before they reach `path`, `expected_size` and `digests`
+    /// then describe the decoded file.
+    pub decompression: Option<Compression>,
+}
+
+/// Workers of a session pick higher priorities first, ties keep their enqueue order.
+#[derive(
+    Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord, Default, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum DownloadPriority {
+    Low,
+    #[default]
+    Normal,
+    High,
+    Critical,
+}
+
+/// Ordered from weakest to strongest.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DigestAlgorithm {
+    Md5,
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileDigest {
+    pub algorithm: DigestAlgorithm,
+    pub hex: String,
+}
+
+impl DigestAlgorithm {
+    pub fn name(self) -> &'static str {
+        match self {
+            DigestAlgorithm::Md5 => "md5",
+            DigestAlgorithm::Sha1 => "sha1",
+            DigestAlgorithm::Sha256 => "sha256",
+            DigestAlgorithm::Sha512 => "sha512",
+        }
+    }
+}
+
+impl fmt::Display for DigestAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FileDigest {
+    pub fn new(algorithm: DigestAlgorithm, hex: impl Into<String>) -> Self {
+        Self {
+            algorithm,
+            hex: hex.into().to_ascii_lowercase(),
+        }
+    }
+
+    pub fn md5(hex: impl Into<String>) -> Self {
+        Self::new(DigestAlgorithm::Md5, hex)
+    }
+
+    pub fn sha1(hex: impl Into<String>) -> Self {
+        Self::new(DigestAlgorithm::Sha1, hex)
+    }
+
+    pub fn sha256(hex: impl Into<String>) -> Self {
+        Self::new(DigestAlgorithm::Sha256, hex)
+    }
+
+    pub fn sha512(hex: impl Into<String>) -> Self {
+        Self::new(DigestAlgorithm::Sha512, hex)
+    }
 }
 
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
@@ -49,6 +128,52 @@ pub enum DownloadRateLimit {
     Limited(ByteRate),
 }
 
+/// Caps shared by every connection to one host, keyed by `host[:port]` in the downloader config.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct HostLimit {
+    pub max_connections: Option<usize>,
+    pub rate_limit: DownloadRateLimit,
+}
+
+/// Splits large downloads into parallel `Range` requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DownloadSegmentation {
+    pub min_file_size: u64,
+    pub min_segment_size: u64,
+    pub max_segments: usize,
+}
+
+impl Default for DownloadSegmentation {
+    fn default() -> Self {
+        Self {
+            min_file_size: 16 * 1024 * 1024,
+            min_segment_size: 4 * 1024 * 1024,
+            max_segments: 4,
+        }
+    }
+}
+
+impl DownloadSegmentation {
+    pub fn segment_count(&self, size: u64) -> usize {
+        if size < self.min_file_size {
+            return 1;
+        }
+
+        let by_size = size / self.min_segment_size.max(1);
+        (self.max_segments as u64).min(by_size).max(1) as usize
+    }
+}
+
+/// How `file://` task urls are materialized into the staging file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LocalFileMode {
+    #[default]
+    Copy,
+    /// Hardlinks when the source is on the same filesystem, so a later in-place edit of the
+    /// source also changes the target. Opt in only for read-only local mirrors.
+    HardlinkOrCopy,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum DownloadExecutionPolicy {
     ServiceDefault,
@@ -142,9 +267,71 @@ impl DownloadTask {
     ) -> Self {
         Self {
             url: url.into(),
+            mirrors: Vec::new(),
             path: path.into(),
             expected_size,
-            sha1,
+            digests: sha1.map(FileDigest::sha1).into_iter().collect(),
+            priority: DownloadPriority::default(),
+            decompression: None,
         }
     }
+
+    pub fn from_candidates(
+        candidates: Vec<String>,
+        path: impl Into<PathBuf>,
+        expected_size: Option<u64>,
+        sha1: Option<String>,
+    ) -> Result<Self> {
+        let mut candidates = candidates.into_iter();
+        let Some(url) = candidates.next() else {
+            bail!("download task requires at least one candidate url");
+        };
+
+        Ok(Self::new(url, path, expected_size, sha1).with_mirrors(candidates.collect()))
+    }
+
+    pub fn with_digest(mut self, digest: FileDigest) -> Self {
+        self.digests
+            .retain(|existing| existing.algorithm != digest.algorithm);
+        self.digests.push(digest);
+        self
+    }
+
+    pub fn with_digests(self, digests: impl IntoIterator<Item = FileDigest>) -> Self {
+        digests.into_iter().fold(self, Self::with_digest)
+    }
+
+    pub fn digest(&self, algorithm: DigestAlgorithm) -> Option<&str> {
+        self.digests
+            .iter()
+            .find(|digest| digest.algorithm == algorithm)
+            .map(|digest| digest.hex.as_str())
+    }
+
+    pub fn sha1(&self) -> Option<&str> {
+        self.digest(DigestAlgorithm::Sha1)
+    }
+
+    pub fn strongest_digest(&self) -> Option<&FileDigest> {
+        self.digests.iter().max_by_key(|digest| digest.algorithm)
+    }
+
+    pub fn with_priority(mut self, priority: DownloadPriority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn with_decompression(mut self, compression: Compression) -> Self {
+        self.decompression = Some(compression);
+        self
+    }
+
+    pub fn with_mirrors(mut self, mirrors: Vec<String>) -> Self {
+        self.mirrors = mirrors;
+        self
+    }
+
+    pub fn candidate_urls(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.url.as_str()).chain(self.mirrors.iter().map(String::as_str))
+    }
 }
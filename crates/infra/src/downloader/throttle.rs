@@ -0,0 +1,182 @@
+use anyhow::{Context, Result};
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use super::{control::BandwidthLimiter, health::host_key, task::HostLimit};
+
+/// Fallback delay when a throttling response carries no usable `Retry-After`.
+pub(crate) const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(1);
+
+#[derive(Debug)]
+pub(crate) struct HostThrottle {
+    limits: HashMap<String, HostLimit>,
+    default_limit: HostLimit,
+    hosts: Mutex<HashMap<String, Arc<HostControl>>>,
+}
+
+#[derive(Debug)]
+pub(crate) struct HostControl {
+    connections: Option<Arc<Semaphore>>,
+    bandwidth_limiter: BandwidthLimiter,
+    backoff_until: Mutex<Option<Instant>>,
+}
+
+impl HostThrottle {
+    pub(crate) fn new(limits: HashMap<String, HostLimit>, default_limit: HostLimit) -> Self {
+        Self {
+            limits,
+            default_limit,
+            hosts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns `None` for urls without a host, such as `file://` sources.
+    pub(crate) fn control(&self, url: &str) -> Option<Arc<HostControl>> {
+        let host = host_key(url)?;
+        let mut hosts = self.hosts.lock().expect("host throttle mutex poisoned");
+        let control = hosts.entry(host).or_insert_with_key(|host| {
+            let limit = self.limits.get(host).copied().unwrap_or(self.default_limit);
+            Arc::new(HostControl {
+                connections: limit
+                    .max_connections
+                    .map(|max_connections| Arc::new(Semaphore::new(max_connections))),
+                bandwidth_limiter: BandwidthLimiter::new(limit.rate_limit)
+                    .expect("host rate limit must be valid"),
+                backoff_until: Mutex::new(None),
+            })
+        });
+        Some(control.clone())
+    }
+
+    pub(crate) fn backoff_remaining(&self, host: &str) -> Option<Duration> {
+        self.hosts
+            .lock()
+            .expect("host throttle mutex poisoned")
+            .get(host)?
+            .backoff_remaining()
+    }
+}
+
+impl HostControl {
+    /// Waits out any pending backoff, then takes a connection slot for this host.
+    pub(crate) async fn acquire(&self) -> Result<Option<OwnedSemaphorePermit>> {
+        while let Some(remaining) = self.backoff_remaining() {
+            tokio::time::sleep(remaining).await;
+        }
+
+        match &self.connections {
+            Some(connections) => connections
+                .clone()
+                .acquire_owned()
+                .await
+                .map(Some)
+                .context("failed to acquire host connection permit"),
+            None => Ok(None),
+        }
+    }
+
+    /// `None` when the host is at its cap, `Some(None)` when it has none.
+    pub(crate) fn try_acquire(&self) -> Option<Option<OwnedSemaphorePermit>> {
+        match &self.connections {
+            Some(connections) => connections.clone().try_acquire_owned().ok().map(Some),
+            None => Some(None),
+        }
+    }
+
+    pub(crate) async fn throttle(&self, bytes: usize) {
+        self.bandwidth_limiter.throttle(bytes).await;
+    }
+
+    /// Pushes the shared deadline out, so every task bound for this host waits.
+    pub(crate) fn back_off(&self, retry_after: Duration) {
+        let until = Instant::now() + retry_after;
+        let mut backoff_until = self
+            .backoff_until
+            .lock()
+            .expect("host backoff mutex poisoned");
+        if backoff_until.is_none_or(|current| current < until) {
+            *backoff_until = Some(until);
+        }
+    }
+
+    fn backoff_remaining(&self) -> Option<Duration> {
+        let backoff_until = *self
+            .backoff_until
+            .lock()
+            .expect("host backoff mutex poisoned");
+        backoff_until
+            .map(|until| until.saturating_duration_since(Instant::now()))
+            .filter(|remaining| !remaining.is_zero())
+    }
+}
+
+/// Accepts both the delay-seconds and the HTTP-date forms.
+pub(crate) fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let date = parse_http_date(value)?;
+    Some(
+        date.duration_since(SystemTime::now())
+            .unwrap_or(Duration::ZERO),
+    )
+}
+
+// Only the IMF-fixdate form is parsed, the obsolete formats are not sent by current servers.
+fn parse_http_date(value: &str) -> Option<SystemTime> {
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+
+    let (_, rest) = value.split_once(", ")?;
+    let mut parts = rest.split(' ');
+    let day = parts.next()?.parse::<i64>().ok()?;
+    let month = parts.next()?;
+    let month = MONTHS.iter().position(|name| *name == month)? as i64 + 1;
+    let year = parts.next()?.parse::<i64>().ok()?;
+    let mut time = parts
+        .next()?
+        .split(':')
+        .map(|part| part.parse::<i64>().ok());
+    let (hour, minute, second) = (time.next()??, time.next()??, time.next()??);
+    if parts.next()? != "GMT" {
+        return None;
+    }
+
+    // Days since the epoch from a proleptic Gregorian date, counted from March to keep leap days last.
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+    let seconds = days * 86_400 + hour * 3_600 + minute * 60 + second;
+    Some(UNIX_EPOCH + Duration::from_secs(u64::try_from(seconds).ok()?))
+}
+
+#[test]
+fn test_retry_after_accepts_seconds_and_http_dates() {
+    let mut headers = HeaderMap::new();
+    headers.insert(RETRY_AFTER, "7".parse().unwrap());
+    assert_eq!(retry_after(&headers), Some(Duration::from_secs(7)));
+
+    assert_eq!(
+        parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT"),
+        Some(UNIX_EPOCH + Duration::from_secs(784_111_777))
+    );
+    headers.insert(
+        RETRY_AFTER,
+        "Sun, 06 Nov 1994 08:49:37 GMT".parse().unwrap(),
+    );
+    assert_eq!(retry_after(&headers), Some(Duration::ZERO));
+
+    headers.insert(RETRY_AFTER, "soon".parse().unwrap());
+    assert_eq!(retry_after(&headers), None);
+}
@@ -1,28 +1,67 @@
 use anyhow::{Result, bail};
+use md5::Md5;
 use sha1_smol::Sha1;
-use std::path::Path;
+use sha2::{Digest, Sha256, Sha512};
+use std::{fmt::Write, path::Path};
 use tokio::{fs::File, io::AsyncReadExt};
 
-use super::task::DownloadTask;
+use super::task::{DigestAlgorithm, DownloadTask, FileDigest};
 
 const VALIDATION_BUFFER_SIZE: usize = 64 * 1024;
 
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct DownloadValidation {
     pub expected_size: Option<u64>,
-    pub expected_sha1: Option<String>,
+    pub expected_digests: Vec<FileDigest>,
 }
 
 impl DownloadValidation {
     pub fn from_task(task: &DownloadTask) -> Self {
         Self {
             expected_size: task.expected_size,
-            expected_sha1: task.sha1.clone(),
+            expected_digests: task.digests.clone(),
         }
     }
 
     pub fn is_enabled(&self) -> bool {
-        self.expected_size.is_some() || self.expected_sha1.is_some()
+        self.expected_size.is_some() || !self.expected_digests.is_empty()
+    }
+}
+
+#[derive(Clone)]
+enum DigestHasher {
+    Md5(Md5),
+    Sha1(Sha1),
+    Sha256(Sha256),
+    Sha512(Sha512),
+}
+
+impl DigestHasher {
+    fn new(algorithm: DigestAlgorithm) -> Self {
+        match algorithm {
+            DigestAlgorithm::Md5 => Self::Md5(Md5::new()),
+            DigestAlgorithm::Sha1 => Self::Sha1(Sha1::new()),
+            DigestAlgorithm::Sha256 => Self::Sha256(Sha256::new()),
+            DigestAlgorithm::Sha512 => Self::Sha512(Sha512::new()),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Self::Md5(hasher) => hasher.update(data),
+            Self::Sha1(hasher) => hasher.update(data),
+            Self::Sha256(hasher) => hasher.update(data),
+            Self::Sha512(hasher) => hasher.update(data),
+        }
+    }
+
+    fn finish(self) -> String {
+        match self {
+            Self::Md5(hasher) => to_hex(&hasher.finalize()),
+            Self::Sha1(hasher) => hasher.digest().to_string(),
+            Self::Sha256(hasher) => to_hex(&hasher.finalize()),
+            Self::Sha512(hasher) => to_hex(&hasher.finalize()),
+        }
     }
 }
 
@@ -30,7 +69,7 @@ impl DownloadValidation {
 pub struct StreamingValidator {
     validation: DownloadValidation,
     actual_size: u64,
-    sha1: Option<Sha1>,
+    hashers: Vec<DigestHasher>,
 }
 
 impl StreamingValidator {
@@ -38,20 +77,57 @@ impl StreamingValidator {
         Self::new(DownloadValidation::from_task(task))
     }
 
+    /// Validates the bytes as they arrive, decompressed tasks are only checked once decoded.
+    pub fn for_transfer(task: &DownloadTask) -> Self {
+        match task.decompression {
+            Some(_) => Self::new(DownloadValidation::default()),
+            None => Self::from_task(task),
+        }
+    }
+
     pub fn new(validation: DownloadValidation) -> Self {
-        let sha1 = validation.expected_sha1.as_ref().map(|_| Sha1::new());
+        let hashers = validation
+            .expected_digests
+            .iter()
+            .map(|digest| DigestHasher::new(digest.algorithm))
+            .collect();
         Self {
             validation,
             actual_size: 0,
-            sha1,
+            hashers,
         }
     }
 
     pub fn update(&mut self, data: &[u8]) {
         self.actual_size += data.len() as u64;
-        if let Some(sha1) = &mut self.sha1 {
-            sha1.update(data);
+        for hasher in &mut self.hashers {
+            hasher.update(data);
+        }
+    }
+
+    pub async fn update_from_file(&mut self, path: &Path, len: u64) -> Result<()> {
+        let mut file = File::open(path).await?.take(len);
+        let mut buffer = [0u8; VALIDATION_BUFFER_SIZE];
+
+        loop {
+            let read = file.read(&mut buffer).await?;
+            if read == 0 {
+                break;
+            }
+
+            self.update(&buffer[..read]);
         }
+
+        if self.actual_size != len {
+            bail!(
+                "staged file '{}' is shorter than expected: expected {}, got {}",
+                path.display(),
+                len,
+                self.actual_size
+            );
+        }
+
+        Ok(())
     }
 
     pub fn finish(self, path: &Path) -> Result<()> {
@@ -66,14 +142,15 @@ impl StreamingValidator {
             );
         }
 
-        if let Some(expected_sha1) = self.validation.expected_sha1 {
-            let actual_sha1 = self.sha1.unwrap_or_default().digest().to_string();
-            if actual_sha1 != expected_sha1 {
+        for (expected, hasher) in self.validation.expected_digests.iter().zip(self.hashers) {
+            let actual = hasher.finish();
+            if !actual.eq_ignore_ascii_case(&expected.hex) {
                 bail!(
-                    "downloaded file sha1 mismatch for '{}': expected {}, got {}",
+                    "downloaded file {} mismatch for '{}': expected {}, got {}",
+                    expected.algorithm,
                     path.display(),
-                    expected_sha1,
-                    actual_sha1
+                    expected.hex,
+                    actual
                 );
             }
         }
@@ -121,7 +198,7 @@ pub async fn target_matches_task(path: &Path, task: &DownloadTask) -> Result<boo
         return Ok(false);
     }
 
-    if validation.expected_sha1.is_none() {
+    if validation.expected_digests.is_empty() {
         return Ok(true);
     }
 
@@ -130,3 +207,39 @@ pub async fn target_matches_task(path: &Path, task: &DownloadTask) -> Result<boo
         Err(_) => Ok(false),
     }
 }
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .fold(String::with_capacity(bytes.len() * 2), |mut hex, byte| {
+            let _ = write!(hex, "{byte:02x}");
+            hex
+        })
+}
+
+#[tokio::test]
+async fn test_target_matches_every_digest_algorithm() {
+    let root = std::env::temp_dir().join(format!("elemental-digests-{}", std::process::id()));
+    let target = root.join("abc.bin");
+    tokio::fs::create_dir_all(&root).await.unwrap();
+    tokio::fs::write(&target, b"abc").await.unwrap();
+
+    let task = DownloadTask::new("http://127.0.0.1:9/abc.bin", &target, Some(3), None)
+        .with_digests([
+            FileDigest::md5("900150983cd24fb0d6963f7d28e17f72"),
+            FileDigest::sha1("A9993E364706816ABA3E25717850C26C9CD0D89D"),
+            FileDigest::sha256("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"),
+            FileDigest::sha512(
+                "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f",
+            ),
+        ]);
+    assert!(target_matches_task(&target, &task).await.unwrap());
+    assert_eq!(
+        task.strongest_digest().map(|digest| digest.algorithm),
+        Some(DigestAlgorithm::Sha512)
+    );
+
+    let tampered = task.with_digest(FileDigest::sha256("00".repeat(32)));
+    assert!(!target_matches_task(&target, &tampered).await.unwrap());
+    let _ = tokio::fs::remove_dir_all(&root).await;
+}
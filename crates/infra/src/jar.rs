@@ -1,12 +1,21 @@
 use std::{
+    collections::HashSet,
     ffi::OsStr,
-    fs::{File, create_dir_all},
-    io::{self, Cursor, Read, Seek},
+    fs::{File, create_dir_all, remove_file, rename},
+    io::{self, BufWriter, Cursor, Read, Seek, Write},
     path::{Path, PathBuf},
 };
 
-use anyhow::Result;
-use zip::ZipArchive;
+use anyhow::{Context, Result, bail};
+use serde::de::DeserializeOwned;
+use zip::{ZipArchive, ZipWriter, write::SimpleFileOptions};
+
+pub const MANIFEST_ENTRY: &str = "META-INF/MANIFEST.MF";
+
+const S_IFMT: u32 = 0o170000;
+const S_IFREG: u32 = 0o100000;
+const S_IFDIR: u32 = 0o040000;
+const S_IFLNK: u32 = 0o120000;
 
 #[derive(Debug, Clone)]
 pub struct JarFile<P: AsRef<Path>> {
@@ -18,24 +27,150 @@ pub struct JarBytes<'a> {
     bytes: &'a [u8],
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JarEntry {
+    pub name: String,
+    pub size: u64,
+    pub compressed_size: u64,
+    pub crc32: u32,
+    pub kind: JarEntryKind,
+}
+
+/// Classified from the unix mode when the archive records one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JarEntryKind {
+    File,
+    Directory,
+    Symlink,
+    /// Devices, fifos and sockets.
+    Special,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct JarExtraction {
+    pub extracted: Vec<JarEntry>,
+    /// Names of the entries skipped by an exclude prefix.
+    pub excluded: Vec<String>,
+}
+
+/// Writes a new jar, keeping the first entry written under each name.
+pub struct JarWriter {
+    path: PathBuf,
+    staging_path: PathBuf,
+    writer: ZipWriter<BufWriter<File>>,
+    names: HashSet<String>,
+}
+
+/// Builds a patched jar from a base jar and an ordered list of jarmods.
+///
+/// Later jarmods replace entries of earlier ones and of the base jar, and signature files are
+/// dropped since patched classes would no longer match them.
+#[derive(Debug, Clone)]
+pub struct JarPatcher {
+    base: PathBuf,
+    jarmods: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct JarPatch {
+    pub entries: usize,
+    /// Base or earlier jarmod entries that a later jarmod replaced.
+    pub replaced: Vec<String>,
+    /// Signature entries left out of the output.
+    pub stripped: Vec<String>,
+}
+
+/// A parsed `META-INF/MANIFEST.MF`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Manifest {
+    pub main_attributes: ManifestSection,
+    /// Per-entry sections keyed by their `Name` attribute, in file order.
+    pub entries: Vec<(String, ManifestSection)>,
+}
+
+/// Attributes in file order, looked up case-insensitively as the jar spec requires.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ManifestSection {
+    pub attributes: Vec<(String, String)>,
+}
+
 impl<P: AsRef<Path>> JarFile<P> {
     pub fn new(path: P) -> Self {
         Self { path }
     }
 
     pub fn extract_blocking<S: AsRef<OsStr> + ?Sized>(&self, dest: &S) -> Result<()> {
-        let file = File::open(&self.path)?;
-        let mut archive = ZipArchive::new(file)?;
-        let destination = Path::new(dest);
-        extract_entries_blocking(&mut archive, destination, |relative_path| {
-            if relative_path.starts_with("META-INF") {
-                return None;
+        self.extract_excluding_blocking(Path::new(dest), &["META-INF/".to_owned()], |_| true)?;
+        Ok(())
+    }
+
+    /// Extracts every entry that doesn't start with one of the `exclude` prefixes, refusing
+    /// symlinks, special files and entries that escape `dest`.
+    ///
+    /// `claim` sees each file before it is written and can skip it, so callers merging several
+    /// jars into one directory decide which copy of a name wins.
+    pub fn extract_excluding_blocking<F>(
+        &self,
+        dest: &Path,
+        exclude: &[String],
+        mut claim: F,
+    ) -> Result<JarExtraction>
+    where
+        F: FnMut(&JarEntry) -> bool,
+    {
+        let jar_path = self.path.as_ref();
+        let mut archive = ZipArchive::new(File::open(jar_path)?)?;
+        let mut extraction = JarExtraction::default();
+
+        for index in 0..archive.len() {
+            let mut file = archive.by_index(index)?;
+            let entry = jar_entry(&file);
+            if exclude
+                .iter()
+                .any(|prefix| entry.name.starts_with(prefix.as_str()))
+            {
+                extraction.excluded.push(entry.name);
+                continue;
+            }
+
+            match entry.kind {
+                JarEntryKind::File | JarEntryKind::Directory => {}
+                JarEntryKind::Symlink => bail!(
+                    "refusing to extract symlink entry '{}' from {}",
+                    entry.name,
+                    jar_path.display()
+                ),
+                JarEntryKind::Special => bail!(
+                    "refusing to extract special file entry '{}' from {}",
+                    entry.name,
+                    jar_path.display()
+                ),
+            }
+            let Some(relative_path) = file.enclosed_name() else {
+                bail!(
+                    "refusing to extract entry '{}' outside of the destination from {}",
+                    entry.name,
+                    jar_path.display()
+                );
+            };
+            let output_path = dest.join(relative_path);
+            if entry.kind == JarEntryKind::Directory {
+                create_dir_all(&output_path)?;
+                continue;
+            }
+            if !claim(&entry) {
+                continue;
             }
 
-            Some(relative_path.to_path_buf())
-        })?;
+            if let Some(parent) = output_path.parent() {
+                create_dir_all(parent)?;
+            }
+            let mut output = File::create(&output_path)?;
+            io::copy(&mut file, &mut output)?;
+            extraction.extracted.push(entry);
+        }
 
-        Ok(())
+        Ok(extraction)
     }
 
     pub fn by_name_bytes(&self, name: &str) -> Result<Vec<u8>> {
@@ -47,6 +182,28 @@ impl<P: AsRef<Path>> JarFile<P> {
         Ok(String::from_utf8(self.by_name_bytes(name)?)?)
     }
 
+    pub fn by_name_json<T: DeserializeOwned>(&self, name: &str) -> Result<T> {
+        decode_json_entry(&self.by_name_bytes(name)?, name)
+    }
+
+    /// `None` when the jar carries no manifest.
+    pub fn manifest(&self) -> Result<Option<Manifest>> {
+        read_manifest(File::open(&self.path)?)
+    }
+
+    pub fn entries(&self) -> Result<Vec<JarEntry>> {
+        list_entries(File::open(&self.path)?)
+    }
+
+    /// Signature files and blocks under `META-INF`, empty for unsigned jars.
+    pub fn signature_entries(&self) -> Result<Vec<String>> {
+        Ok(filter_signature_entries(self.entries()?))
+    }
+
+    pub fn is_signed(&self) -> Result<bool> {
+        Ok(!self.signature_entries()?.is_empty())
+    }
+
     pub fn extract_prefixed_blocking(&self, prefix: &str, dest: &Path) -> Result<Vec<PathBuf>> {
         let file = File::open(&self.path)?;
         let mut archive = ZipArchive::new(file)?;
@@ -78,6 +235,353 @@ impl<'a> JarBytes<'a> {
     pub fn by_name_string(&self, name: &str) -> Result<String> {
         Ok(String::from_utf8(self.by_name_bytes(name)?)?)
     }
+
+    pub fn by_name_json<T: DeserializeOwned>(&self, name: &str) -> Result<T> {
+        decode_json_entry(&self.by_name_bytes(name)?, name)
+    }
+
+    pub fn manifest(&self) -> Result<Option<Manifest>> {
+        read_manifest(Cursor::new(self.bytes))
+    }
+
+    pub fn entries(&self) -> Result<Vec<JarEntry>> {
+        list_entries(Cursor::new(self.bytes))
+    }
+
+    pub fn signature_entries(&self) -> Result<Vec<String>> {
+        Ok(filter_signature_entries(self.entries()?))
+    }
+
+    pub fn is_signed(&self) -> Result<bool> {
+        Ok(!self.signature_entries()?.is_empty())
+    }
+}
+
+impl JarWriter {
+    /// Entries are staged next to `path` and only moved into place by [`JarWriter::finish`].
+    pub fn create(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        if let Some(parent) = path.parent() {
+            create_dir_all(parent)?;
+        }
+        let mut staging_path = path.clone().into_os_string();
+        staging_path.push(".part");
+        let staging_path = PathBuf::from(staging_path);
+        let file = File::create(&staging_path)
+            .with_context(|| format!("create jar failed: {}", staging_path.display()))?;
+
+        Ok(Self {
+            path,
+            staging_path,
+            writer: ZipWriter::new(BufWriter::new(file)),
+            names: HashSet::new(),
+        })
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.names.contains(name)
+    }
+
+    /// Returns `false` without writing when an entry with that name already exists.
+    pub fn add_bytes(&mut self, name: &str, bytes: &[u8]) -> Result<bool> {
+        if !self.names.insert(name.to_owned()) {
+            return Ok(false);
+        }
+        self.writer
+            .start_file(name, SimpleFileOptions::default())
+            .with_context(|| format!("write jar entry failed: {name}"))?;
+        self.writer.write_all(bytes)?;
+        Ok(true)
+    }
+
+    /// Copies the entries of `jar` accepted by `filter` without recompressing them, skipping names
+    /// already written. Returns the copied entries.
+    pub fn copy_from<F>(&mut self, jar: &Path, mut filter: F) -> Result<Vec<JarEntry>>
+    where
+        F: FnMut(&JarEntry) -> bool,
+    {
+        let mut archive = ZipArchive::new(
+            File::open(jar).with_context(|| format!("open jar failed: {}", jar.display()))?,
+        )
+        .with_context(|| format!("read jar failed: {}", jar.display()))?;
+        let mut copied = Vec::new();
+
+        for index in 0..archive.len() {
+            let file = archive.by_index_raw(index)?;
+            let entry = jar_entry(&file);
+            match entry.kind {
+                JarEntryKind::File | JarEntryKind::Directory => {}
+                JarEntryKind::Symlink | JarEntryKind::Special => bail!(
+                    "refusing to copy non-regular entry '{}' from {}",
+                    entry.name,
+                    jar.display()
+                ),
+            }
+            if self.names.contains(&entry.name) || !filter(&entry) {
+                continue;
+            }
+
+            self.writer.raw_copy_file(file).with_context(|| {
+                format!(
+                    "copy jar entry '{}' from {} failed",
+                    entry.name,
+                    jar.display()
+                )
+            })?;
+            self.names.insert(entry.name.clone());
+            copied.push(entry);
+        }
+
+        Ok(copied)
+    }
+
+    pub fn finish(self) -> Result<PathBuf> {
+        let result = self
+            .writer
+            .finish()
+            .map_err(anyhow::Error::from)
+            .and_then(|writer| {
+                writer
+                    .into_inner()
+                    .map_err(|error| error.into_error().into())
+            })
+            .and_then(|file| Ok(file.sync_all()?))
+            .and_then(|_| Ok(rename(&self.staging_path, &self.path)?));
+        if let Err(error) = result {
+            let _ = remove_file(&self.staging_path);
+            return Err(error)
+                .with_context(|| format!("write jar failed: {}", self.path.display()));
+        }
+
+        Ok(self.path)
+    }
+}
+
+impl JarPatcher {
+    pub fn new(base: impl Into<PathBuf>) -> Self {
+        Self {
+            base: base.into(),
+            jarmods: Vec::new(),
+        }
+    }
+
+    pub fn with_jarmod(mut self, jarmod: impl Into<PathBuf>) -> Self {
+        self.jarmods.push(jarmod.into());
+        self
+    }
+
+    pub fn with_jarmods<I, P>(mut self, jarmods: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<PathBuf>,
+    {
+        self.jarmods.extend(jarmods.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn jarmods(&self) -> &[PathBuf] {
+        &self.jarmods
+    }
+
+    pub fn write_blocking(&self, output: &Path) -> Result<JarPatch> {
+        let mut writer = JarWriter::create(output)?;
+        let mut patch = JarPatch::default();
+
+        // The last jarmod wins, so sources are copied from the top of the stack down and every
+        // later copy of a name is what got replaced.
+        for source in self.jarmods.iter().rev().chain([&self.base]) {
+            patch.replaced.extend(
+                JarFile::new(source)
+                    .entries()
+                    .with_context(|| format!("read jar failed: {}", source.display()))?
+                    .into_iter()
+                    .filter(|entry| {
+                        entry.kind == JarEntryKind::File && writer.contains(&entry.name)
+                    })
+                    .map(|entry| entry.name),
+            );
+            let copied = writer.copy_from(source, |entry| {
+                if is_signature_entry(&entry.name) {
+                    patch.stripped.push(entry.name.clone());
+                    return false;
+                }
+                true
+            })?;
+            patch.entries += copied.len();
+        }
+
+        writer.finish()?;
+        patch.replaced.sort();
+        patch.replaced.dedup();
+        Ok(patch)
+    }
+}
+
+impl Manifest {
+    pub fn parse(raw: &str) -> Self {
+        let mut sections = unfold_manifest_lines(raw)
+            .split(|line| line.is_empty())
+            .filter(|lines| !lines.is_empty())
+            .map(|lines| ManifestSection {
+                attributes: lines
+                    .iter()
+                    .filter_map(|line| line.split_once(':'))
+                    .map(|(name, value)| {
+                        let value = value.strip_prefix(' ').unwrap_or(value);
+                        (name.trim().to_owned(), value.to_owned())
+                    })
+                    .collect(),
+            })
+            .collect::<Vec<ManifestSection>>()
+            .into_iter();
+
+        // A manifest without main attributes starts straight with a named section.
+        let mut manifest = Manifest::default();
+        let mut first = sections.next();
+        if first
+            .as_ref()
+            .is_some_and(|section| section.get("Name").is_none())
+        {
+            manifest.main_attributes = first.take().unwrap_or_default();
+        }
+        manifest.entries = first
+            .into_iter()
+            .chain(sections)
+            .filter_map(|section| Some((section.get("Name")?.to_owned(), section)))
+            .collect();
+        manifest
+    }
+
+    pub fn main_attribute(&self, name: &str) -> Option<&str> {
+        self.main_attributes.get(name)
+    }
+
+    pub fn main_class(&self) -> Option<&str> {
+        self.main_attribute("Main-Class").map(str::trim)
+    }
+
+    /// Relative urls from the `Class-Path` attribute.
+    pub fn class_path(&self) -> Vec<&str> {
+        self.main_attribute("Class-Path")
+            .map(|class_path| class_path.split_whitespace().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn is_multi_release(&self) -> bool {
+        self.main_attribute("Multi-Release")
+            .is_some_and(|value| value.trim().eq_ignore_ascii_case("true"))
+    }
+
+    pub fn entry(&self, name: &str) -> Option<&ManifestSection> {
+        self.entries
+            .iter()
+            .find(|(entry_name, _)| entry_name == name)
+            .map(|(_, section)| section)
+    }
+}
+
+impl ManifestSection {
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(attribute, _)| attribute.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Whether `name` is a jar signature file or block, `META-INF/*.SF`, `*.RSA`, `*.DSA`, `*.EC`
+/// or `SIG-*`.
+pub fn is_signature_entry(name: &str) -> bool {
+    let Some(file_name) = name.strip_prefix("META-INF/") else {
+        return false;
+    };
+    if file_name.contains('/') {
+        return false;
+    }
+
+    let upper = file_name.to_ascii_uppercase();
+    upper.starts_with("SIG-")
+        || [".SF", ".RSA", ".DSA", ".EC"]
+            .iter()
+            .any(|extension| upper.ends_with(extension))
+}
+
+fn filter_signature_entries(entries: Vec<JarEntry>) -> Vec<String> {
+    entries
+        .into_iter()
+        .filter(|entry| entry.kind == JarEntryKind::File && is_signature_entry(&entry.name))
+        .map(|entry| entry.name)
+        .collect()
+}
+
+// Continuation lines start with a single space and are joined onto the previous line.
+fn unfold_manifest_lines(manifest: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    let mut continuable = false;
+
+    for line in manifest.lines() {
+        if continuable
+            && let Some(last) = lines.last_mut()
+            && let Some(continued) = line.strip_prefix(' ')
+        {
+            last.push_str(continued);
+            continue;
+        }
+
+        continuable = !line.is_empty();
+        lines.push(line.to_owned());
+    }
+
+    lines
+}
+
+fn decode_json_entry<T: DeserializeOwned>(bytes: &[u8], name: &str) -> Result<T> {
+    serde_json::from_slice(bytes).with_context(|| format!("decode jar entry failed: {name}"))
+}
+
+fn read_manifest<R>(reader: R) -> Result<Option<Manifest>>
+where
+    R: Read + Seek,
+{
+    let mut archive = ZipArchive::new(reader)?;
+    let mut entry = match archive.by_name(MANIFEST_ENTRY) {
+        Ok(entry) => entry,
+        Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+        Err(error) => return Err(error.into()),
+    };
+    let mut data = Vec::new();
+    entry.read_to_end(&mut data)?;
+    // Manifests are utf-8, but a stray byte shouldn't hide every other attribute.
+    Ok(Some(Manifest::parse(&String::from_utf8_lossy(&data))))
+}
+
+fn list_entries<R>(reader: R) -> Result<Vec<JarEntry>>
+where
+    R: Read + Seek,
+{
+    let mut archive = ZipArchive::new(reader)?;
+    (0..archive.len())
+        .map(|index| Ok(jar_entry(&archive.by_index_raw(index)?)))
+        .collect()
+}
+
+fn jar_entry<R: Read>(file: &zip::read::ZipFile<'_, R>) -> JarEntry {
+    let kind = match file.unix_mode().map(|mode| mode & S_IFMT) {
+        Some(S_IFLNK) => JarEntryKind::Symlink,
+        Some(S_IFDIR) => JarEntryKind::Directory,
+        // Archivers that don't record a file type leave the bits at zero.
+        Some(0 | S_IFREG) | None if file.is_dir() => JarEntryKind::Directory,
+        Some(0 | S_IFREG) | None => JarEntryKind::File,
+        Some(_) => JarEntryKind::Special,
+    };
+
+    JarEntry {
+        name: file.name().to_owned(),
+        size: file.size(),
+        compressed_size: file.compressed_size(),
+        crc32: file.crc32(),
+        kind,
+    }
 }
 
 fn read_archive_entry_bytes<R>(reader: R, name: &str) -> Result<Vec<u8>>
@@ -127,3 +631,171 @@ where
 
     Ok(extracted)
 }
+
+#[test]
+fn test_jar_reads_manifest_entries_and_signatures() {
+    use std::io::Write;
+    use zip::{ZipWriter, write::SimpleFileOptions};
+
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default();
+    writer.start_file(MANIFEST_ENTRY, options).unwrap();
+    writer
+        .write_all(
+            b"Manifest-Version: 1.0\r\nMain-Class: net.minecraftforge.installertools.ConsoleTool\r\nClass-Path: libraries/a.jar\r\n  libraries/b.jar\r\nmulti-release: true\r\n\r\nName: net/minecraft/client/Main.class\r\nSHA-256-Digest: abc=\r\n\r\n",
+        )
+        .unwrap();
+    writer.start_file("META-INF/FORGE.SF", options).unwrap();
+    writer.write_all(b"Signature-Version: 1.0\r\n").unwrap();
+    writer.start_file("META-INF/FORGE.RSA", options).unwrap();
+    writer.start_file("profile.json", options).unwrap();
+    writer.write_all(br#"{"id":"rift"}"#).unwrap();
+    let bytes = writer.finish().unwrap().into_inner();
+    let jar = JarBytes::new(&bytes);
+
+    let manifest = jar.manifest().unwrap().unwrap();
+    assert_eq!(
+        manifest.main_class(),
+        Some("net.minecraftforge.installertools.ConsoleTool")
+    );
+    assert_eq!(
+        manifest.class_path(),
+        vec!["libraries/a.jar", "libraries/b.jar"]
+    );
+    assert!(manifest.is_multi_release());
+    assert_eq!(
+        manifest
+            .entry("net/minecraft/client/Main.class")
+            .and_then(|section| section.get("sha-256-digest")),
+        Some("abc=")
+    );
+
+    let entries = jar.entries().unwrap();
+    let profile = entries
+        .iter()
+        .find(|entry| entry.name == "profile.json")
+        .unwrap();
+    assert_eq!(profile.size, 13);
+    assert_eq!(profile.crc32, 0xa385_9b54);
+    assert_eq!(
+        jar.signature_entries().unwrap(),
+        vec!["META-INF/FORGE.SF", "META-INF/FORGE.RSA"]
+    );
+    let profile: serde_json::Value = jar.by_name_json("profile.json").unwrap();
+    assert_eq!(profile["id"], "rift");
+}
+
+#[test]
+fn test_jar_extract_excluding_honors_prefixes_claims_and_refuses_symlinks() {
+    use std::io::Write;
+    use zip::{ZipWriter, write::SimpleFileOptions};
+
+    let root = std::env::temp_dir().join(format!("elemental-jar-extract-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(&root).unwrap();
+    let options = SimpleFileOptions::default();
+
+    let mut writer = ZipWriter::new(File::create(root.join("natives.jar")).unwrap());
+    writer.start_file("META-INF/MANIFEST.MF", options).unwrap();
+    writer.start_file("liblwjgl.so", options).unwrap();
+    writer.write_all(b"lwjgl").unwrap();
+    writer.start_file("libopenal.so", options).unwrap();
+    writer.write_all(b"openal").unwrap();
+    writer.start_file("docs/readme.txt", options).unwrap();
+    writer.finish().unwrap();
+
+    let extraction = JarFile::new(root.join("natives.jar"))
+        .extract_excluding_blocking(
+            &root.join("out"),
+            &["META-INF/".to_owned(), "docs/".to_owned()],
+            |entry| entry.name != "libopenal.so",
+        )
+        .unwrap();
+    assert_eq!(
+        extraction
+            .extracted
+            .iter()
+            .map(|entry| entry.name.as_str())
+            .collect::<Vec<_>>(),
+        vec!["liblwjgl.so"]
+    );
+    assert_eq!(
+        extraction.excluded,
+        vec!["META-INF/MANIFEST.MF", "docs/readme.txt"]
+    );
+    assert_eq!(
+        std::fs::read(root.join("out/liblwjgl.so")).unwrap(),
+        b"lwjgl"
+    );
+    assert!(!root.join("out/libopenal.so").exists());
+
+    let mut writer = ZipWriter::new(File::create(root.join("symlink.jar")).unwrap());
+    writer
+        .add_symlink("liblwjgl.so", "/etc/passwd", options)
+        .unwrap();
+    writer.finish().unwrap();
+    let error = JarFile::new(root.join("symlink.jar"))
+        .extract_blocking(&root.join("symlink"))
+        .unwrap_err();
+    assert!(error.to_string().contains("symlink"));
+    assert!(!root.join("symlink/liblwjgl.so").exists());
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn test_jar_patcher_applies_jarmods_in_order_and_strips_signatures() {
+    let root = std::env::temp_dir().join(format!("elemental-jar-patch-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(&root).unwrap();
+    let write_jar = |name: &str, entries: &[(&str, &[u8])]| {
+        let mut writer = JarWriter::create(root.join(name)).unwrap();
+        for (entry, bytes) in entries {
+            writer.add_bytes(entry, bytes).unwrap();
+        }
+        writer.finish().unwrap()
+    };
+    let base = write_jar(
+        "1.5.2.jar",
+        &[
+            (MANIFEST_ENTRY, b"Manifest-Version: 1.0\r\n"),
+            ("META-INF/MOJANG_C.SF", b"Signature-Version: 1.0\r\n"),
+            ("META-INF/MOJANG_C.DSA", b"block"),
+            ("net/minecraft/client/Minecraft.class", b"vanilla"),
+            ("a.class", b"vanilla"),
+        ],
+    );
+    let modloader = write_jar(
+        "modloader.zip",
+        &[("a.class", b"modloader"), ("ModLoader.class", b"modloader")],
+    );
+    let optifine = write_jar("optifine.zip", &[("a.class", b"optifine")]);
+
+    let patch = JarPatcher::new(&base)
+        .with_jarmods([&modloader, &optifine])
+        .write_blocking(&root.join("patched/1.5.2.jar"))
+        .unwrap();
+    assert_eq!(patch.entries, 4);
+    assert_eq!(patch.replaced, vec!["a.class"]);
+    assert_eq!(
+        patch.stripped,
+        vec!["META-INF/MOJANG_C.SF", "META-INF/MOJANG_C.DSA"]
+    );
+
+    let patched = JarFile::new(root.join("patched/1.5.2.jar"));
+    assert_eq!(patched.by_name_string("a.class").unwrap(), "optifine");
+    assert_eq!(
+        patched.by_name_string("ModLoader.class").unwrap(),
+        "modloader"
+    );
+    assert_eq!(
+        patched
+            .by_name_string("net/minecraft/client/Minecraft.class")
+            .unwrap(),
+        "vanilla"
+    );
+    assert!(!patched.is_signed().unwrap());
+    assert!(patched.manifest().unwrap().is_some());
+    assert!(JarFile::new(&base).is_signed().unwrap());
+    assert!(!root.join("patched/1.5.2.jar.part").exists());
+    let _ = std::fs::remove_dir_all(&root);
+}
@@ -1,9 +1,17 @@
 use std::{path::PathBuf, sync::Arc};
 
-use elemental_driver::families::version_json::{
-    BaseInstanceLayout, BaseRootLayout, VersionJsonInstanceLayout, VersionJsonRootLayout,
+use anyhow::Result;
+use elemental_core::network::NetworkConfig;
+use elemental_driver::{
+    families::version_json::{
+        BaseInstanceLayout, BaseRootLayout, VersionJsonInstanceLayout, VersionJsonRootLayout,
+        XdgRootLayout,
+    },
+    http::HttpCache,
+};
+use elemental_infra::downloader::core::{
+    ElementalDownloader, ElementalDownloaderBuilder, ElementalDownloaderConfig,
 };
-use elemental_infra::downloader::core::ElementalDownloader;
 
 use crate::launcher::Launcher;
 
@@ -14,18 +22,26 @@ where
     VL: VersionJsonInstanceLayout,
 {
     storage_root: PathBuf,
-    downloader: Arc<ElementalDownloader>,
+    downloader: Option<Arc<ElementalDownloader>>,
+    journal_root: Option<PathBuf>,
     root_layout: L,
     instance_layout: VL,
+    network: NetworkConfig,
+    http_client: Option<reqwest::Client>,
+    metadata_cache: Option<HttpCache>,
 }
 
 impl Default for LauncherBuilder<BaseRootLayout, BaseInstanceLayout> {
     fn default() -> Self {
         Self {
             storage_root: PathBuf::from(".minecraft"),
-            downloader: ElementalDownloader::new(),
+            downloader: None,
+            journal_root: None,
             root_layout: BaseRootLayout,
             instance_layout: BaseInstanceLayout,
+            network: NetworkConfig::default(),
+            http_client: None,
+            metadata_cache: None,
         }
     }
 }
@@ -53,8 +69,12 @@ where
         LauncherBuilder {
             storage_root: self.storage_root,
             downloader: self.downloader,
+            journal_root: self.journal_root,
             root_layout,
             instance_layout,
+            network: self.network,
+            http_client: self.http_client,
+            metadata_cache: self.metadata_cache,
         }
     }
 
@@ -63,8 +83,28 @@ where
         self
     }
 
+    /// Uses `downloader` as is, instead of one built from the network config and journal root.
     pub fn downloader(mut self, downloader: Arc<ElementalDownloader>) -> Self {
-        self.downloader = downloader;
+        self.downloader = Some(downloader);
+        self
+    }
+
+    /// Routes metadata sources, downloads and authorizers through `network`.
+    pub fn network(mut self, network: NetworkConfig) -> Result<Self> {
+        self.http_client = Some(network.build_client()?);
+        self.network = network;
+        Ok(self)
+    }
+
+    /// Journals download sessions under `journal_root` so interrupted ones can be resumed.
+    pub fn journal_root(mut self, journal_root: impl Into<PathBuf>) -> Self {
+        self.journal_root = Some(journal_root.into());
+        self
+    }
+
+    /// Caches version manifests, loader meta and release lists on disk, see [`HttpCache`].
+    pub fn metadata_cache(mut self, cache: HttpCache) -> Self {
+        self.metadata_cache = Some(cache);
         self
     }
 
@@ -75,9 +115,26 @@ where
         LauncherBuilder {
             storage_root: self.storage_root,
             downloader: self.downloader,
+            journal_root: self.journal_root,
             root_layout,
             instance_layout: self.instance_layout,
+            network: self.network,
+            http_client: self.http_client,
+            metadata_cache: self.metadata_cache,
+        }
+    }
+
+    /// Keeps instances in the XDG data directory, shared downloads and cached metadata in the
+    /// cache directory and the download journal in the state directory.
+    pub fn xdg_layout(mut self) -> Result<LauncherBuilder<XdgRootLayout, VL>> {
+        let root_layout = XdgRootLayout::from_scopes()?;
+        self.journal_root = Some(XdgRootLayout::journal_root()?);
+        if self.metadata_cache.is_none() {
+            self.metadata_cache = Some(HttpCache::new(root_layout.metadata_cache_root()));
         }
+        Ok(self
+            .storage_root(XdgRootLayout::data_root()?)
+            .root_layout(root_layout))
     }
 
     pub fn instance_layout<NVL>(self, instance_layout: NVL) -> LauncherBuilder<L, NVL>
@@ -87,17 +144,63 @@ where
         LauncherBuilder {
             storage_root: self.storage_root,
             downloader: self.downloader,
+            journal_root: self.journal_root,
             root_layout: self.root_layout,
             instance_layout,
+            network: self.network,
+            http_client: self.http_client,
+            metadata_cache: self.metadata_cache,
         }
     }
 
-    pub fn build(self) -> Launcher<L, VL> {
-        Launcher::with_layouts(
+    pub fn build(self) -> Result<Launcher<L, VL>> {
+        let downloader = match self.downloader {
+            Some(downloader) => downloader,
+            None => {
+                let builder = ElementalDownloaderBuilder::new()
+                    .config(ElementalDownloaderConfig {
+                        connect_timeout: self.network.connect_timeout,
+                        ..ElementalDownloaderConfig::default()
+                    })
+                    .client_builder(self.network.client_builder()?);
+                match self.journal_root {
+                    Some(journal_root) => builder.journal(journal_root),
+                    None => builder,
+                }
+                .build()?
+            }
+        };
+
+        Ok(Launcher::with_layouts(
             self.storage_root,
-            self.downloader,
+            downloader,
             self.root_layout,
             self.instance_layout,
         )
+        .with_network(self.network, self.http_client)
+        .with_metadata_cache(self.metadata_cache))
     }
 }
+
+#[test]
+fn test_build_keeps_explicit_downloader_and_journal_regardless_of_order() {
+    let downloader = ElementalDownloader::new();
+    let launcher = LauncherBuilder::new()
+        .downloader(downloader.clone())
+        .network(NetworkConfig::default())
+        .unwrap()
+        .build()
+        .unwrap();
+    assert!(Arc::ptr_eq(launcher.downloader(), &downloader));
+
+    let journal_root =
+        std::env::temp_dir().join(format!("elemental-builder-journal-{}", std::process::id()));
+    let launcher = LauncherBuilder::new()
+        .journal_root(&journal_root)
+        .network(NetworkConfig::default())
+        .unwrap()
+        .build()
+        .unwrap();
+    assert!(launcher.downloader().journal().is_some());
+    let _ = std::fs::remove_dir_all(&journal_root);
+}
@@ -1,15 +1,23 @@
 use std::{
     collections::HashMap,
     path::{Path, PathBuf},
-    sync::Arc,
+    sync::{Arc, Mutex},
 };
 
 use anyhow::{Result, anyhow, bail};
 use elemental_core::{
-    auth::authorizer::Authorizer, launcher::process, minecraft::MinecraftVersionId,
-    storage::Storage,
+    auth::{authorizer::Authorizer, authorizers::microsoft::MicrosoftAuthorizer},
+    launcher::process,
+    minecraft::MinecraftVersionId,
+    network::NetworkConfig,
+    runtime::distribution::Distribution,
+    storage::{Storage, StorageLockMode},
 };
 use elemental_driver::{
+    archive::{
+        InstanceArchiveOptions, InstanceArchiveReport, export_instance_archive,
+        import_instance_archive, read_instance_archive_manifest,
+    },
     catalog::Catalog,
     descriptors::{
         BABRIC_DRIVER, CLEANROOM_DRIVER, FABRIC_DRIVER, FORGE_DRIVER, LEGACY_FABRIC_DRIVER,
@@ -30,19 +38,28 @@ use elemental_driver::{
         installer::{InstallerFamilyDriver, InstallerFamilyDriverSpec},
         version_json::{
             BaseInstanceLayout, BaseRootLayout, ProfiledVersionJsonDriver,
-            ProfiledVersionJsonFamily, ProfiledVersionJsonFamilyExt, VersionJsonGameStorageExt,
-            VersionJsonInstanceLayout, VersionJsonRootLayout, inspect_instances,
+            ProfiledVersionJsonFamily, ProfiledVersionJsonFamilyExt, RootMigrationReport,
+            VersionJsonGameStorageExt, VersionJsonInstanceLayout, VersionJsonRootLayout,
+            VersionJsonVersionStorageExt, inspect_instances, migrate_root_layout,
         },
     },
+    gc::{StorageGcMode, StorageGcReport, collect_storage_garbage},
+    http::{Fetched, HttpCache, HttpClientSource},
     inspect::InstalledInstance,
+    usage::{DiskUsageScanner, StorageDiskUsage},
+};
+use elemental_infra::downloader::{
+    core::{DownloadEvent, ElementalDownloader},
+    scope::{ScopedEventForwarder, SessionScope},
 };
-use elemental_infra::downloader::core::ElementalDownloader;
+use tokio::sync::broadcast::error::RecvError;
 
 use crate::{
     builder::LauncherBuilder,
     request::{LaunchOptions, PrepareInstanceRequest},
     result::{
-        Instance, LaunchCommandResult, LaunchedInstance, PreparedInstance, PreparedInstanceKind,
+        ImportedInstance, Instance, LaunchCommandResult, LaunchedInstance, PreparedInstance,
+        PreparedInstanceKind,
     },
     spec::DriverSpec,
 };
@@ -84,6 +101,10 @@ where
     downloader: Arc<ElementalDownloader>,
     root_layout: L,
     instance_layout: VL,
+    network: NetworkConfig,
+    http_client: Option<reqwest::Client>,
+    metadata_cache: Option<HttpCache>,
+    disk_usage_scanner: Arc<Mutex<DiskUsageScanner>>,
 }
 
 impl Launcher<BaseRootLayout, BaseInstanceLayout> {
@@ -112,9 +133,28 @@ where
             downloader,
             root_layout,
             instance_layout,
+            network: NetworkConfig::default(),
+            http_client: None,
+            metadata_cache: None,
+            disk_usage_scanner: Arc::default(),
         }
     }
 
+    pub(crate) fn with_network(
+        mut self,
+        network: NetworkConfig,
+        http_client: Option<reqwest::Client>,
+    ) -> Self {
+        self.network = network;
+        self.http_client = http_client;
+        self
+    }
+
+    pub(crate) fn with_metadata_cache(mut self, metadata_cache: Option<HttpCache>) -> Self {
+        self.metadata_cache = metadata_cache;
+        self
+    }
+
     pub fn storage_root(&self) -> &Path {
         &self.storage_root
     }
@@ -123,6 +163,29 @@ where
         &self.downloader
     }
 
+    pub fn network(&self) -> &NetworkConfig {
+        &self.network
+    }
+
+    pub fn metadata_cache(&self) -> Option<&HttpCache> {
+        self.metadata_cache.as_ref()
+    }
+
+    pub fn microsoft_authorizer<F>(
+        &self,
+        client_id: impl Into<String>,
+        verification_handler: F,
+    ) -> MicrosoftAuthorizer<F>
+    where
+        F: Fn(String, String),
+    {
+        MicrosoftAuthorizer {
+            client_id: client_id.into(),
+            verification_handler,
+            network: self.network.clone(),
+        }
+    }
+
     pub async fn inspect_instances(&self) -> Result<Vec<Instance>> {
         let storage = self.game_storage();
         let inspect_drivers = InspectDrivers::new(self)?;
@@ -146,10 +209,117 @@ where
             .map(summarize_installed_instance))
     }
 
+    pub async fn collect_garbage(&self, mode: StorageGcMode) -> Result<StorageGcReport> {
+        let report =
+            collect_storage_garbage(&self.game_storage(), self.instance_layout.clone(), mode)
+                .await?;
+        if mode == StorageGcMode::Delete {
+            self.disk_usage_scanner
+                .lock()
+                .expect("disk usage scanner mutex poisoned")
+                .clear_cache();
+        }
+        Ok(report)
+    }
+
+    pub async fn disk_usage(&self) -> Result<StorageDiskUsage> {
+        let mut runtimes = self.game_storage().runtimes().await?;
+        for runtime in Distribution::cached().await {
+            if runtimes.iter().all(|known| known.path != runtime.path) {
+                runtimes.push(runtime);
+            }
+        }
+        // The scan walks the whole root synchronously, so the worker's other tasks are handed off.
+        tokio::task::block_in_place(|| {
+            self.disk_usage_scanner
+                .lock()
+                .expect("disk usage scanner mutex poisoned")
+                .scan(
+                    &self.game_storage(),
+                    self.instance_layout.clone(),
+                    &runtimes,
+                )
+        })
+    }
+
+    pub fn migrate_legacy_root(
+        &self,
+        legacy_root: impl AsRef<Path>,
+    ) -> Result<RootMigrationReport> {
+        migrate_root_layout(
+            &Storage::new(legacy_root, BaseRootLayout),
+            &self.game_storage(),
+        )
+    }
+
+    pub async fn export_instance(
+        &self,
+        instance_name: String,
+        destination: impl AsRef<Path>,
+        options: &InstanceArchiveOptions,
+    ) -> Result<InstanceArchiveReport> {
+        let driver_spec = self.instance_driver_spec(instance_name.clone()).await?;
+        let _root_lock = self.game_storage().try_lock_root(StorageLockMode::Shared)?;
+        let instance = self.instance(instance_name)?;
+        let _instance_lock = instance.try_lock_instance()?;
+        export_instance_archive(
+            &instance,
+            driver_spec.archive_driver(),
+            destination,
+            options,
+        )
+    }
+
+    pub async fn import_instance(
+        &self,
+        archive: impl AsRef<Path>,
+        instance_name: Option<String>,
+        options: &InstanceArchiveOptions,
+    ) -> Result<ImportedInstance<L, VL>> {
+        let archive = archive.as_ref();
+        let manifest = read_instance_archive_manifest(archive)?;
+        let driver_spec = DriverSpec::try_from(&manifest.driver)?;
+        let instance_name = instance_name.unwrap_or_else(|| manifest.instance_name.clone());
+
+        let report = {
+            let storage = self.game_storage();
+            let _root_lock = storage.try_lock_root(StorageLockMode::Shared)?;
+            if storage.instance_exists(&instance_name, &self.instance_layout)? {
+                bail!("instance '{instance_name}' already exists");
+            }
+            let instance = self.ensure_instance(instance_name.clone()).await?;
+            let imported = instance
+                .try_lock_instance()
+                .and_then(|_instance_lock| import_instance_archive(archive, &instance, options));
+            match imported {
+                Ok(report) => report,
+                Err(error) => {
+                    // Don't leave a half-imported instance behind for the next import to trip on.
+                    if let Err(cleanup) = std::fs::remove_dir_all(&instance.path) {
+                        return Err(error.context(format!(
+                            "remove partially imported instance failed: {}: {cleanup}",
+                            instance.path.display()
+                        )));
+                    }
+                    return Err(error);
+                }
+            }
+        };
+
+        // Shared artifacts missing from this root are fetched by the regular prepare planners.
+        let prepared = self
+            .prepare_instance(PrepareInstanceRequest {
+                instance_name,
+                driver: driver_spec,
+            })
+            .await?;
+        Ok(ImportedInstance { report, prepared })
+    }
+
     pub async fn catalog<R, C: Catalog<Release = R>>(
         &self,
         catalog: C,
-    ) -> Result<HashMap<MinecraftVersionId, Vec<R>>> {
+    ) -> Result<Fetched<HashMap<MinecraftVersionId, Vec<R>>>> {
         catalog.releases().await
     }
 
@@ -157,7 +327,9 @@ where
         &self,
         request: PrepareInstanceRequest,
     ) -> Result<PreparedInstance<L, VL>> {
+        let _root_lock = self.game_storage().try_lock_root(StorageLockMode::Shared)?;
         let instance = self.ensure_instance(request.instance_name).await?;
+        let _instance_lock = instance.try_lock_instance()?;
         let driver_spec = request.driver;
         let prepared_kind = self
             .resolve_driver(driver_spec.descriptor())?
@@ -169,8 +341,50 @@ where
         ))
     }
 
+    /// Prepares the instance while forwarding the events of its own download sessions to
+    /// `on_event`, with progress coalesced and terminal events replayed after a lag.
+    pub async fn prepare_instance_with_events<F>(
+        &self,
+        request: PrepareInstanceRequest,
+        on_event: F,
+    ) -> Result<PreparedInstance<L, VL>>
+    where
+        F: FnMut(DownloadEvent),
+    {
+        let scope = SessionScope::new();
+        let mut events = self.downloader.subscribe();
+        let mut forwarder = ScopedEventForwarder::new(scope.clone(), on_event);
+        let prepare = scope.run(self.prepare_instance(request));
+        tokio::pin!(prepare);
+
+        loop {
+            tokio::select! {
+                result = &mut prepare => {
+                    forwarder.forward_pending(&self.downloader, &mut events, None).await;
+                    forwarder.resync(&self.downloader).await;
+                    return result;
+                }
+                event = events.recv() => match event {
+                    Ok(event) => {
+                        forwarder
+                            .forward_pending(&self.downloader, &mut events, Some(event))
+                            .await;
+                    }
+                    Err(RecvError::Lagged(_)) => forwarder.resync(&self.downloader).await,
+                    Err(RecvError::Closed) => {
+                        let result = prepare.await;
+                        forwarder.resync(&self.downloader).await;
+                        return result;
+                    }
+                },
+            }
+        }
+    }
+
     pub async fn load_instance(&self, instance: Instance) -> Result<PreparedInstance<L, VL>> {
+        let _root_lock = self.game_storage().try_lock_root(StorageLockMode::Shared)?;
         let layout = self.instance(instance.instance_name)?;
+        let _instance_lock = layout.try_lock_instance()?;
         let driver_spec = instance.driver.driver;
         let prepared_kind = self
             .resolve_driver(driver_spec)?
@@ -213,6 +427,43 @@ where
         })
     }
 
+    async fn instance_driver_spec(&self, instance_name: String) -> Result<DriverSpec> {
+        let instance = self
+            .inspect_instance(instance_name.clone())
+            .await?
+            .ok_or_else(|| anyhow!("can't detect the driver of instance '{instance_name}'"))?;
+        if !matches!(
+            instance.driver.driver,
+            FORGE_DRIVER | CLEANROOM_DRIVER | NEOFORGE_DRIVER
+        ) {
+            return DriverSpec::from_installed(&instance.driver);
+        }
+
+        // Inspection reports the loader library version for installer families, which is not
+        // always the installer version, so read it back from the persisted install profile.
+        let prepared = self.load_instance(instance).await?;
+        let (game_version, loader_version) = match &prepared.inner {
+            PreparedInstanceKind::Forge(prepared) => (
+                &prepared.resolved_version.game_version,
+                &prepared.resolved_version.loader_version,
+            ),
+            PreparedInstanceKind::Cleanroom(prepared) => (
+                &prepared.resolved_version.game_version,
+                &prepared.resolved_version.loader_version,
+            ),
+            PreparedInstanceKind::NeoForge(prepared) => (
+                &prepared.resolved_version.game_version,
+                &prepared.resolved_version.loader_version,
+            ),
+            _ => return Err(prepared_variant_mismatch(&prepared)),
+        };
+        DriverSpec::from_parts(
+            prepared.driver.id,
+            game_version.clone(),
+            Some(loader_version.clone()),
+        )
+    }
+
     fn game_storage(&self) -> LauncherGameStorage<L> {
         Storage::new(self.storage_root.clone(), self.root_layout.clone())
     }
@@ -231,17 +482,31 @@ where
             .instance(instance_name, self.instance_layout.clone())
     }
 
+    fn http_source<S: HttpClientSource>(&self, mut source: S) -> S {
+        if let Some(client) = &self.http_client {
+            source.set_client(client.clone());
+        }
+        if let Some(cache) = &self.metadata_cache {
+            source.set_cache(cache.clone());
+        }
+        source
+    }
+
     fn vanilla_driver(&self) -> VanillaDriver {
-        VanillaDriver::new(VanillaSource::default(), self.downloader.clone())
+        VanillaDriver::new(
+            self.http_source(VanillaSource::default()),
+            self.downloader.clone(),
+        )
     }
 
     fn profiled_driver<F>(&self, family: F) -> Result<ProfiledVersionJsonDriver<F>>
     where
         F: ProfiledVersionJsonFamily,
+        F::Source: HttpClientSource,
     {
         Ok(family.clone().build_driver(
-            family.default_source()?,
-            VanillaSource::default(),
+            self.http_source(family.default_source()?),
+            self.http_source(VanillaSource::default()),
             self.downloader.clone(),
         ))
     }
@@ -249,11 +514,11 @@ where
     fn installer_driver<F>(&self) -> InstallerFamilyDriver<F>
     where
         F: InstallerFamilyDriverSpec,
-        F::Source: Default,
+        F::Source: Default + HttpClientSource,
     {
         InstallerFamilyDriver::new(
-            F::Source::default(),
-            VanillaSource::default(),
+            self.http_source(F::Source::default()),
+            self.http_source(VanillaSource::default()),
             self.downloader.clone(),
         )
     }
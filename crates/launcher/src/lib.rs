@@ -7,5 +7,7 @@ mod spec;
 pub use builder::LauncherBuilder;
 pub use launcher::Launcher;
 pub use request::{LaunchOptions, PrepareInstanceRequest};
-pub use result::{Instance, LaunchCommandResult, LaunchedInstance, PreparedInstance};
+pub use result::{
+    ImportedInstance, Instance, LaunchCommandResult, LaunchedInstance, PreparedInstance,
+};
 pub use spec::{DriverSpec, LoaderSpec, VanillaSpec};
@@ -2,6 +2,7 @@ use std::path::{Path, PathBuf};
 
 use elemental_core::{launcher::command::LaunchCommand, runtime::distribution::Distribution};
 use elemental_driver::{
+    archive::InstanceArchiveReport,
     driver::{DriverDescriptor, InstalledDriver},
     drivers::{
         cleanroom::driver::PreparedCleanroomVersion, fabric::prepared::PreparedFabricVersion,
@@ -31,6 +32,15 @@ pub struct Instance {
     pub driver: InstalledDriver,
 }
 
+pub struct ImportedInstance<L = BaseRootLayout, VL = BaseInstanceLayout>
+where
+    L: VersionJsonRootLayout,
+    VL: VersionJsonInstanceLayout,
+{
+    pub report: InstanceArchiveReport,
+    pub prepared: PreparedInstance<L, VL>,
+}
+
 pub struct PreparedInstance<L = BaseRootLayout, VL = BaseInstanceLayout>
 where
     L: VersionJsonRootLayout,
@@ -96,4 +106,22 @@ where
             }
         }
     }
+    /// Whether the metadata resolved for this prepare was served from the http cache because
+    /// its origin was unreachable.
+    pub fn is_stale(&self) -> bool {
+        match &self.inner {
+            PreparedInstanceKind::Vanilla(prepared) => prepared.resolved_version.stale,
+            PreparedInstanceKind::FabricLike(prepared) => prepared.resolved_version.stale,
+            PreparedInstanceKind::Quilt(prepared) => prepared.resolved_version.stale,
+            PreparedInstanceKind::LiteLoader(prepared) => prepared.resolved_version.stale,
+            PreparedInstanceKind::Rift(prepared) => prepared.resolved_version.stale,
+            PreparedInstanceKind::Forge(prepared) => prepared.launch_version.resolved_version.stale,
+            PreparedInstanceKind::Cleanroom(prepared) => {
+                prepared.launch_version.resolved_version.stale
+            }
+            PreparedInstanceKind::NeoForge(prepared) => {
+                prepared.launch_version.resolved_version.stale
+            }
+        }
+    }
 }
@@ -1,9 +1,11 @@
+use anyhow::{Result, anyhow, bail};
 use elemental_core::minecraft::MinecraftVersionId;
+use elemental_driver::archive::InstanceArchiveDriver;
 use elemental_driver::descriptors::{
     BABRIC_DRIVER, CLEANROOM_DRIVER, FABRIC_DRIVER, FORGE_DRIVER, LEGACY_FABRIC_DRIVER,
     LITELOADER_DRIVER, NEOFORGE_DRIVER, QUILT_DRIVER, RIFT_DRIVER, VANILLA_DRIVER,
 };
-use elemental_driver::driver::DriverDescriptor;
+use elemental_driver::driver::{DriverDescriptor, InstalledDriver};
 use elemental_driver::loader_version::LoaderVersionId;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -32,6 +34,57 @@ pub enum DriverSpec {
 }
 
 impl DriverSpec {
+    pub fn from_parts(
+        driver_id: &str,
+        game_version: MinecraftVersionId,
+        loader_version: Option<LoaderVersionId>,
+    ) -> Result<Self> {
+        if driver_id == VANILLA_DRIVER.id {
+            return Ok(Self::Vanilla(VanillaSpec { game_version }));
+        }
+
+        let loader_version = loader_version
+            .ok_or_else(|| anyhow!("driver '{driver_id}' requires a loader version"))?;
+        let loader_spec = LoaderSpec {
+            game_version,
+            loader_version,
+        };
+        Ok(match driver_id {
+            id if id == FABRIC_DRIVER.id => Self::Fabric(loader_spec),
+            id if id == LEGACY_FABRIC_DRIVER.id => Self::LegacyFabric(loader_spec),
+            id if id == BABRIC_DRIVER.id => Self::Babric(loader_spec),
+            id if id == QUILT_DRIVER.id => Self::Quilt(loader_spec),
+            id if id == LITELOADER_DRIVER.id => Self::LiteLoader(loader_spec),
+            id if id == RIFT_DRIVER.id => Self::Rift(loader_spec),
+            id if id == FORGE_DRIVER.id => Self::Forge(loader_spec),
+            id if id == CLEANROOM_DRIVER.id => Self::Cleanroom(loader_spec),
+            id if id == NEOFORGE_DRIVER.id => Self::NeoForge(loader_spec),
+            _ => bail!("unsupported driver '{driver_id}'"),
+        })
+    }
+
+    pub fn from_installed(installed: &InstalledDriver) -> Result<Self> {
+        let game_version = installed.game_version.clone().ok_or_else(|| {
+            anyhow!(
+                "installed driver '{}' has no game version",
+                installed.driver.id
+            )
+        })?;
+        Self::from_parts(
+            installed.driver.id,
+            game_version,
+            installed.driver_version.clone().map(LoaderVersionId::from),
+        )
+    }
+
+    pub fn archive_driver(&self) -> InstanceArchiveDriver {
+        InstanceArchiveDriver {
+            id: self.id().to_owned(),
+            game_version: self.game_version().to_string(),
+            loader_version: self.loader_version().map(ToString::to_string),
+        }
+    }
+
     pub fn descriptor(&self) -> DriverDescriptor {
         match self {
             Self::Vanilla(_) => VANILLA_DRIVER,
@@ -85,3 +138,15 @@ impl DriverSpec {
         }
     }
 }
+
+impl TryFrom<&InstanceArchiveDriver> for DriverSpec {
+    type Error = anyhow::Error;
+
+    fn try_from(driver: &InstanceArchiveDriver) -> Result<Self> {
+        Self::from_parts(
+            &driver.id,
+            MinecraftVersionId::from(driver.game_version.as_str()),
+            driver.loader_version.as_deref().map(LoaderVersionId::from),
+        )
+    }
+}
@@ -24,6 +24,6 @@ pub struct LaunchMetaVersionData {
     #[serde(rename = "releaseTime")]
     pub release_time: DateTime<Utc>,
     pub sha1: String,
-    #[serde(rename = "complianceLevel")]
+    #[serde(rename = "complianceLevel", default)]
     pub compliance_level: usize,
 }
@@ -15,6 +15,12 @@ pub struct PistonMetaAssetIndex {
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct PistonMetaAssetIndexObjects {
     pub objects: HashMap<String, PistonMetaAssetIndexObject>,
+    /// Set by the `legacy` index, assets are read from `assets/virtual/<id>` by name.
+    #[serde(default, rename = "virtual")]
+    pub is_virtual: bool,
+    /// Set by the `pre-1.6` index, assets are read from `<game directory>/resources` by name.
+    #[serde(default)]
+    pub map_to_resources: bool,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
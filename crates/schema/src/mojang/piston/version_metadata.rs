@@ -15,17 +15,24 @@ pub struct PistonMetaData {
     #[serde(rename = "assetIndex")]
     pub asset_index: PistonMetaAssetIndex,
     pub assets: String,
-    #[serde(rename = "complianceLevel")]
+    /// Absent from old-style manifests, which predate the safety compliance levels.
+    #[serde(rename = "complianceLevel", default)]
     pub compliance_level: usize,
     pub downloads: PistonMetaDownloads,
     pub id: String,
-    #[serde(rename = "javaVersion")]
-    pub java_version: PistonMetaJavaVersion,
+    /// Absent from old-style manifests, the launcher has to infer the runtime.
+    #[serde(
+        rename = "javaVersion",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub java_version: Option<PistonMetaJavaVersion>,
     pub libraries: Vec<PistonMetaLibraries>,
+    #[serde(default)]
     pub logging: Option<PistonMetaLogging>,
     #[serde(rename = "mainClass")]
     pub main_class: String,
-    #[serde(rename = "minimumLauncherVersion")]
+    #[serde(rename = "minimumLauncherVersion", default)]
     pub minimum_launcher_version: usize,
     #[serde(rename = "type")]
     pub release_type: String,
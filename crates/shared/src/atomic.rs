@@ -0,0 +1,68 @@
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use anyhow::{Context, Result, bail};
+
+static TEMP_FILE_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+pub async fn write_atomic(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> Result<()> {
+    let path = path.as_ref().to_path_buf();
+    let contents = contents.as_ref().to_vec();
+    tokio::task::spawn_blocking(move || write_atomic_blocking(&path, contents))
+        .await
+        .context("join atomic write task failed")?
+}
+
+pub fn write_atomic_blocking(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> Result<()> {
+    let path = path.as_ref();
+    let parent = path
+        .parent()
+        .with_context(|| format!("atomic write target has no parent: {}", path.display()))?;
+    std::fs::create_dir_all(parent)?;
+
+    let (temp_path, mut file) = create_temp_file(parent, path)?;
+    let written = file
+        .write_all(contents.as_ref())
+        .and_then(|_| file.sync_all())
+        .and_then(|_| std::fs::rename(&temp_path, path));
+    if let Err(error) = written {
+        let _ = std::fs::remove_file(&temp_path);
+        return Err(error).with_context(|| format!("atomic write failed: {}", path.display()));
+    }
+
+    Ok(())
+}
+
+fn create_temp_file(parent: &Path, path: &Path) -> Result<(PathBuf, std::fs::File)> {
+    let file_name = path
+        .file_name()
+        .with_context(|| format!("atomic write target has no file name: {}", path.display()))?
+        .to_string_lossy();
+
+    for _ in 0..32u32 {
+        let suffix = TEMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let temp_path = parent.join(format!(
+            ".{}.tmp.{}.{}",
+            file_name,
+            std::process::id(),
+            suffix
+        ));
+        match std::fs::OpenOptions::new()
+            .create_new(true)
+            .write(true)
+            .open(&temp_path)
+        {
+            Ok(file) => return Ok((temp_path, file)),
+            Err(error) if error.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(error) => return Err(error.into()),
+        }
+    }
+
+    bail!(
+        "failed to allocate temporary file under '{}'",
+        parent.display()
+    )
+}
@@ -1,3 +1,4 @@
+pub mod atomic;
 pub mod loader;
 pub mod migrator;
 pub mod persistor;
@@ -50,4 +51,27 @@ mod shared_test {
             loader.get(|profile| profile.config.user.clone()).await
         ); // NewPlayer
     }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_concurrent_atomic_writes_leave_one_whole_file() {
+        let root = std::env::temp_dir().join(format!("elemental-atomic-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let path = root.join("state.json");
+
+        let writes = (0..16u8).map(|index| {
+            let path = path.clone();
+            tokio::spawn(
+                async move { crate::atomic::write_atomic(&path, vec![index; 64 * 1024]).await },
+            )
+        });
+        for write in writes.collect::<Vec<_>>() {
+            write.await.unwrap().unwrap();
+        }
+
+        let contents = std::fs::read(&path).unwrap();
+        assert_eq!(contents.len(), 64 * 1024);
+        assert!(contents.iter().all(|byte| *byte == contents[0]));
+        assert_eq!(std::fs::read_dir(&root).unwrap().count(), 1);
+        std::fs::remove_dir_all(&root).unwrap();
+    }
 }
@@ -1,4 +1,5 @@
 use crate::{
+    atomic::write_atomic,
     scope::Scope,
     version::{Persistor, VersionControlled},
 };
@@ -6,7 +7,7 @@ use anyhow::Result;
 use serde::Serialize;
 use serde::de::DeserializeOwned;
 use std::{io::ErrorKind, marker::PhantomData, path::PathBuf};
-use tokio::fs::{read_to_string, write};
+use tokio::fs::read_to_string;
 
 pub struct NoPersistor;
 
@@ -37,8 +38,8 @@ impl PersistorIO<String> for AsyncFileStringIO {
     }
 
     async fn write(path: PathBuf, data: String) -> Result<()> {
-        write(&path, data).await?;
-        Ok(())
+        // Readers never observe a partial file, even with concurrent writers.
+        write_atomic(path, data).await
     }
 }
 
@@ -1,5 +1,8 @@
 use anyhow::{Context, Result};
-use dirs::{config_dir, config_local_dir, document_dir, home_dir};
+use dirs::{
+    cache_dir, config_dir, config_local_dir, data_dir, data_local_dir, document_dir, home_dir,
+    state_dir,
+};
 use std::path::PathBuf;
 use tokio::fs::create_dir_all;
 
@@ -11,6 +14,10 @@ pub enum Scope {
     Home,
     Config,
     ConfigLocal,
+    Data,
+    DataLocal,
+    Cache,
+    State,
     Dot,
     Custom(PathBuf),
 }
@@ -22,6 +29,11 @@ impl Scope {
             Scope::Home => home_dir(),
             Scope::Config => config_dir(),
             Scope::ConfigLocal => config_local_dir(),
+            Scope::Data => data_dir(),
+            Scope::DataLocal => data_local_dir(),
+            Scope::Cache => cache_dir(),
+            // Only XDG platforms define a state directory; elsewhere it lives with local data.
+            Scope::State => state_dir().or_else(data_local_dir),
             Scope::Dot => Some(PathBuf::from(".")),
             // May need more pathbuf here
             Scope::Custom(p) => Some(p.clone()),